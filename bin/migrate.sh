@@ -3,4 +3,9 @@
 SCRIPT_DIR=`dirname ${0}`
 cd $SCRIPT_DIR/..
 
+# The unique index on users.email ignores case; stop if existing accounts would collide
+if [ -f kore-douyo.sqlite3 ]; then
+    cargo run --quiet -- normalize-emails || exit 1
+fi
+
 ./bin/sqlite3def --file schema.sql kore-douyo.sqlite3
\ No newline at end of file
@@ -2,21 +2,233 @@ create table users(
     id integer not null primary key autoincrement,
     email varchar not null,
     password varchar not null,
+    email_verified_at datetime,
+    deletion_scheduled_at datetime,
+    disabled_at datetime,
+    locale varchar,
     updated_at datetime not null default current_timestamp,
     created_at datetime not null default current_timestamp
 );
-create unique index users_table_email_index on users (email);
+create unique index users_table_email_index on users (email collate nocase);
 
 create table sessions(
     id integer not null primary key autoincrement,
     user_id integer not null,
     uuid varchar not null,
     csrf_token varchar not null,
+    previous_csrf_token varchar,
+    csrf_token_rotated_at datetime,
     issued_at datetime not null,
     device_info text,
     ip_address varchar,
+    last_seen_at datetime,
+    expires_at datetime,
+    impersonator_user_id integer references users (id) on delete cascade,
+    impersonator_session_id integer,
     created_at datetime not null default current_timestamp,
     updated_at datetime not null default current_timestamp
 );
 create unique index sessions_table_uuid_index on sessions (uuid);
 create index sessions_table_user_id_index on sessions (user_id);
+
+create table password_reset_tokens(
+    id integer not null primary key autoincrement,
+    user_id integer not null,
+    token_hash varchar not null,
+    expires_at datetime not null,
+    used_at datetime,
+    created_at datetime not null default current_timestamp
+);
+create unique index password_reset_tokens_table_token_hash_index on password_reset_tokens (token_hash);
+create index password_reset_tokens_table_user_id_index on password_reset_tokens (user_id);
+
+create table email_verification_tokens(
+    id integer not null primary key autoincrement,
+    user_id integer not null,
+    token_hash varchar not null,
+    new_email varchar,
+    expires_at datetime not null,
+    created_at datetime not null default current_timestamp
+);
+create unique index email_verification_tokens_table_token_hash_index on email_verification_tokens (token_hash);
+create index email_verification_tokens_table_user_id_index on email_verification_tokens (user_id);
+
+create table totp_credentials(
+    id integer not null primary key autoincrement,
+    user_id integer not null,
+    secret varchar not null,
+    confirmed_at datetime,
+    last_used_step integer,
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp
+);
+create unique index totp_credentials_table_user_id_index on totp_credentials (user_id);
+
+create table recovery_codes(
+    id integer not null primary key autoincrement,
+    user_id integer not null,
+    code_hash varchar not null,
+    used_at datetime,
+    created_at datetime not null default current_timestamp
+);
+create index recovery_codes_table_user_id_index on recovery_codes (user_id);
+
+create table two_factor_challenges(
+    id integer not null primary key autoincrement,
+    user_id integer not null,
+    token_hash varchar not null,
+    attempts integer not null default 0,
+    expires_at datetime not null,
+    created_at datetime not null default current_timestamp
+);
+create unique index two_factor_challenges_table_token_hash_index on two_factor_challenges (token_hash);
+
+create table passkey_credentials(
+    id integer not null primary key autoincrement,
+    user_id integer not null,
+    credential_id varchar not null,
+    public_key text not null,
+    passkey text not null,
+    sign_count integer not null default 0,
+    name varchar,
+    last_used_at datetime,
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp
+);
+create unique index passkey_credentials_table_credential_id_index on passkey_credentials (credential_id);
+create index passkey_credentials_table_user_id_index on passkey_credentials (user_id);
+
+create table webauthn_challenges(
+    id integer not null primary key autoincrement,
+    user_id integer not null,
+    ceremony varchar not null,
+    token_hash varchar not null,
+    state text not null,
+    expires_at datetime not null,
+    created_at datetime not null default current_timestamp
+);
+create unique index webauthn_challenges_table_token_hash_index on webauthn_challenges (token_hash);
+
+create table oidc_identities(
+    id integer not null primary key autoincrement,
+    user_id integer not null,
+    provider varchar not null,
+    subject varchar not null,
+    email varchar,
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp
+);
+create unique index oidc_identities_table_provider_subject_index on oidc_identities (provider, subject);
+create index oidc_identities_table_user_id_index on oidc_identities (user_id);
+
+create table oidc_login_states(
+    id integer not null primary key autoincrement,
+    provider varchar not null,
+    state_hash varchar not null,
+    nonce varchar not null,
+    code_verifier varchar not null,
+    expires_at datetime not null,
+    created_at datetime not null default current_timestamp
+);
+create unique index oidc_login_states_table_state_hash_index on oidc_login_states (state_hash);
+
+create table api_tokens(
+    id integer not null primary key autoincrement,
+    user_id integer not null,
+    name varchar not null,
+    prefix varchar not null,
+    token_hash varchar not null,
+    scopes varchar not null,
+    expires_at datetime,
+    last_used_at datetime,
+    created_at datetime not null default current_timestamp
+);
+create unique index api_tokens_table_token_hash_index on api_tokens (token_hash);
+create index api_tokens_table_user_id_index on api_tokens (user_id);
+
+create table login_attempts(
+    id integer not null primary key autoincrement,
+    key varchar not null,
+    failure_count integer not null default 0,
+    last_failed_at datetime not null,
+    locked_until datetime,
+    created_at datetime not null default current_timestamp,
+    updated_at datetime not null default current_timestamp
+);
+create unique index login_attempts_table_key_index on login_attempts (key);
+
+create table roles(
+    id integer not null primary key autoincrement,
+    name varchar not null,
+    description varchar,
+    created_at datetime not null default current_timestamp
+);
+create unique index roles_table_name_index on roles (name);
+
+create table permissions(
+    id integer not null primary key autoincrement,
+    name varchar not null,
+    description varchar,
+    created_at datetime not null default current_timestamp
+);
+create unique index permissions_table_name_index on permissions (name);
+
+create table role_permissions(
+    role_id integer not null references roles (id) on delete cascade,
+    permission_id integer not null references permissions (id) on delete cascade,
+    primary key (role_id, permission_id)
+);
+
+create table user_roles(
+    user_id integer not null references users (id) on delete cascade,
+    role_id integer not null references roles (id) on delete cascade,
+    created_at datetime not null default current_timestamp,
+    primary key (user_id, role_id)
+);
+create index user_roles_table_role_id_index on user_roles (role_id);
+
+create table admin_audit_logs(
+    id integer not null primary key autoincrement,
+    admin_user_id integer references users (id) on delete set null,
+    action varchar not null,
+    target_user_id integer references users (id) on delete set null,
+    details text,
+    ip_address varchar,
+    created_at datetime not null default current_timestamp
+);
+create index admin_audit_logs_table_target_user_id_index on admin_audit_logs (target_user_id);
+
+create table invitations(
+    id integer not null primary key autoincrement,
+    code_hash varchar not null,
+    email varchar,
+    max_uses integer not null default 1,
+    use_count integer not null default 0,
+    expires_at datetime,
+    created_by_user_id integer references users (id) on delete set null,
+    created_at datetime not null default current_timestamp
+);
+create unique index invitations_table_code_hash_index on invitations (code_hash);
+
+create table magic_link_tokens(
+    id integer not null primary key autoincrement,
+    user_id integer not null references users (id) on delete cascade,
+    token_hash varchar not null,
+    binding_hash varchar not null,
+    expires_at datetime not null,
+    created_at datetime not null default current_timestamp
+);
+create unique index magic_link_tokens_table_token_hash_index on magic_link_tokens (token_hash);
+create index magic_link_tokens_table_user_id_index on magic_link_tokens (user_id);
+
+create table auth_events(
+    id integer not null primary key autoincrement,
+    user_id integer references users (id) on delete set null,
+    event_type varchar not null,
+    details varchar,
+    ip_address varchar,
+    user_agent text,
+    created_at datetime not null default current_timestamp
+);
+create index auth_events_table_user_id_index on auth_events (user_id);
+create index auth_events_table_created_at_index on auth_events (created_at);
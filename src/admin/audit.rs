@@ -0,0 +1,83 @@
+use crate::AppState;
+use crate::auth::AuthenticatedUser;
+use crate::auth::client::ClientInfo;
+use crate::auth::errors::AuthResult;
+use crate::extract::Query;
+use crate::models::{AdminAuditLog, AuthEvent};
+use crate::pagination::Page;
+use crate::repositories::{AdminAuditLogRepository, AuthEventRepository};
+use axum::{Extension, Json, extract::State};
+use serde::Deserialize;
+use tracing::{info, instrument};
+
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct AuthEventQuery {
+    pub event_type: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub target_user_id: Option<i64>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+/// 管理者の操作を監査ログに記録する
+pub async fn record(
+    state: &AppState,
+    admin_user_id: i64,
+    client: &ClientInfo,
+    action: &str,
+    target_user_id: Option<i64>,
+    details: Option<&str>,
+) -> AuthResult<()> {
+    AdminAuditLogRepository::new(&state.pool)
+        .create(
+            admin_user_id,
+            action,
+            target_user_id,
+            details,
+            client.ip.map(|ip| ip.to_string()).as_deref(),
+        )
+        .await?;
+    info!(admin_user_id = %admin_user_id, action = %action, target_user_id = ?target_user_id, "Admin action recorded");
+
+    Ok(())
+}
+
+/// 監査ログの一覧（新しい順）
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    Query(query): Query<AuditLogQuery>,
+) -> AuthResult<Json<Vec<AdminAuditLog>>> {
+    let page = Page::new(query.page, query.per_page, DEFAULT_PER_PAGE, MAX_PER_PAGE);
+
+    let logs = AdminAuditLogRepository::new(&state.pool)
+        .find_recent(query.target_user_id, page.limit(), page.offset())
+        .await?;
+
+    Ok(Json(logs))
+}
+
+/// 全ユーザーの認証イベントの一覧（新しい順）。種類で絞り込める
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn auth_events(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    Query(query): Query<AuthEventQuery>,
+) -> AuthResult<Json<Vec<AuthEvent>>> {
+    let page = Page::new(query.page, query.per_page, DEFAULT_PER_PAGE, MAX_PER_PAGE);
+
+    let events = AuthEventRepository::new(&state.pool)
+        .find_recent(query.event_type.as_deref(), page.limit(), page.offset())
+        .await?;
+
+    Ok(Json(events))
+}
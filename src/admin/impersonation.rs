@@ -0,0 +1,177 @@
+use crate::AppState;
+use crate::admin::audit;
+use crate::auth::AuthenticatedUser;
+use crate::auth::client::ClientInfo;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::cookie::set_cookie;
+use crate::extract::Path;
+use crate::models::{
+    ADMIN_ACTION_IMPERSONATE_END, ADMIN_ACTION_IMPERSONATE_START, PERMISSION_USERS_MANAGE, Session,
+};
+use crate::repositories::{RoleRepository, SessionRepository, UserRepository};
+use axum::{
+    Extension, Json,
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use cookie::Cookie;
+use serde::Serialize;
+use tracing::{info, instrument};
+
+#[derive(Debug, Serialize)]
+pub struct EndImpersonationResponse {
+    /// 管理者自身のセッションに戻れた場合はtrue（期限切れの場合は再ログインが必要）
+    pub restored: bool,
+}
+
+/// 管理者として指定したユーザーになりすます。管理者自身のセッションは残しておき、終了時に戻る
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn start(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    Path(id): Path<i64>,
+) -> AuthResult<Response> {
+    let admin_session_id = auth_user.session_id.ok_or(AuthError::SessionRequired)?;
+    if auth_user.impersonator.is_some() || id == auth_user.user.id {
+        return Err(AuthError::PermissionDenied);
+    }
+
+    let target = UserRepository::new(&state.pool)
+        .find_by_id(id)
+        .await?
+        .ok_or(AuthError::UserNotFound)?;
+    if target.disabled_at.is_some() {
+        return Err(AuthError::AccountDisabled);
+    }
+
+    // Impersonating another administrator would be a way around the audit trail
+    if RoleRepository::new(&state.pool)
+        .find_permission_names_by_user_id(target.id)
+        .await?
+        .iter()
+        .any(|permission| permission == PERMISSION_USERS_MANAGE)
+    {
+        return Err(AuthError::PermissionDenied);
+    }
+
+    let session = Session::new(
+        target.id,
+        client.user_agent.clone(),
+        client.ip.map(|ip| ip.to_string()),
+        state.config.auth.impersonation.lifetime(),
+    )
+    .impersonated_by(auth_user.user.id, admin_session_id);
+    SessionRepository::new(&state.pool).create(&session).await?;
+
+    audit::record(
+        &state,
+        auth_user.user.id,
+        &client,
+        ADMIN_ACTION_IMPERSONATE_START,
+        Some(target.id),
+        None,
+    )
+    .await?;
+    info!(target_user_id = %target.id, "Impersonation started");
+
+    let cookie = state.cookies.session_cookie(
+        &session.uuid,
+        session.cookie_max_age(Utc::now(), state.config.auth.session.idle_timeout()),
+    );
+    let mut res = Json(target).into_response();
+    set_cookie(&mut res, cookie);
+    Ok(res)
+}
+
+/// なりすましのセッションを削除して監査ログに記録する。
+/// 管理者自身のセッションが残っていれば、CSRFトークンを新しくして返す
+pub async fn end_impersonation(
+    state: &AppState,
+    session: &Session,
+    client: &ClientInfo,
+) -> AuthResult<Option<Session>> {
+    let impersonator_id = session
+        .impersonator_user_id
+        .ok_or(AuthError::NotImpersonating)?;
+    let session_repo = SessionRepository::new(&state.pool);
+    let now = Utc::now();
+
+    session_repo.delete(session.id).await?;
+    audit::record(
+        state,
+        impersonator_id,
+        client,
+        ADMIN_ACTION_IMPERSONATE_END,
+        Some(session.user_id),
+        None,
+    )
+    .await?;
+    info!(admin_user_id = %impersonator_id, "Impersonation ended");
+
+    // Go back to the administrator's own session if it is still alive
+    let admin_session = match session.impersonator_session_id {
+        Some(id) => {
+            session_repo
+                .find_active_by_id(id, now, state.config.auth.session.idle_timeout())
+                .await?
+        }
+        None => None,
+    }
+    .filter(|admin_session| admin_session.user_id == impersonator_id);
+
+    // The administrator's privileges come back, so their old token is replaced
+    if let Some(admin_session) = &admin_session {
+        session_repo
+            .rotate_csrf_token(admin_session.id, &Session::generate_csrf_token(), now)
+            .await?;
+    }
+
+    Ok(admin_session)
+}
+
+/// なりすましを終了し、管理者自身のセッションに戻る
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn end(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+) -> AuthResult<Response> {
+    if auth_user.impersonator.is_none() {
+        return Err(AuthError::NotImpersonating);
+    }
+    let session = SessionRepository::new(&state.pool)
+        .find_active_by_id(
+            auth_user.session_id.ok_or(AuthError::NotImpersonating)?,
+            Utc::now(),
+            state.config.auth.session.idle_timeout(),
+        )
+        .await?
+        .ok_or(AuthError::NotLogined)?;
+    let admin_session = end_impersonation(&state, &session, &client).await?;
+
+    let mut res = Json(EndImpersonationResponse {
+        restored: admin_session.is_some(),
+    })
+    .into_response();
+    set_cookie(
+        &mut res,
+        restored_session_cookie(&state, admin_session.as_ref()),
+    );
+    Ok(res)
+}
+
+/// なりすまし終了後のCookie。管理者自身のセッションがなければCookieを削除する
+pub fn restored_session_cookie(
+    state: &AppState,
+    admin_session: Option<&Session>,
+) -> Cookie<'static> {
+    match admin_session {
+        Some(admin_session) => state.cookies.session_cookie(
+            &admin_session.uuid,
+            admin_session.cookie_max_age(Utc::now(), state.config.auth.session.idle_timeout()),
+        ),
+        None => state.cookies.session_removal(),
+    }
+}
@@ -0,0 +1,128 @@
+use crate::AppState;
+use crate::admin::audit;
+use crate::auth::AuthenticatedUser;
+use crate::auth::client::ClientInfo;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::token::{expires_in_days, generate_token, hash_token};
+use crate::extract::Path;
+use crate::models::{ADMIN_ACTION_INVITATION_CREATE, ADMIN_ACTION_INVITATION_REVOKE, Invitation};
+use crate::repositories::InvitationRepository;
+use crate::validation::{EXPIRES_IN_DAYS, Validate, ValidatedJson, ValidationErrors};
+use axum::{Extension, Json, extract::State, http::StatusCode};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use tracing::instrument;
+
+/// 招待コード1つで登録できる人数
+const MAX_USES: RangeInclusive<i64> = 1..=10_000;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvitationRequest {
+    /// 指定した場合、このメールアドレスでしか登録できない
+    pub email: Option<String>,
+    /// 使用できる回数（省略時は1回）
+    pub max_uses: Option<i64>,
+    /// 省略時は無期限
+    pub expires_in_days: Option<i64>,
+}
+
+impl Validate for CreateInvitationRequest {
+    fn validate(&mut self, errors: &mut ValidationErrors) {
+        if let Some(email) = &mut self.email {
+            errors.email("email", email);
+        }
+        if let Some(max_uses) = self.max_uses {
+            errors.range("max_uses", max_uses, &MAX_USES, "max_uses_out_of_range");
+        }
+        if let Some(days) = self.expires_in_days {
+            errors.range(
+                "expires_in_days",
+                days,
+                &EXPIRES_IN_DAYS,
+                "expires_in_days_out_of_range",
+            );
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateInvitationResponse {
+    /// 平文の招待コード。この応答でしか取得できない
+    pub code: String,
+    pub invitation: Invitation,
+}
+
+/// 招待コードの発行
+#[instrument(skip(state, auth_user, client, request), fields(user_id = %auth_user.user.id))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    ValidatedJson(request): ValidatedJson<CreateInvitationRequest>,
+) -> AuthResult<Json<CreateInvitationResponse>> {
+    let invitation_repo = InvitationRepository::new(&state.pool);
+
+    let code = generate_token();
+    let expires_at = expires_in_days(request.expires_in_days)?;
+    let id = invitation_repo
+        .create(
+            &hash_token(&code),
+            request.email.as_deref(),
+            request.max_uses.unwrap_or(1),
+            expires_at,
+            Some(auth_user.user.id),
+        )
+        .await?;
+    let invitation = invitation_repo
+        .find_by_id(id)
+        .await?
+        .ok_or(AuthError::InternalError)?;
+
+    audit::record(
+        &state,
+        auth_user.user.id,
+        &client,
+        ADMIN_ACTION_INVITATION_CREATE,
+        None,
+        Some(&format!("invitation_id={}", id)),
+    )
+    .await?;
+
+    Ok(Json(CreateInvitationResponse { code, invitation }))
+}
+
+/// 招待コードの一覧
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+) -> AuthResult<Json<Vec<Invitation>>> {
+    let invitations = InvitationRepository::new(&state.pool).find_all().await?;
+
+    Ok(Json(invitations))
+}
+
+/// 招待コードの取り消し
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn revoke(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    Path(id): Path<i64>,
+) -> AuthResult<StatusCode> {
+    if !InvitationRepository::new(&state.pool).delete(id).await? {
+        return Err(AuthError::InvitationNotFound);
+    }
+
+    audit::record(
+        &state,
+        auth_user.user.id,
+        &client,
+        ADMIN_ACTION_INVITATION_REVOKE,
+        None,
+        Some(&format!("invitation_id={}", id)),
+    )
+    .await?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,4 @@
+pub mod audit;
+pub mod impersonation;
+pub mod invitations;
+pub mod users;
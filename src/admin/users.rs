@@ -0,0 +1,308 @@
+use crate::AppState;
+use crate::admin::audit;
+use crate::auth::AuthenticatedUser;
+use crate::auth::client::ClientInfo;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::events::{self, ActivityQuery};
+use crate::auth::password_reset::send_password_reset_mail;
+use crate::auth::sessions::SessionSummary;
+use crate::extract::{Path, Query};
+use crate::models::{
+    ADMIN_ACTION_DISABLE, ADMIN_ACTION_ENABLE, ADMIN_ACTION_FORCE_LOGOUT,
+    ADMIN_ACTION_PASSWORD_RESET, ADMIN_ACTION_SEARCH, ADMIN_ACTION_VIEW,
+    ADMIN_ACTION_VIEW_ACTIVITY, ADMIN_ACTION_VIEW_SESSIONS, AUTH_EVENT_SESSION_REVOKED, AuthEvent,
+    PERMISSION_USERS_MANAGE, User,
+};
+use crate::pagination::Page;
+use crate::repositories::{AuthEventRepository, RoleRepository, SessionRepository, UserRepository};
+use axum::{Extension, Json, extract::State, http::StatusCode};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+const DEFAULT_PER_PAGE: i64 = 20;
+const MAX_PER_PAGE: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct UserSearchQuery {
+    /// メールアドレスの部分一致
+    pub email: Option<String>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserDetail {
+    #[serde(flatten)]
+    pub user: User,
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ForceLogoutResponse {
+    pub revoked: u64,
+}
+
+async fn find_user(state: &AppState, id: i64) -> AuthResult<User> {
+    UserRepository::new(&state.pool)
+        .find_by_id(id)
+        .await?
+        .ok_or(AuthError::UserNotFound)
+}
+
+/// ユーザーの検索（ページング）
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    Query(query): Query<UserSearchQuery>,
+) -> AuthResult<Json<UserPage>> {
+    let user_repo = UserRepository::new(&state.pool);
+    let email = query.email.unwrap_or_default();
+    let page = Page::new(query.page, query.per_page, DEFAULT_PER_PAGE, MAX_PER_PAGE);
+
+    let users = user_repo
+        .search_by_email(&email, page.limit(), page.offset())
+        .await?;
+    let total = user_repo.count_by_email(&email).await?;
+    audit::record(
+        &state,
+        auth_user.user.id,
+        &client,
+        ADMIN_ACTION_SEARCH,
+        None,
+        Some(&email),
+    )
+    .await?;
+
+    Ok(Json(UserPage {
+        users,
+        total,
+        page: page.number,
+        per_page: page.per_page,
+    }))
+}
+
+/// ユーザーの詳細
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn show(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    Path(id): Path<i64>,
+) -> AuthResult<Json<UserDetail>> {
+    let user = find_user(&state, id).await?;
+    let roles = RoleRepository::new(&state.pool)
+        .find_by_user_id(id)
+        .await?
+        .into_iter()
+        .map(|role| role.name)
+        .collect();
+    audit::record(
+        &state,
+        auth_user.user.id,
+        &client,
+        ADMIN_ACTION_VIEW,
+        Some(id),
+        None,
+    )
+    .await?;
+
+    Ok(Json(UserDetail { user, roles }))
+}
+
+/// ユーザーのログイン中のセッション
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn sessions(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    Path(id): Path<i64>,
+) -> AuthResult<Json<Vec<SessionSummary>>> {
+    find_user(&state, id).await?;
+
+    let sessions = SessionRepository::new(&state.pool)
+        .find_active_by_user_id(id, Utc::now(), state.config.auth.session.idle_timeout())
+        .await?
+        .into_iter()
+        .map(|session| SessionSummary::new(session, auth_user.session_id))
+        .collect();
+    audit::record(
+        &state,
+        auth_user.user.id,
+        &client,
+        ADMIN_ACTION_VIEW_SESSIONS,
+        Some(id),
+        None,
+    )
+    .await?;
+
+    Ok(Json(sessions))
+}
+
+/// ユーザーの認証イベント（新しい順）
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn activity(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    Path(id): Path<i64>,
+    Query(query): Query<ActivityQuery>,
+) -> AuthResult<Json<Vec<AuthEvent>>> {
+    find_user(&state, id).await?;
+    let page = query.page();
+
+    let events = AuthEventRepository::new(&state.pool)
+        .find_by_user_id(id, page.limit(), page.offset())
+        .await?;
+    audit::record(
+        &state,
+        auth_user.user.id,
+        &client,
+        ADMIN_ACTION_VIEW_ACTIVITY,
+        Some(id),
+        None,
+    )
+    .await?;
+
+    Ok(Json(events))
+}
+
+/// ユーザーのセッションをすべて失効させる（強制ログアウト）
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn force_logout(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    Path(id): Path<i64>,
+) -> AuthResult<Json<ForceLogoutResponse>> {
+    find_user(&state, id).await?;
+
+    let revoked = SessionRepository::new(&state.pool)
+        .delete_by_user_id(id)
+        .await?;
+    events::record(
+        &state,
+        AUTH_EVENT_SESSION_REVOKED,
+        Some(id),
+        Some("admin"),
+        &client,
+    )
+    .await?;
+    audit::record(
+        &state,
+        auth_user.user.id,
+        &client,
+        ADMIN_ACTION_FORCE_LOGOUT,
+        Some(id),
+        None,
+    )
+    .await?;
+
+    Ok(Json(ForceLogoutResponse { revoked }))
+}
+
+/// アカウントを無効化する。ログイン中のセッションも失効させる。
+/// 自分自身や、無効化されていない最後の管理者は無効化できない
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn disable(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    Path(id): Path<i64>,
+) -> AuthResult<Json<User>> {
+    let target = find_user(&state, id).await?;
+    if id == auth_user.user.id {
+        return Err(AuthError::AdminLockout);
+    }
+    let role_repo = RoleRepository::new(&state.pool);
+    let target_is_admin = role_repo
+        .find_permission_names_by_user_id(id)
+        .await?
+        .iter()
+        .any(|permission| permission == PERMISSION_USERS_MANAGE);
+    if target_is_admin
+        && target.disabled_at.is_none()
+        && role_repo
+            .count_enabled_users_with_permission(PERMISSION_USERS_MANAGE)
+            .await?
+            <= 1
+    {
+        return Err(AuthError::AdminLockout);
+    }
+    let user_repo = UserRepository::new(&state.pool);
+
+    user_repo.set_disabled(id, Some(Utc::now())).await?;
+    SessionRepository::new(&state.pool)
+        .delete_by_user_id(id)
+        .await?;
+    audit::record(
+        &state,
+        auth_user.user.id,
+        &client,
+        ADMIN_ACTION_DISABLE,
+        Some(id),
+        None,
+    )
+    .await?;
+
+    Ok(Json(user_repo.get_by_id(id).await?))
+}
+
+/// 無効化したアカウントを元に戻す
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn enable(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    Path(id): Path<i64>,
+) -> AuthResult<Json<User>> {
+    find_user(&state, id).await?;
+    let user_repo = UserRepository::new(&state.pool);
+
+    user_repo.set_disabled(id, None).await?;
+    audit::record(
+        &state,
+        auth_user.user.id,
+        &client,
+        ADMIN_ACTION_ENABLE,
+        Some(id),
+        None,
+    )
+    .await?;
+
+    Ok(Json(user_repo.get_by_id(id).await?))
+}
+
+/// パスワード再設定のメールをユーザーに送る
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn password_reset(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    Path(id): Path<i64>,
+) -> AuthResult<StatusCode> {
+    let user = find_user(&state, id).await?;
+
+    send_password_reset_mail(&state, &user).await?;
+    audit::record(
+        &state,
+        auth_user.user.id,
+        &client,
+        ADMIN_ACTION_PASSWORD_RESET,
+        Some(id),
+        None,
+    )
+    .await?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,328 @@
+use crate::AppState;
+use crate::auth::client::ClientInfo;
+use crate::auth::email_verification::send_email_change_mail;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::events;
+use crate::auth::handlers::start_session;
+use crate::auth::middleware::AuthenticatedUser;
+use crate::auth::password_policy;
+use crate::auth::sessions::SessionSummary;
+use crate::cookie::{Cookies, set_cookie};
+use crate::i18n::Locale;
+use crate::mailer::Mail;
+use crate::models::{AUTH_EVENT_PASSWORD_CHANGE, ApiToken, AuthEvent, PasskeyCredential, User};
+use crate::repositories::{
+    ApiTokenRepository, AuthEventRepository, OidcIdentityRepository, PasskeyCredentialRepository,
+    PasswordResetTokenRepository, SessionRepository, TotpCredentialRepository, UserRepository,
+};
+use crate::validation::{Validate, ValidatedJson, ValidationErrors};
+use axum::{
+    Extension, Json,
+    extract::State,
+    http::{
+        StatusCode,
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    },
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument};
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeEmailRequest {
+    pub email: String,
+    pub current_password: String,
+}
+
+impl Validate for ChangeEmailRequest {
+    fn validate(&mut self, errors: &mut ValidationErrors) {
+        errors.email("email", &mut self.email);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+impl Validate for ChangePasswordRequest {}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeLocaleRequest {
+    /// nullの場合は設定を解除し、ブラウザのAccept-Languageに従う
+    pub locale: Option<Locale>,
+}
+
+impl Validate for ChangeLocaleRequest {
+    fn validate(&mut self, _errors: &mut ValidationErrors) {}
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+}
+
+impl Validate for DeleteAccountRequest {}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteAccountResponse {
+    /// アカウントを削除する日時。即座に削除した場合はNone
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedOidcIdentity {
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 個人データのエクスポート。認証情報の秘密（ハッシュや鍵）は含めない
+#[derive(Debug, Serialize)]
+pub struct AccountExport {
+    pub exported_at: DateTime<Utc>,
+    pub profile: User,
+    pub sessions: Vec<SessionSummary>,
+    pub api_tokens: Vec<ApiToken>,
+    pub passkeys: Vec<PasskeyCredential>,
+    pub oidc_identities: Vec<ExportedOidcIdentity>,
+    pub two_factor_enabled: bool,
+    /// ログインやパスワード変更などの認証イベント（保持期間内のもの）
+    pub auth_events: Vec<AuthEvent>,
+}
+
+/// アカウント情報の変更はブラウザのセッションからのみ許可し、現在のパスワードを確認する
+async fn verify_current_password(
+    state: &AppState,
+    auth_user: &AuthenticatedUser,
+    current_password: &str,
+) -> AuthResult<()> {
+    if auth_user.session_id.is_none() {
+        return Err(AuthError::SessionRequired);
+    }
+    if !state
+        .password_hasher
+        .verify(current_password, &auth_user.user.password)
+        .await?
+    {
+        debug!("Current password mismatch");
+        return Err(AuthError::InvalidCredentials);
+    }
+    Ok(())
+}
+
+/// ログイン中のユーザー情報
+#[instrument(skip(auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn me(Extension(auth_user): Extension<AuthenticatedUser>) -> Json<User> {
+    Json(auth_user.user)
+}
+
+/// メールアドレスの変更の申請。新しいアドレスに確認メールを送り、
+/// リンクが使われた時点で切り替える（それまではログインも通知も現在のアドレスのまま）
+#[instrument(skip(state, auth_user, request), fields(user_id = %auth_user.user.id))]
+pub async fn change_email(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    ValidatedJson(request): ValidatedJson<ChangeEmailRequest>,
+) -> AuthResult<StatusCode> {
+    verify_current_password(&state, &auth_user, &request.current_password).await?;
+    let user = &auth_user.user;
+
+    if request.email == user.email {
+        return Ok(StatusCode::OK);
+    }
+    // Addresses are unique regardless of case, so only another account is a conflict
+    if let Some(existing) = UserRepository::new(&state.pool)
+        .find_by_email(&request.email)
+        .await?
+        && existing.id != user.id
+    {
+        return Err(AuthError::EmailAlreadyExists);
+    }
+
+    send_email_change_mail(&state, user.id, &request.email).await?;
+    info!("Email change requested");
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// 表示言語の設定の変更
+#[instrument(skip(state, auth_user, request), fields(user_id = %auth_user.user.id))]
+pub async fn change_locale(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    ValidatedJson(request): ValidatedJson<ChangeLocaleRequest>,
+) -> AuthResult<Json<User>> {
+    let mut user = auth_user.user;
+    user.locale = request.locale.map(|locale| locale.as_str().to_string());
+
+    UserRepository::new(&state.pool)
+        .update_locale(user.id, user.locale.as_deref())
+        .await?;
+    info!(locale = ?user.locale, "Locale changed");
+
+    Ok(Json(user))
+}
+
+/// パスワードの変更。既存のセッションはすべて失効させ、新しいセッションを発行する
+#[instrument(skip(state, auth_user, cookies, client, request), fields(user_id = %auth_user.user.id))]
+pub async fn change_password(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    cookies: Cookies,
+    client: ClientInfo,
+    ValidatedJson(request): ValidatedJson<ChangePasswordRequest>,
+) -> AuthResult<Response> {
+    verify_current_password(&state, &auth_user, &request.current_password).await?;
+    let user_id = auth_user.user.id;
+
+    password_policy::validate(
+        &state.config.auth.password_policy,
+        &request.new_password,
+        &password_policy::email_inputs(&auth_user.user.email),
+    )
+    .await?;
+
+    let password_hash = state.password_hasher.hash(&request.new_password).await?;
+    UserRepository::new(&state.pool)
+        .update_password(user_id, &password_hash)
+        .await?;
+
+    // Outstanding reset links and other devices must not survive a password change
+    PasswordResetTokenRepository::new(&state.pool)
+        .delete_unused_by_user_id(user_id)
+        .await?;
+    SessionRepository::new(&state.pool)
+        .delete_by_user_id(user_id)
+        .await?;
+    info!("Password changed");
+    events::record(
+        &state,
+        AUTH_EVENT_PASSWORD_CHANGE,
+        Some(user_id),
+        None,
+        &client,
+    )
+    .await?;
+
+    start_session(&state, user_id, &client, &cookies).await
+}
+
+/// 退会。猶予期間が設定されている場合は削除を予約し、期間内に再度ログインすると取り消される
+#[instrument(skip(state, auth_user, request), fields(user_id = %auth_user.user.id))]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    ValidatedJson(request): ValidatedJson<DeleteAccountRequest>,
+) -> AuthResult<Response> {
+    verify_current_password(&state, &auth_user, &request.password).await?;
+    let user_repo = UserRepository::new(&state.pool);
+    let user = &auth_user.user;
+
+    let grace_days = state.config.auth.account_deletion_grace_days;
+    let deletion_scheduled_at = if grace_days > 0 {
+        let at = Utc::now() + Duration::days(grace_days);
+        user_repo.schedule_deletion(user.id, at).await?;
+        SessionRepository::new(&state.pool)
+            .delete_by_user_id(user.id)
+            .await?;
+        info!(deletion_scheduled_at = %at, "Account deletion scheduled");
+
+        state
+            .mailer
+            .send(&Mail {
+                to: user.email.clone(),
+                subject: "退会の受付".to_string(),
+                body: format!(
+                    "退会を受け付けました。アカウントは{}日後に削除されます。\nそれまでに再度ログインすると、退会を取り消すことができます。",
+                    grace_days
+                ),
+            })
+            .await?;
+        Some(at)
+    } else {
+        user_repo.delete_with_related(user.id).await?;
+        info!("Account deleted");
+        None
+    };
+
+    let mut res = Json(DeleteAccountResponse {
+        deletion_scheduled_at,
+    })
+    .into_response();
+    set_cookie(&mut res, state.cookies.session_removal());
+    Ok(res)
+}
+
+/// アカウントに紐づく個人データをJSONファイルとしてダウンロードさせる
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn export(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+) -> AuthResult<Response> {
+    auth_user.require_session()?;
+    // Downloading the user's personal data is not part of acting on their behalf
+    if auth_user.impersonator.is_some() {
+        return Err(AuthError::ImpersonationForbidden);
+    }
+    let user_id = auth_user.user.id;
+    let now = Utc::now();
+
+    let sessions = SessionRepository::new(&state.pool)
+        .find_active_by_user_id(user_id, now, state.config.auth.session.idle_timeout())
+        .await?
+        .into_iter()
+        .map(|session| SessionSummary::new(session, auth_user.session_id))
+        .collect();
+    let oidc_identities = OidcIdentityRepository::new(&state.pool)
+        .find_by_user_id(user_id)
+        .await?
+        .into_iter()
+        .map(|identity| ExportedOidcIdentity {
+            provider: identity.provider,
+            subject: identity.subject,
+            email: identity.email,
+            created_at: identity.created_at,
+        })
+        .collect();
+
+    let export = AccountExport {
+        exported_at: now,
+        sessions,
+        api_tokens: ApiTokenRepository::new(&state.pool)
+            .find_by_user_id(user_id)
+            .await?,
+        passkeys: PasskeyCredentialRepository::new(&state.pool)
+            .find_by_user_id(user_id)
+            .await?,
+        oidc_identities,
+        two_factor_enabled: TotpCredentialRepository::new(&state.pool)
+            .find_confirmed_by_user_id(user_id)
+            .await?
+            .is_some(),
+        auth_events: AuthEventRepository::new(&state.pool)
+            .find_all_by_user_id(user_id)
+            .await?,
+        profile: auth_user.user,
+    };
+    let body = serde_json::to_vec_pretty(&export).map_err(|_| AuthError::InternalError)?;
+    info!("Personal data exported");
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/json".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"account-export-{}.json\"",
+                    now.format("%Y%m%d")
+                ),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
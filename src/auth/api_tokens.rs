@@ -0,0 +1,120 @@
+use crate::AppState;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::middleware::AuthenticatedUser;
+use crate::auth::token::{expires_in_days, generate_token, hash_token};
+use crate::extract::Path;
+use crate::models::{API_TOKEN_SCOPES, ApiToken};
+use crate::repositories::ApiTokenRepository;
+use crate::validation::{EXPIRES_IN_DAYS, Validate, ValidatedJson, ValidationErrors};
+use axum::{Extension, Json, extract::State, http::StatusCode};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+/// トークンの先頭に付ける識別子。ログやシークレットスキャンで見分けやすくする
+const TOKEN_PREFIX: &str = "kd_";
+/// 一覧に表示する先頭部分の長さ（TOKEN_PREFIXを含む）
+const VISIBLE_PREFIX_LEN: usize = 11;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    pub expires_in_days: Option<i64>,
+    pub scopes: Option<Vec<String>>,
+}
+
+impl Validate for CreateApiTokenRequest {
+    fn validate(&mut self, errors: &mut ValidationErrors) {
+        if let Some(days) = self.expires_in_days {
+            errors.range(
+                "expires_in_days",
+                days,
+                &EXPIRES_IN_DAYS,
+                "expires_in_days_out_of_range",
+            );
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateApiTokenResponse {
+    /// 平文のトークン。この応答でしか取得できない
+    pub token: String,
+    pub api_token: ApiToken,
+}
+
+/// トークンの発行
+#[instrument(skip(state, auth_user, request), fields(user_id = %auth_user.user.id))]
+pub async fn create(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    ValidatedJson(request): ValidatedJson<CreateApiTokenRequest>,
+) -> AuthResult<Json<CreateApiTokenResponse>> {
+    auth_user.require_session()?;
+    let token_repo = ApiTokenRepository::new(&state.pool);
+
+    let scopes = request
+        .scopes
+        .unwrap_or_else(|| API_TOKEN_SCOPES.iter().map(|s| s.to_string()).collect());
+    if let Some(scope) = scopes
+        .iter()
+        .find(|scope| !API_TOKEN_SCOPES.contains(&scope.as_str()))
+    {
+        return Err(AuthError::InvalidScope(scope.clone()));
+    }
+
+    let token = format!("{}{}", TOKEN_PREFIX, generate_token());
+    let expires_at = expires_in_days(request.expires_in_days)?;
+
+    let id = token_repo
+        .create(
+            auth_user.user.id,
+            &request.name,
+            &token[..VISIBLE_PREFIX_LEN],
+            &hash_token(&token),
+            &scopes.join(" "),
+            expires_at,
+        )
+        .await?;
+    let api_token = token_repo
+        .find_by_id(id)
+        .await?
+        .ok_or(AuthError::InternalError)?;
+    info!(api_token_id = %id, "API token created");
+
+    Ok(Json(CreateApiTokenResponse { token, api_token }))
+}
+
+/// トークンの一覧
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+) -> AuthResult<Json<Vec<ApiToken>>> {
+    auth_user.require_session()?;
+
+    let tokens = ApiTokenRepository::new(&state.pool)
+        .find_by_user_id(auth_user.user.id)
+        .await?;
+
+    Ok(Json(tokens))
+}
+
+/// トークンの失効
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn revoke(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    Path(id): Path<i64>,
+) -> AuthResult<StatusCode> {
+    auth_user.require_session()?;
+
+    if !ApiTokenRepository::new(&state.pool)
+        .delete(auth_user.user.id, id)
+        .await?
+    {
+        return Err(AuthError::ApiTokenNotFound);
+    }
+    info!(api_token_id = %id, "API token revoked");
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,63 @@
+use crate::AppState;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{Extensions, HeaderMap, header::USER_AGENT, request::Parts},
+};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+/// 保存するUser-Agentの最大長
+const MAX_USER_AGENT_LEN: usize = 512;
+
+/// リクエスト元のクライアント情報（セッションの端末情報やログイン試行の記録に使う）
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+/// 信頼するプロキシが付与したX-Forwarded-Forのアドレス。
+/// 先頭側はクライアントが自由に書けるため、末尾から `trusted_hops` 番目を使う
+fn forwarded_for(headers: &HeaderMap, trusted_hops: usize) -> Option<IpAddr> {
+    let entries: Vec<&str> = headers
+        .get_all("X-Forwarded-For")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    let index = entries.len().checked_sub(trusted_hops.max(1))?;
+    entries[index].parse().ok()
+}
+
+impl ClientInfo {
+    /// ミドルウェアなど、extractorを使えない場所から取り出す
+    pub fn from_request(state: &AppState, headers: &HeaderMap, extensions: &Extensions) -> Self {
+        let peer_ip = extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let ip = if state.config.server.trust_proxy_headers {
+            forwarded_for(headers, state.config.server.trusted_proxy_hops).or(peer_ip)
+        } else {
+            peer_ip
+        };
+
+        let user_agent = headers
+            .get(USER_AGENT)
+            .and_then(|header| header.to_str().ok())
+            .map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_LEN).collect());
+
+        Self { ip, user_agent }
+    }
+}
+
+impl FromRequestParts<AppState> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_request(state, &parts.headers, &parts.extensions))
+    }
+}
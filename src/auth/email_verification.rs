@@ -0,0 +1,188 @@
+use crate::AppState;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::token::{generate_token, hash_token};
+use crate::mailer::Mail;
+use crate::models::{EmailVerificationToken, User};
+use crate::repositories::{EmailVerificationTokenRepository, UserRepository};
+use crate::validation::{Validate, ValidatedJson, ValidationErrors};
+use axum::{extract::State, http::StatusCode};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use tracing::{debug, error, info, instrument};
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+impl Validate for VerifyEmailRequest {}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+impl Validate for ResendVerificationRequest {
+    fn validate(&mut self, errors: &mut ValidationErrors) {
+        errors.email("email", &mut self.email);
+    }
+}
+
+/// 確認用トークンを発行して、確認ページのリンクを返す
+async fn issue_link(state: &AppState, user_id: i64, new_email: Option<&str>) -> AuthResult<String> {
+    let token_repo = EmailVerificationTokenRepository::new(&state.pool);
+
+    // Only the latest link of each kind is valid
+    token_repo
+        .delete_by_user_id(user_id, new_email.is_some())
+        .await?;
+
+    let token = generate_token();
+    let ttl = Duration::hours(state.config.auth.email_verification_ttl_hours);
+    token_repo
+        .create(&EmailVerificationToken::new(
+            user_id,
+            hash_token(&token),
+            new_email.map(str::to_string),
+            ttl,
+        ))
+        .await?;
+
+    Ok(format!(
+        "{}/verify-email?token={}",
+        state.config.server.base_url(),
+        token
+    ))
+}
+
+/// 確認用トークンを発行してメールを送信する
+pub async fn send_verification_mail(state: &AppState, user_id: i64, email: &str) -> AuthResult<()> {
+    let link = issue_link(state, user_id, None).await?;
+    state
+        .mailer
+        .send(&Mail {
+            to: email.to_string(),
+            subject: "メールアドレスの確認".to_string(),
+            body: format!(
+                "以下のリンクからメールアドレスを確認してください。\n{}\n\nこのリンクの有効期限は{}時間です。",
+                link, state.config.auth.email_verification_ttl_hours
+            ),
+        })
+        .await?;
+    info!(user_id = %user_id, "Verification mail sent");
+
+    Ok(())
+}
+
+/// メールアドレスの変更を確認するリンクを新しいアドレスに送信する。
+/// リンクが使われるまで `users.email` は変えない
+pub async fn send_email_change_mail(
+    state: &AppState,
+    user_id: i64,
+    new_email: &str,
+) -> AuthResult<()> {
+    let link = issue_link(state, user_id, Some(new_email)).await?;
+    state
+        .mailer
+        .send(&Mail {
+            to: new_email.to_string(),
+            subject: "メールアドレスの変更の確認".to_string(),
+            body: format!(
+                "以下のリンクを開くと、アカウントのメールアドレスがこのアドレスに変更されます。\n{}\n\nこのリンクの有効期限は{}時間です。",
+                link, state.config.auth.email_verification_ttl_hours
+            ),
+        })
+        .await?;
+    info!(user_id = %user_id, "Email change verification mail sent");
+
+    Ok(())
+}
+
+/// トークンを検証してメールアドレスを確認済みにする。
+/// メールアドレスの変更のトークンの場合は、ここで新しいアドレスに切り替える
+#[instrument(skip(state, request))]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    ValidatedJson(request): ValidatedJson<VerifyEmailRequest>,
+) -> AuthResult<StatusCode> {
+    let user_repo = UserRepository::new(&state.pool);
+    let token_repo = EmailVerificationTokenRepository::new(&state.pool);
+
+    let token = token_repo
+        .consume(&hash_token(&request.token), Utc::now())
+        .await?
+        .ok_or_else(|| {
+            debug!("Email verification failed: invalid or expired token");
+            AuthError::InvalidToken
+        })?;
+    let user_id = token.user_id;
+
+    let Some(new_email) = token.new_email else {
+        user_repo.mark_email_verified(user_id).await?;
+        info!(user_id = %user_id, "Email verified");
+        return Ok(StatusCode::OK);
+    };
+
+    // Another account may have taken the address while the link was pending
+    if let Some(existing) = user_repo.find_by_email(&new_email).await?
+        && existing.id != user_id
+    {
+        return Err(AuthError::EmailAlreadyExists);
+    }
+    let user = user_repo.get_by_id(user_id).await?;
+    user_repo.update_email(user_id, &new_email).await?;
+    info!(user_id = %user_id, "Email changed");
+
+    // Let the previous address know, in case the change was not made by its owner
+    state
+        .mailer
+        .send(&Mail {
+            to: user.email,
+            subject: "メールアドレスの変更".to_string(),
+            body: format!(
+                "アカウントのメールアドレスが {} に変更されました。\n心当たりがない場合はパスワードを再設定してください。",
+                new_email
+            ),
+        })
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// 確認メールを再送する。
+/// メールアドレスの登録有無を推測されないよう、未登録や確認済みの場合、
+/// 再送間隔を空けずに要求された場合も、送信せずに同じ200を返す。
+#[instrument(skip(state, request), fields(email = %request.email))]
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    ValidatedJson(request): ValidatedJson<ResendVerificationRequest>,
+) -> AuthResult<StatusCode> {
+    let user_repo = UserRepository::new(&state.pool);
+    let token_repo = EmailVerificationTokenRepository::new(&state.pool);
+
+    let Some(User {
+        id,
+        email,
+        email_verified_at: None,
+        ..
+    }) = user_repo.find_by_email(&request.email).await?
+    else {
+        debug!("Resend skipped: unknown or already verified email");
+        return Ok(StatusCode::OK);
+    };
+
+    let interval = Duration::seconds(state.config.auth.email_verification_resend_interval_seconds);
+    if let Some(last_sent_at) = token_repo.latest_created_at_by_user_id(id).await?
+        && Utc::now() - last_sent_at < interval
+    {
+        debug!(user_id = %id, "Resend skipped: requested too soon");
+        return Ok(StatusCode::OK);
+    }
+
+    // Answer as for an unknown address so that a failed delivery does not reveal the account
+    if let Err(e) = send_verification_mail(&state, id, &email).await {
+        error!(user_id = %id, "Failed to resend verification mail: {}", e);
+    }
+
+    Ok(StatusCode::OK)
+}
@@ -1,9 +1,9 @@
+use crate::auth::password_policy::PasswordViolation;
+use crate::error::AppError;
 use axum::{
-    Json,
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use serde_json::json;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -26,12 +26,93 @@ pub enum AuthError {
     #[error("User not found")]
     UserNotFound,
 
+    #[error("Invalid or expired token")]
+    InvalidToken,
+
+    #[error("Email not verified")]
+    EmailNotVerified,
+
+    #[error("Too many failed attempts, retry after {retry_after_secs} seconds")]
+    TooManyAttempts { retry_after_secs: i64 },
+
+    #[error("Invalid two-factor code")]
+    InvalidTwoFactorCode,
+
+    #[error("Two-factor authentication is already enabled")]
+    TwoFactorAlreadyEnabled,
+
+    #[error("Two-factor authentication is not enrolled")]
+    TwoFactorNotEnrolled,
+
+    #[error("Passkey verification failed: {0}")]
+    PasskeyVerificationFailed(#[from] webauthn_rs::prelude::WebauthnError),
+
+    #[error("Passkey already registered")]
+    PasskeyAlreadyRegistered,
+
+    #[error("Passkey not found")]
+    PasskeyNotFound,
+
+    #[error("API token not found")]
+    ApiTokenNotFound,
+
+    #[error("Session not found")]
+    SessionNotFound,
+
+    #[error("Invalid API token scope: {0}")]
+    InvalidScope(String),
+
+    #[error("Insufficient API token scope")]
+    InsufficientScope,
+
+    #[error("Browser session required")]
+    SessionRequired,
+
+    #[error("Permission denied")]
+    PermissionDenied,
+
+    #[error("Account disabled")]
+    AccountDisabled,
+
+    #[error("Registration closed")]
+    RegistrationClosed,
+
+    #[error("Invalid invitation code")]
+    InvalidInvitation,
+
+    #[error("Invitation not found")]
+    InvitationNotFound,
+
+    #[error("Magic link login is disabled")]
+    MagicLinkDisabled,
+
+    #[error("Not impersonating")]
+    NotImpersonating,
+
+    #[error("Not allowed while impersonating")]
+    ImpersonationForbidden,
+
+    #[error("Would leave no active administrator")]
+    AdminLockout,
+
+    #[error("Password does not meet the policy")]
+    PasswordPolicyViolation(Vec<PasswordViolation>),
+
+    #[error("OIDC provider not found")]
+    OidcProviderNotFound,
+
+    #[error("OIDC authentication failed: {0}")]
+    OidcFailed(String),
+
     #[error("Database error: {0}")]
     DatabaseError(#[from] sqlx::Error),
 
     #[error("Password hashing error: {0}")]
     BcryptError(#[from] bcrypt::BcryptError),
 
+    #[error("Password hashing error: {0}")]
+    PasswordHashError(#[from] argon2::password_hash::Error),
+
     #[error("Repository error: {0}")]
     RepositoryError(#[from] anyhow::Error),
 
@@ -39,32 +120,197 @@ pub enum AuthError {
     InternalError,
 }
 
-impl IntoResponse for AuthError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AuthError::InvalidCredentials => (StatusCode::UNAUTHORIZED, "Invalid credentials"),
-            AuthError::EmailAlreadyExists => (StatusCode::CONFLICT, "Email already exists"),
-            AuthError::NotLogined => (StatusCode::UNAUTHORIZED, "Not Logined"),
-            AuthError::MissingCookieHeader => {
-                (StatusCode::UNAUTHORIZED, "Cookie header not found.")
-            }
-            AuthError::MissingSessionId => (StatusCode::UNAUTHORIZED, "Invalid session id"),
-            AuthError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+impl AuthError {
+    /// エラーコード・ステータス・メッセージの対応表
+    fn parts(&self) -> (&'static str, StatusCode, &'static str) {
+        match self {
+            AuthError::InvalidCredentials => (
+                "invalid_credentials",
+                StatusCode::UNAUTHORIZED,
+                "Invalid credentials",
+            ),
+            AuthError::EmailAlreadyExists => (
+                "email_already_exists",
+                StatusCode::CONFLICT,
+                "Email already exists",
+            ),
+            AuthError::NotLogined => ("not_logged_in", StatusCode::UNAUTHORIZED, "Not Logined"),
+            AuthError::MissingCookieHeader => (
+                "missing_cookie",
+                StatusCode::UNAUTHORIZED,
+                "Cookie header not found.",
+            ),
+            AuthError::MissingSessionId => (
+                "missing_session_id",
+                StatusCode::UNAUTHORIZED,
+                "Invalid session id",
+            ),
+            AuthError::UserNotFound => ("user_not_found", StatusCode::NOT_FOUND, "User not found"),
+            AuthError::InvalidToken => (
+                "invalid_token",
+                StatusCode::BAD_REQUEST,
+                "Invalid or expired token",
+            ),
+            AuthError::EmailNotVerified => (
+                "email_not_verified",
+                StatusCode::FORBIDDEN,
+                "Email not verified",
+            ),
+            AuthError::TooManyAttempts { .. } => (
+                "too_many_attempts",
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many failed attempts. Please try again later.",
+            ),
+            AuthError::InvalidTwoFactorCode => (
+                "invalid_two_factor_code",
+                StatusCode::UNAUTHORIZED,
+                "Invalid two-factor code",
+            ),
+            AuthError::TwoFactorAlreadyEnabled => (
+                "two_factor_already_enabled",
+                StatusCode::CONFLICT,
+                "Two-factor authentication is already enabled",
+            ),
+            AuthError::TwoFactorNotEnrolled => (
+                "two_factor_not_enrolled",
+                StatusCode::BAD_REQUEST,
+                "Two-factor authentication is not enrolled",
+            ),
+            AuthError::PasskeyVerificationFailed(_) => (
+                "passkey_verification_failed",
+                StatusCode::UNAUTHORIZED,
+                "Passkey verification failed",
+            ),
+            AuthError::PasskeyAlreadyRegistered => (
+                "passkey_already_registered",
+                StatusCode::CONFLICT,
+                "Passkey already registered",
+            ),
+            AuthError::PasskeyNotFound => (
+                "passkey_not_found",
+                StatusCode::NOT_FOUND,
+                "Passkey not found",
+            ),
+            AuthError::ApiTokenNotFound => (
+                "api_token_not_found",
+                StatusCode::NOT_FOUND,
+                "API token not found",
+            ),
+            AuthError::SessionNotFound => (
+                "session_not_found",
+                StatusCode::NOT_FOUND,
+                "Session not found",
+            ),
+            AuthError::InvalidScope(_) => (
+                "invalid_scope",
+                StatusCode::BAD_REQUEST,
+                "Invalid API token scope",
+            ),
+            AuthError::InsufficientScope => (
+                "insufficient_scope",
+                StatusCode::FORBIDDEN,
+                "Insufficient API token scope",
+            ),
+            AuthError::SessionRequired => (
+                "session_required",
+                StatusCode::FORBIDDEN,
+                "Browser session required",
+            ),
+            AuthError::PermissionDenied => (
+                "permission_denied",
+                StatusCode::FORBIDDEN,
+                "Permission denied",
+            ),
+            AuthError::AccountDisabled => (
+                "account_disabled",
+                StatusCode::FORBIDDEN,
+                "Account disabled",
+            ),
+            AuthError::RegistrationClosed => (
+                "registration_closed",
+                StatusCode::FORBIDDEN,
+                "Registration closed",
+            ),
+            AuthError::InvalidInvitation => (
+                "invalid_invitation",
+                StatusCode::BAD_REQUEST,
+                "Invalid invitation code",
+            ),
+            AuthError::InvitationNotFound => (
+                "invitation_not_found",
+                StatusCode::NOT_FOUND,
+                "Invitation not found",
+            ),
+            AuthError::MagicLinkDisabled => (
+                "magic_link_disabled",
+                StatusCode::NOT_FOUND,
+                "Magic link login is disabled",
+            ),
+            AuthError::NotImpersonating => (
+                "not_impersonating",
+                StatusCode::BAD_REQUEST,
+                "Not impersonating",
+            ),
+            AuthError::ImpersonationForbidden => (
+                "impersonation_forbidden",
+                StatusCode::FORBIDDEN,
+                "Not allowed while impersonating",
+            ),
+            AuthError::AdminLockout => (
+                "admin_lockout",
+                StatusCode::CONFLICT,
+                "Would leave no active administrator",
+            ),
+            AuthError::PasswordPolicyViolation(_) => (
+                "password_policy_violation",
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Password does not meet the policy",
+            ),
+            AuthError::OidcProviderNotFound => (
+                "oidc_provider_not_found",
+                StatusCode::NOT_FOUND,
+                "OIDC provider not found",
+            ),
+            AuthError::OidcFailed(_) => (
+                "oidc_failed",
+                StatusCode::UNAUTHORIZED,
+                "OIDC authentication failed",
+            ),
             AuthError::DatabaseError(_)
             | AuthError::BcryptError(_)
-            | AuthError::RepositoryError(_) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
-            }
-            AuthError::InternalError => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
-            }
-        };
-
-        let body = Json(json!({
-            "error": error_message
-        }));
-
-        (status, body).into_response()
+            | AuthError::PasswordHashError(_)
+            | AuthError::RepositoryError(_)
+            | AuthError::InternalError => (
+                "internal_error",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            ),
+        }
+    }
+
+    /// クライアントが判別に使うエラーコード。変更しないこと
+    pub fn code(&self) -> &'static str {
+        self.parts().0
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.parts().1
+    }
+
+    /// クライアントに返すメッセージ
+    pub fn message(&self) -> &'static str {
+        self.parts().2
+    }
+
+    /// サーバ内部の失敗かどうか（原因はログにのみ出力する）
+    pub fn is_internal(&self) -> bool {
+        self.status() == StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        AppError::from(self).into_response()
     }
 }
 
@@ -0,0 +1,66 @@
+use crate::AppState;
+use crate::auth::client::ClientInfo;
+use crate::auth::errors::AuthResult;
+use crate::auth::middleware::AuthenticatedUser;
+use crate::extract::Query;
+use crate::models::AuthEvent;
+use crate::pagination::Page;
+use crate::repositories::AuthEventRepository;
+use axum::{Extension, Json, extract::State};
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::{debug, instrument};
+
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+impl ActivityQuery {
+    pub fn page(&self) -> Page {
+        Page::new(self.page, self.per_page, DEFAULT_PER_PAGE, MAX_PER_PAGE)
+    }
+}
+
+/// 認証イベントを記録する
+pub async fn record(
+    state: &AppState,
+    event_type: &str,
+    user_id: Option<i64>,
+    details: Option<&str>,
+    client: &ClientInfo,
+) -> AuthResult<()> {
+    AuthEventRepository::new(&state.pool)
+        .create(
+            user_id,
+            event_type,
+            details,
+            client.ip.map(|ip| ip.to_string()).as_deref(),
+            client.user_agent.as_deref(),
+            Utc::now(),
+        )
+        .await?;
+    debug!(event_type = %event_type, user_id = ?user_id, "Auth event recorded");
+
+    Ok(())
+}
+
+/// ログイン中のユーザー自身の認証イベント（新しい順）
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn activity(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    Query(query): Query<ActivityQuery>,
+) -> AuthResult<Json<Vec<AuthEvent>>> {
+    let page = query.page();
+
+    let events = AuthEventRepository::new(&state.pool)
+        .find_by_user_id(auth_user.user.id, page.limit(), page.offset())
+        .await?;
+
+    Ok(Json(events))
+}
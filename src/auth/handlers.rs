@@ -1,14 +1,24 @@
 use crate::AppState;
+use crate::admin::impersonation::{end_impersonation, restored_session_cookie};
+use crate::auth::client::ClientInfo;
+use crate::auth::email_verification::send_verification_mail;
 use crate::auth::errors::{AuthError, AuthResult};
-use crate::models::Session;
-use crate::repositories::{SessionRepository, UserRepository};
-use axum::response::IntoResponse;
-use axum::{
-    Json,
-    extract::State,
-    http::{StatusCode, header::SET_COOKIE},
+use crate::auth::events;
+use crate::auth::password_policy;
+use crate::auth::throttle;
+use crate::auth::token::hash_token;
+use crate::auth::two_factor::start_two_factor_challenge;
+use crate::config::RegistrationMode;
+use crate::cookie::{Cookies, set_cookie};
+use crate::models::{
+    AUTH_EVENT_LOGIN_FAILURE, AUTH_EVENT_LOGIN_SUCCESS, AUTH_EVENT_LOGOUT, AUTH_EVENT_REGISTER,
+    Session,
 };
-use bcrypt::{DEFAULT_COST, hash, verify};
+use crate::repositories::{SessionRepository, TotpCredentialRepository, UserRepository};
+use crate::validation::{Validate, ValidatedJson, ValidationErrors};
+use axum::response::{IntoResponse, Response};
+use axum::{extract::State, http::StatusCode};
+use chrono::Utc;
 use serde::Deserialize;
 use tracing::{debug, info, instrument};
 
@@ -16,6 +26,14 @@ use tracing::{debug, info, instrument};
 pub struct RegisterRequest {
     pub email: String,
     pub password: String,
+    /// 招待制（`registration = "invite"`）の場合に必要
+    pub invitation_code: Option<String>,
+}
+
+impl Validate for RegisterRequest {
+    fn validate(&mut self, errors: &mut ValidationErrors) {
+        errors.email("email", &mut self.email);
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -24,13 +42,74 @@ pub struct LoginRequest {
     pub password: String,
 }
 
-#[instrument(skip(state, request), fields(email = %request.email), )]
+impl Validate for LoginRequest {
+    fn validate(&mut self, errors: &mut ValidationErrors) {
+        errors.email("email", &mut self.email);
+    }
+}
+
+/// セッションを作成し、セッションIDをCookieに書き込んだレスポンスを返す
+pub async fn start_session(
+    state: &AppState,
+    user_id: i64,
+    client: &ClientInfo,
+    cookies: &Cookies,
+) -> AuthResult<Response> {
+    let user_repo = UserRepository::new(&state.pool);
+    let session_repo = SessionRepository::new(&state.pool);
+    let session_config = &state.config.auth.session;
+
+    // Every login method ends here, so disabled accounts are refused in one place
+    if user_repo.get_by_id(user_id).await?.disabled_at.is_some() {
+        debug!(user_id = %user_id, "Login refused: account disabled");
+        return Err(AuthError::AccountDisabled);
+    }
+
+    // A fresh session replaces this browser's previous one, so its CSRF token stops working too
+    if let Some(previous) = state.cookies.session_id(cookies) {
+        session_repo.delete_by_uuid(&previous).await?;
+    }
+
+    // Create session
+    let session = Session::new(
+        user_id,
+        client.user_agent.clone(),
+        client.ip.map(|ip| ip.to_string()),
+        session_config.absolute_lifetime(),
+    );
+    let session_uuid = session.get_session_uuid().to_string();
+    session_repo.create(&session).await?;
+
+    // Signing in again during the grace period withdraws the deletion request
+    if user_repo.cancel_deletion(user_id).await? {
+        info!(user_id = %user_id, "Account deletion cancelled");
+    }
+
+    // Set cookie with session UUID
+    let cookie = state.cookies.session_cookie(
+        &session_uuid,
+        session.cookie_max_age(Utc::now(), session_config.idle_timeout()),
+    );
+
+    let mut res = (StatusCode::OK).into_response();
+    set_cookie(&mut res, cookie);
+    Ok(res)
+}
+
+#[instrument(skip(state, cookies, request), fields(email = %request.email), )]
 pub async fn register(
     State(state): State<AppState>,
-    Json(request): Json<RegisterRequest>,
+    cookies: Cookies,
+    client: ClientInfo,
+    ValidatedJson(request): ValidatedJson<RegisterRequest>,
 ) -> AuthResult<impl IntoResponse> {
     let user_repo = UserRepository::new(&state.pool);
-    let session_repo = SessionRepository::new(&state.pool);
+    let registration = state.config.auth.registration;
+
+    if registration == RegistrationMode::Closed {
+        debug!("Registration failed: registration is closed");
+        return Err(AuthError::RegistrationClosed);
+    }
 
     // Check if user already exists
     if user_repo.exists_by_email(&request.email).await? {
@@ -38,7 +117,14 @@ pub async fn register(
         return Err(AuthError::EmailAlreadyExists);
     }
 
-    let password_hash = hash(&request.password, DEFAULT_COST)?;
+    password_policy::validate(
+        &state.config.auth.password_policy,
+        &request.password,
+        &password_policy::email_inputs(&request.email),
+    )
+    .await?;
+
+    let password_hash = state.password_hasher.hash(&request.password).await?;
 
     //let user_id = match user_repo.create(&request.email, &password_hash).await {
     //    Ok(user_id) => user_id,
@@ -47,100 +133,169 @@ pub async fn register(
     //        return Err(AuthError::InternalError);
     //    }
     //};
-    let user_id = user_repo.create(&request.email, &password_hash).await?;
+    let user_id = if registration == RegistrationMode::Invite {
+        let code = request
+            .invitation_code
+            .as_deref()
+            .ok_or(AuthError::InvalidInvitation)?;
+        user_repo
+            .create_with_invitation(
+                &request.email,
+                &password_hash,
+                &hash_token(code),
+                Utc::now(),
+            )
+            .await?
+            .ok_or_else(|| {
+                debug!("Registration failed: invalid invitation code");
+                AuthError::InvalidInvitation
+            })?
+    } else {
+        user_repo.create(&request.email, &password_hash).await?
+    };
     info!(user_id = %user_id, "User created successfully");
+    events::record(&state, AUTH_EVENT_REGISTER, Some(user_id), None, &client).await?;
 
-    // Create session
-    let session = Session::new(
-        user_id, None, // TODO: Extract device info from headers
-        None, // TODO: Extract IP address from request
-    );
-    let session_uuid = session.get_session_uuid().to_string();
-    session_repo.create(&session).await?;
-
-    // Set cookie with session UUID (30 days = 2592000 seconds)
-    let cookie = format!(
-        "session_id={}; HttpOnly; Secure; SameSite=Lax; Path=/; Max-Age=2592000",
-        session_uuid
-    );
+    send_verification_mail(&state, user_id, &request.email).await?;
 
-    let mut res = (StatusCode::OK).into_response();
-    res.headers_mut()
-        .insert(SET_COOKIE, cookie.parse().unwrap());
-    Ok(res)
+    start_session(&state, user_id, &client, &cookies).await
 }
 
-#[instrument(skip(state, request), fields(email = %request.email))]
+#[instrument(skip(state, cookies, request), fields(email = %request.email))]
 pub async fn login(
     State(state): State<AppState>,
-    Json(request): Json<LoginRequest>,
+    cookies: Cookies,
+    client: ClientInfo,
+    ValidatedJson(request): ValidatedJson<LoginRequest>,
 ) -> AuthResult<impl IntoResponse> {
     let user_repo = UserRepository::new(&state.pool);
-    let session_repo = SessionRepository::new(&state.pool);
+    let ip = client.ip;
+
+    throttle::check(&state, &request.email, ip).await?;
 
     // Find user by email
-    let user = user_repo
-        .find_by_email(&request.email)
-        .await?
-        .ok_or_else(|| {
-            debug!("Login failed: user not found");
-            AuthError::InvalidCredentials
-        })?;
+    let Some(user) = user_repo.find_by_email(&request.email).await? else {
+        debug!("Login failed: user not found");
+        throttle::record_failure(&state, &request.email, ip).await?;
+        events::record(
+            &state,
+            AUTH_EVENT_LOGIN_FAILURE,
+            None,
+            Some("unknown_email"),
+            &client,
+        )
+        .await?;
+        return Err(AuthError::InvalidCredentials);
+    };
 
     // Verify password
-    if !verify(&request.password, &user.password)? {
+    if !state
+        .password_hasher
+        .verify(&request.password, &user.password)
+        .await?
+    {
         debug!("Login failed: invalid password");
+        throttle::record_failure(&state, &request.email, ip).await?;
+        events::record(
+            &state,
+            AUTH_EVENT_LOGIN_FAILURE,
+            Some(user.id),
+            Some("invalid_password"),
+            &client,
+        )
+        .await?;
         return Err(AuthError::InvalidCredentials);
     }
 
-    // Create session
-    let session = Session::new(
-        user.id, None, // TODO: Extract device info from headers
-        None, // TODO: Extract IP address from request
-    );
-    let session_uuid = session.get_session_uuid().to_string();
-    session_repo.create(&session).await?;
+    // Upgrade legacy bcrypt hashes and outdated Argon2 parameters
+    if state.password_hasher.needs_rehash(&user.password) {
+        let password_hash = state.password_hasher.hash(&request.password).await?;
+        user_repo.update_password(user.id, &password_hash).await?;
+        info!(user_id = %user.id, "Password hash upgraded");
+    }
 
-    // Set cookie with session UUID (30 days = 2592000 seconds)
-    let cookie = format!(
-        "session_id={}; HttpOnly; Secure; SameSite=Lax; Path=/; Max-Age=2592000",
-        session_uuid
-    );
+    if user.disabled_at.is_some() {
+        debug!("Login refused: account disabled");
+        events::record(
+            &state,
+            AUTH_EVENT_LOGIN_FAILURE,
+            Some(user.id),
+            Some("account_disabled"),
+            &client,
+        )
+        .await?;
+        return Err(AuthError::AccountDisabled);
+    }
 
-    let mut res = (StatusCode::OK).into_response();
-    res.headers_mut()
-        .insert(SET_COOKIE, cookie.parse().unwrap());
+    // Require the second factor before issuing a session. The failure count is only cleared
+    // once it succeeds, so the password alone cannot buy unlimited guesses at the code
+    if TotpCredentialRepository::new(&state.pool)
+        .find_confirmed_by_user_id(user.id)
+        .await?
+        .is_some()
+    {
+        return start_two_factor_challenge(&state, user.id).await;
+    }
+
+    throttle::reset(&state, &request.email).await?;
+    let res = start_session(&state, user.id, &client, &cookies).await?;
+    events::record(
+        &state,
+        AUTH_EVENT_LOGIN_SUCCESS,
+        Some(user.id),
+        Some("password"),
+        &client,
+    )
+    .await?;
     Ok(res)
 }
 
-#[instrument(skip(state))]
+#[instrument(skip(state, client, cookies))]
 pub async fn logout(
     State(state): State<AppState>,
-    req: axum::extract::Request,
+    client: ClientInfo,
+    cookies: Cookies,
 ) -> AuthResult<impl IntoResponse> {
     let session_repo = SessionRepository::new(&state.pool);
 
     // Extract session ID from cookie
-    if let Some(cookie_header) = req.headers().get("Cookie") {
-        if let Ok(cookie_str) = cookie_header.to_str() {
-            // Parse cookies to find session_id
-            for cookie_pair in cookie_str.split(';') {
-                let cookie_pair = cookie_pair.trim();
-                if let Some(session_id) = cookie_pair.strip_prefix("session_id=") {
-                    // Delete session from database
-                    session_repo.delete_by_uuid(session_id).await?;
-                    break;
-                }
+    if let Some(session_id) = state.cookies.session_id(&cookies) {
+        if let Some(session) = session_repo
+            .find_active_by_uuid(
+                &session_id,
+                Utc::now(),
+                state.config.auth.session.idle_timeout(),
+            )
+            .await?
+        {
+            // Logging out of an impersonation ends it and goes back to the administrator
+            if session.impersonator_user_id.is_some() {
+                let admin_session = end_impersonation(&state, &session, &client).await?;
+                let mut res = (StatusCode::OK).into_response();
+                set_cookie(
+                    &mut res,
+                    restored_session_cookie(&state, admin_session.as_ref()),
+                );
+                return Ok(res);
             }
+
+            events::record(
+                &state,
+                AUTH_EVENT_LOGOUT,
+                Some(session.user_id),
+                None,
+                &client,
+            )
+            .await?;
         }
+
+        // Delete session from database
+        session_repo.delete_by_uuid(&session_id).await?;
     }
 
     // Clear cookie by setting it to expire immediately
-    let clear_cookie = "session_id=; HttpOnly; Secure; SameSite=Lax; Path=/; Max-Age=0";
-
     let mut res = (StatusCode::OK).into_response();
-    res.headers_mut()
-        .insert(SET_COOKIE, clear_cookie.parse().unwrap());
+    set_cookie(&mut res, state.cookies.session_removal());
 
     Ok(res)
 }
@@ -0,0 +1,221 @@
+use crate::AppState;
+use crate::auth::client::ClientInfo;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::events;
+use crate::auth::handlers::start_session;
+use crate::auth::throttle;
+use crate::auth::token::{generate_token, hash_token};
+use crate::auth::two_factor::start_two_factor_challenge;
+use crate::cookie::{Cookies, set_cookie};
+use crate::mailer::Mail;
+use crate::models::{AUTH_EVENT_LOGIN_FAILURE, AUTH_EVENT_LOGIN_SUCCESS, MagicLinkToken};
+use crate::repositories::{MagicLinkTokenRepository, TotpCredentialRepository, UserRepository};
+use crate::validation::{Validate, ValidatedJson, ValidationErrors};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use tracing::{debug, error, info, instrument};
+
+/// リンクを要求したブラウザを識別するCookie
+const BINDING_COOKIE_NAME: &str = "magic_link_binding";
+const BINDING_COOKIE_PATH: &str = "/api/auth/magic-link";
+
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkRequest {
+    pub email: String,
+}
+
+impl Validate for MagicLinkRequest {
+    fn validate(&mut self, errors: &mut ValidationErrors) {
+        errors.email("email", &mut self.email);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkVerifyRequest {
+    pub token: String,
+}
+
+impl Validate for MagicLinkVerifyRequest {}
+
+fn ensure_enabled(state: &AppState) -> AuthResult<()> {
+    if !state.config.auth.magic_link.enabled {
+        return Err(AuthError::MagicLinkDisabled);
+    }
+    Ok(())
+}
+
+/// 再送間隔の間に送ったリンクがあるか
+async fn sent_recently(
+    token_repo: &MagicLinkTokenRepository<'_>,
+    user_id: i64,
+    resend_interval_seconds: i64,
+) -> AuthResult<bool> {
+    Ok(token_repo
+        .latest_created_at_by_user_id(user_id)
+        .await?
+        .is_some_and(|last_sent_at| {
+            Utc::now() - last_sent_at < Duration::seconds(resend_interval_seconds)
+        }))
+}
+
+/// ログイン用のリンクをメールで送信する。
+/// メールアドレスの登録有無を推測されないよう、ユーザーが存在しない場合や
+/// 再送間隔を空けずに要求された場合も、送信せずに同じ200を返す。
+#[instrument(skip(state, cookies, client, request), fields(email = %request.email))]
+pub async fn request_link(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    client: ClientInfo,
+    ValidatedJson(request): ValidatedJson<MagicLinkRequest>,
+) -> AuthResult<Response> {
+    ensure_enabled(&state)?;
+    throttle::check(&state, &request.email, client.ip).await?;
+    let config = &state.config.auth.magic_link;
+    let token_repo = MagicLinkTokenRepository::new(&state.pool);
+
+    // The link only works in the browser that holds this value. The browser keeps its value
+    // so that a link sent earlier still works when a repeated request is dropped
+    let binding = cookies
+        .get(BINDING_COOKIE_NAME)
+        .filter(|binding| !binding.is_empty())
+        .map_or_else(generate_token, str::to_string);
+
+    match UserRepository::new(&state.pool)
+        .find_by_email(&request.email)
+        .await?
+    {
+        Some(user)
+            if user.disabled_at.is_none()
+                && !sent_recently(&token_repo, user.id, config.resend_interval_seconds).await? =>
+        {
+            // Only the latest link is valid
+            token_repo.delete_by_user_id(user.id).await?;
+
+            let token = generate_token();
+            token_repo
+                .create(&MagicLinkToken::new(
+                    user.id,
+                    hash_token(&token),
+                    hash_token(&binding),
+                    Duration::minutes(config.ttl_minutes),
+                ))
+                .await?;
+
+            let link = format!(
+                "{}/magic-link?token={}",
+                state.config.server.base_url(),
+                token
+            );
+            let sent = state
+                .mailer
+                .send(&Mail {
+                    to: user.email.clone(),
+                    subject: "ログイン用リンク".to_string(),
+                    body: format!(
+                        "以下のリンクからログインしてください。\n{}\n\nこのリンクの有効期限は{}分で、リンクを要求したブラウザでのみ使用できます。",
+                        link, config.ttl_minutes
+                    ),
+                })
+                .await;
+            // Answer as for an unknown address so that a failed delivery does not reveal the account
+            match sent {
+                Ok(()) => info!(user_id = %user.id, "Magic link sent"),
+                Err(e) => error!(user_id = %user.id, "Failed to send magic link: {}", e),
+            }
+        }
+        _ => debug!("Magic link not sent: unknown or disabled email, or requested too soon"),
+    }
+
+    let mut res = (StatusCode::OK).into_response();
+    set_cookie(
+        &mut res,
+        state
+            .cookies
+            .builder(BINDING_COOKIE_NAME, binding)
+            .path(BINDING_COOKIE_PATH)
+            .max_age(cookie::time::Duration::minutes(config.ttl_minutes))
+            .build(),
+    );
+    Ok(res)
+}
+
+/// リンクのトークンを検証してログインする。二要素認証が有効な場合は二要素目を要求する
+#[instrument(skip(state, cookies, client, request))]
+pub async fn verify(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    client: ClientInfo,
+    ValidatedJson(request): ValidatedJson<MagicLinkVerifyRequest>,
+) -> AuthResult<Response> {
+    ensure_enabled(&state)?;
+    throttle::check_ip(&state, client.ip).await?;
+    let token_repo = MagicLinkTokenRepository::new(&state.pool);
+    let user_repo = UserRepository::new(&state.pool);
+    let token_hash = hash_token(&request.token);
+
+    let binding = cookies.get(BINDING_COOKIE_NAME).unwrap_or_default();
+    let Some(user_id) = token_repo
+        .consume(&token_hash, &hash_token(binding), Utc::now())
+        .await?
+    else {
+        debug!("Magic link login failed: invalid, expired or forwarded token");
+        match token_repo.find_user_id_by_hash(&token_hash).await? {
+            // A valid link opened in another browser counts against its owner too
+            Some(owner_id) => {
+                let owner = user_repo.get_by_id(owner_id).await?;
+                throttle::record_failure(&state, &owner.email, client.ip).await?;
+                events::record(
+                    &state,
+                    AUTH_EVENT_LOGIN_FAILURE,
+                    Some(owner_id),
+                    Some("magic_link_rejected"),
+                    &client,
+                )
+                .await?;
+            }
+            None => throttle::record_ip_failure(&state, client.ip).await?,
+        }
+        return Err(AuthError::InvalidToken);
+    };
+
+    let user = user_repo.get_by_id(user_id).await?;
+    info!(user_id = %user.id, "Magic link verified");
+
+    if user.disabled_at.is_some() {
+        debug!("Login refused: account disabled");
+        return Err(AuthError::AccountDisabled);
+    }
+
+    // Require the second factor before issuing a session
+    let mut res = if TotpCredentialRepository::new(&state.pool)
+        .find_confirmed_by_user_id(user.id)
+        .await?
+        .is_some()
+    {
+        start_two_factor_challenge(&state, user.id).await?
+    } else {
+        throttle::reset(&state, &user.email).await?;
+        let res = start_session(&state, user.id, &client, &cookies).await?;
+        events::record(
+            &state,
+            AUTH_EVENT_LOGIN_SUCCESS,
+            Some(user.id),
+            Some("magic_link"),
+            &client,
+        )
+        .await?;
+        res
+    };
+    set_cookie(
+        &mut res,
+        state
+            .cookies
+            .removal(BINDING_COOKIE_NAME, BINDING_COOKIE_PATH),
+    );
+    Ok(res)
+}
@@ -1,62 +1,276 @@
 use crate::AppState;
+use crate::auth::client::ClientInfo;
 use crate::auth::errors::AuthError;
-use crate::models::User;
-use crate::repositories::{SessionRepository, UserRepository};
+use crate::auth::events;
+use crate::auth::token::hash_token;
+use crate::cookie::{Cookies, set_cookie, sets_cookie};
+use crate::i18n::{self, Locale};
+use crate::models::{
+    API_TOKEN_SCOPE_READ, API_TOKEN_SCOPE_WRITE, AUTH_EVENT_ACCESS_DENIED, ApiToken,
+    PERMISSION_USERS_MANAGE, Session, User,
+};
+use crate::repositories::{ApiTokenRepository, RoleRepository, SessionRepository, UserRepository};
 use axum::{
     extract::{Request, State},
+    http::{
+        HeaderMap, Method,
+        header::{AUTHORIZATION, COOKIE},
+    },
     middleware::Next,
     response::Response,
 };
+use chrono::Utc;
+use cookie::Cookie;
 
 #[derive(Clone)]
 pub struct AuthenticatedUser {
     pub user: User,
+    /// `Authorization: Bearer` で認証された場合のAPIトークン（Cookieセッションの場合はNone）
+    pub api_token: Option<ApiToken>,
+    /// Cookieセッションで認証された場合のセッションID（APIトークンの場合はNone）
+    pub session_id: Option<i64>,
+    /// 管理者がなりすましている場合、その管理者（`user` はなりすまされているユーザー）
+    pub impersonator: Option<User>,
+    /// 割り当てられたロール名
+    pub roles: Vec<String>,
+    /// ロールを通じて持っている権限名
+    pub permissions: Vec<String>,
 }
 
-pub async fn session_auth_middleware(
-    State(state): State<AppState>,
-    mut request: Request,
-    next: Next,
-) -> Result<Response, AuthError> {
-    let cookie_header = request
-        .headers()
-        .get("Cookie")
-        .and_then(|header| header.to_str().ok())
-        .ok_or(AuthError::MissingCookieHeader)?;
-
-    // Parse cookies to find session_id
-    let mut session_id: Option<&str> = None;
-    for cookie_pair in cookie_header.split(';') {
-        let cookie_pair = cookie_pair.trim();
-        if let Some(id) = cookie_pair.strip_prefix("session_id=") {
-            session_id = Some(id);
-            break;
+impl AuthenticatedUser {
+    /// 認証手段の管理はブラウザのセッションからのみ許可する（APIトークンによる権限昇格を防ぐ）
+    pub fn require_session(&self) -> Result<(), AuthError> {
+        if self.api_token.is_some() {
+            return Err(AuthError::SessionRequired);
         }
+        Ok(())
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// APIトークンを検証し、リクエストのメソッドに必要なスコープを持っているか確認する
+async fn authenticate_api_token(
+    state: &AppState,
+    token: &str,
+    method: &Method,
+    client: &ClientInfo,
+) -> Result<ApiToken, AuthError> {
+    let token_repo = ApiTokenRepository::new(&state.pool);
+    let now = Utc::now();
+
+    let api_token = token_repo
+        .find_valid_by_hash(&hash_token(token), now)
+        .await
+        .map_err(|_| AuthError::NotLogined)?
+        .ok_or(AuthError::NotLogined)?;
+
+    let required_scope = match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => API_TOKEN_SCOPE_READ,
+        _ => API_TOKEN_SCOPE_WRITE,
+    };
+    if !api_token.has_scope(required_scope) {
+        events::record(
+            state,
+            AUTH_EVENT_ACCESS_DENIED,
+            Some(api_token.user_id),
+            Some("insufficient_scope"),
+            client,
+        )
+        .await?;
+        return Err(AuthError::InsufficientScope);
     }
 
-    let session_id = session_id.ok_or(AuthError::MissingSessionId)?;
+    token_repo.touch(api_token.id, now).await?;
+    Ok(api_token)
+}
+
+/// Cookieのセッションを検証し、必要であれば有効期限を延長する。
+/// 延長した場合は再発行するCookieも返す
+async fn authenticate_session(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(Session, Option<Cookie<'static>>), AuthError> {
+    if !headers.contains_key(COOKIE) {
+        return Err(AuthError::MissingCookieHeader);
+    }
+    let session_id = state
+        .cookies
+        .session_id(&Cookies::from_headers(headers))
+        .ok_or(AuthError::MissingSessionId)?;
     let session_repo = SessionRepository::new(&state.pool);
-    let user_repo = UserRepository::new(&state.pool);
+    let session_config = &state.config.auth.session;
+    let now = Utc::now();
 
     // Find session in database
     let session = session_repo
-        .find_by_uuid(session_id)
+        .find_active_by_uuid(&session_id, now, session_config.idle_timeout())
         .await
         .map_err(|_| AuthError::NotLogined)?
         .ok_or(AuthError::NotLogined)?;
 
+    // Sliding renewal, throttled to avoid a write on every request
+    let renewed_cookie = match session.last_seen_at {
+        Some(last_seen_at) if now - last_seen_at < session_config.renewal_interval() => None,
+        _ => {
+            session_repo.touch(session.id, now).await?;
+            Some(state.cookies.session_cookie(
+                &session.uuid,
+                session.cookie_max_age(now, session_config.idle_timeout()),
+            ))
+        }
+    };
+
+    Ok((session, renewed_cookie))
+}
+
+pub async fn session_auth_middleware(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AuthError> {
+    let client = ClientInfo::from_request(&state, request.headers(), request.extensions());
+    let (user_id, api_token, session, renewed_cookie) = match bearer_token(request.headers()) {
+        Some(token) => {
+            let api_token =
+                authenticate_api_token(&state, token, request.method(), &client).await?;
+            (api_token.user_id, Some(api_token), None, None)
+        }
+        None => {
+            let (session, renewed_cookie) = authenticate_session(&state, request.headers()).await?;
+            (session.user_id, None, Some(session), renewed_cookie)
+        }
+    };
+
+    let user_repo = UserRepository::new(&state.pool);
+
+    let role_repo = RoleRepository::new(&state.pool);
+
+    // The administrator behind an impersonation session must still be an enabled administrator
+    let impersonator = match &session {
+        Some(session) => match session.impersonator_user_id {
+            Some(impersonator_id) => {
+                let impersonator = user_repo.find_by_id(impersonator_id).await?;
+                let still_admin = match &impersonator {
+                    Some(impersonator) if impersonator.disabled_at.is_none() => role_repo
+                        .find_permission_names_by_user_id(impersonator.id)
+                        .await?
+                        .iter()
+                        .any(|permission| permission == PERMISSION_USERS_MANAGE),
+                    _ => false,
+                };
+                if !still_admin {
+                    SessionRepository::new(&state.pool)
+                        .delete(session.id)
+                        .await?;
+                    return Err(AuthError::NotLogined);
+                }
+                impersonator
+            }
+            None => None,
+        },
+        None => None,
+    };
+
     // Get user information
     let user = user_repo
-        .get_by_id(session.user_id)
+        .get_by_id(user_id)
         .await
         .map_err(|_| AuthError::UserNotFound)?;
 
+    // The language setting of whoever is at the keyboard takes precedence over Accept-Language
+    if let Some(locale) = impersonator
+        .as_ref()
+        .unwrap_or(&user)
+        .locale
+        .as_deref()
+        .and_then(Locale::from_tag)
+    {
+        i18n::set_current(locale);
+    }
+
+    if user.disabled_at.is_some() {
+        events::record(
+            &state,
+            AUTH_EVENT_ACCESS_DENIED,
+            Some(user.id),
+            Some("account_disabled"),
+            &client,
+        )
+        .await?;
+        return Err(AuthError::AccountDisabled);
+    }
+
+    // Accounts pending deletion can only come back by signing in again
+    if user.deletion_scheduled_at.is_some() {
+        return Err(AuthError::NotLogined);
+    }
+
+    if state.config.auth.require_email_verification && user.email_verified_at.is_none() {
+        return Err(AuthError::EmailNotVerified);
+    }
+
+    // Load roles and permissions
+    let roles = role_repo
+        .find_by_user_id(user.id)
+        .await?
+        .into_iter()
+        .map(|role| role.name)
+        .collect();
+    let permissions = role_repo.find_permission_names_by_user_id(user.id).await?;
+
     // Create AuthenticatedUser
-    let authenticated_user = AuthenticatedUser { user: user };
+    let authenticated_user = AuthenticatedUser {
+        user,
+        api_token,
+        session_id: session.as_ref().map(|s| s.id),
+        impersonator,
+        roles,
+        permissions,
+    };
 
     // Add authenticated user to request extensions
     request.extensions_mut().insert(authenticated_user);
+    // Later layers such as CSRF protection reuse the session instead of querying it again
+    if let Some(session) = session {
+        request.extensions_mut().insert(session);
+    }
 
     // Continue to next middleware/handler
+    let mut res = next.run(request).await;
+
+    // Reissue the cookie so that its Max-Age follows the renewed session
+    if let Some(cookie) = renewed_cookie
+        && !sets_cookie(&res, state.cookies.session_name())
+    {
+        set_cookie(&mut res, cookie);
+    }
+
+    Ok(res)
+}
+
+/// なりすまし中にアカウントへ影響する変更を拒否するミドルウェア。
+/// 参照系（GET/HEAD）は通す。`session_auth_middleware` の内側に置く
+pub async fn deny_during_impersonation(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthError> {
+    let impersonating = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .is_some_and(|auth_user| auth_user.impersonator.is_some());
+    let safe_method = matches!(*request.method(), Method::GET | Method::HEAD);
+
+    if impersonating && !safe_method && state.config.auth.impersonation.block_destructive {
+        return Err(AuthError::ImpersonationForbidden);
+    }
+
     Ok(next.run(request).await)
 }
@@ -1,6 +1,22 @@
+pub mod account;
+pub mod api_tokens;
+pub mod client;
+pub mod email_verification;
 pub mod errors;
+pub mod events;
 pub mod handlers;
+pub mod magic_link;
 pub mod middleware;
+pub mod oidc;
+pub mod passkey;
+pub mod password;
+pub mod password_policy;
+pub mod password_reset;
+pub mod rbac;
+pub mod sessions;
+pub mod throttle;
+pub mod token;
+pub mod two_factor;
 
 pub use errors::*;
 pub use handlers::*;
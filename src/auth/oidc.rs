@@ -0,0 +1,415 @@
+use crate::AppState;
+use crate::auth::client::ClientInfo;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::events;
+use crate::auth::handlers::start_session;
+use crate::auth::token::{generate_token, hash_token};
+use crate::config::{OidcProviderConfig, RegistrationMode};
+use crate::cookie::{Cookies, set_cookie};
+use crate::extract::{Path, Query};
+use crate::models::{
+    AUTH_EVENT_IDENTITY_LINKED, AUTH_EVENT_LOGIN_SUCCESS, AUTH_EVENT_REGISTER, OidcLoginState,
+};
+use crate::repositories::{
+    OidcIdentityRepository, OidcLoginStateRepository, SessionRepository, UserRepository,
+};
+use crate::validation::normalize_email;
+use axum::{
+    extract::State,
+    http::{StatusCode, header::LOCATION},
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header, jwk::JwkSet};
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, instrument, warn};
+
+/// 認可リクエストからコールバックまでに許容する時間
+const LOGIN_STATE_TTL_MINUTES: i64 = 10;
+const STATE_COOKIE_NAME: &str = "oidc_state";
+const STATE_COOKIE_PATH: &str = "/api/auth/oidc";
+
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_email_verified")]
+    email_verified: bool,
+}
+
+/// `email_verified` を真偽値として読む。文字列の `"true"` を返すIDプロバイダもあるため、それも受け付ける
+fn deserialize_email_verified<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match BoolOrString::deserialize(deserializer)? {
+        BoolOrString::Bool(verified) => verified,
+        BoolOrString::String(verified) => verified.eq_ignore_ascii_case("true"),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+fn oidc_error(message: impl Into<String>) -> AuthError {
+    let message = message.into();
+    warn!("OIDC authentication failed: {}", message);
+    AuthError::OidcFailed(message)
+}
+
+fn provider_config<'a>(state: &'a AppState, provider: &str) -> AuthResult<&'a OidcProviderConfig> {
+    state
+        .config
+        .auth
+        .oidc
+        .get(provider)
+        .ok_or(AuthError::OidcProviderNotFound)
+}
+
+fn redirect_url(state: &AppState, provider: &str, config: &OidcProviderConfig) -> String {
+    config.redirect_url.clone().unwrap_or_else(|| {
+        format!(
+            "{}/api/auth/oidc/{}/callback",
+            state.config.server.base_url(),
+            provider
+        )
+    })
+}
+
+async fn fetch_metadata(
+    state: &AppState,
+    config: &OidcProviderConfig,
+) -> AuthResult<ProviderMetadata> {
+    let metadata: ProviderMetadata = state
+        .http_client
+        .get(config.discovery_url())
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| oidc_error(format!("discovery request failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| oidc_error(format!("invalid discovery document: {}", e)))?;
+
+    if metadata.issuer.trim_end_matches('/') != config.issuer.trim_end_matches('/') {
+        return Err(oidc_error("issuer in discovery document does not match"));
+    }
+
+    Ok(metadata)
+}
+
+/// IDトークンの署名・iss・aud・期限・nonceを検証してクレームを返す
+async fn verify_id_token(
+    state: &AppState,
+    config: &OidcProviderConfig,
+    metadata: &ProviderMetadata,
+    id_token: &str,
+    nonce: &str,
+) -> AuthResult<IdTokenClaims> {
+    let jwks_url = config.jwks_url.as_deref().unwrap_or(&metadata.jwks_uri);
+    let jwks: JwkSet = state
+        .http_client
+        .get(jwks_url)
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| oidc_error(format!("JWKS request failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| oidc_error(format!("invalid JWKS: {}", e)))?;
+
+    let header = decode_header(id_token).map_err(|e| oidc_error(e.to_string()))?;
+    if !matches!(
+        header.alg,
+        Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::ES256
+            | Algorithm::ES384
+            | Algorithm::PS256
+            | Algorithm::EdDSA
+    ) {
+        return Err(oidc_error(format!(
+            "unsupported algorithm {:?}",
+            header.alg
+        )));
+    }
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        None => jwks.keys.first(),
+    }
+    .ok_or_else(|| oidc_error("signing key not found in JWKS"))?;
+    let key = DecodingKey::from_jwk(jwk).map_err(|e| oidc_error(e.to_string()))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&config.issuer]);
+    validation.set_audience(&[&config.client_id]);
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(|e| oidc_error(format!("invalid ID token: {}", e)))?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(oidc_error("nonce mismatch"));
+    }
+
+    Ok(claims)
+}
+
+/// 外部アカウントに対応するユーザーIDを決める。
+/// 未連携の場合、ログイン中ならそのユーザーに紐付け、そうでなければ設定に応じて自動作成する
+async fn resolve_user_id(
+    state: &AppState,
+    provider: &str,
+    config: &OidcProviderConfig,
+    claims: &IdTokenClaims,
+    cookies: &Cookies,
+    client: &ClientInfo,
+) -> AuthResult<i64> {
+    let identity_repo = OidcIdentityRepository::new(&state.pool);
+    let user_repo = UserRepository::new(&state.pool);
+
+    if let Some(identity) = identity_repo.find_by_subject(provider, &claims.sub).await? {
+        identity_repo
+            .update_email(identity.id, claims.email.as_deref())
+            .await?;
+        return Ok(identity.user_id);
+    }
+
+    // Link to the currently logged-in user
+    if let Some(session_id) = state.cookies.session_id(cookies)
+        && let Some(session) = SessionRepository::new(&state.pool)
+            .find_active_by_uuid(
+                &session_id,
+                Utc::now(),
+                state.config.auth.session.idle_timeout(),
+            )
+            .await?
+    {
+        // Otherwise an administrator could link their own IdP account to the user
+        // and later sign in as them without any record of impersonation
+        if session.impersonator_user_id.is_some() {
+            debug!("OIDC link refused: session is impersonated");
+            return Err(AuthError::ImpersonationForbidden);
+        }
+
+        identity_repo
+            .create(
+                session.user_id,
+                provider,
+                &claims.sub,
+                claims.email.as_deref(),
+            )
+            .await?;
+        info!(user_id = %session.user_id, provider = %provider, "OIDC identity linked");
+        events::record(
+            state,
+            AUTH_EVENT_IDENTITY_LINKED,
+            Some(session.user_id),
+            Some(&format!("oidc:{}", provider)),
+            client,
+        )
+        .await?;
+        return Ok(session.user_id);
+    }
+
+    if !config.auto_provision {
+        debug!("OIDC login failed: identity is not linked to any user");
+        return Err(AuthError::InvalidCredentials);
+    }
+    // Auto provisioning is a form of sign-up and has no way to carry an invitation code
+    if state.config.auth.registration != RegistrationMode::Open {
+        debug!("OIDC auto provisioning failed: registration is not open");
+        return Err(AuthError::RegistrationClosed);
+    }
+
+    let email = claims
+        .email
+        .as_deref()
+        .ok_or_else(|| oidc_error("email claim is required for auto provisioning"))?;
+    let email = normalize_email(email).map_err(|e| oidc_error(e.to_string()))?;
+    if user_repo.exists_by_email(&email).await? {
+        // Never take over an existing password account automatically
+        debug!("OIDC auto provisioning failed: email already exists");
+        return Err(AuthError::EmailAlreadyExists);
+    }
+
+    // The account has no usable password until the user resets it
+    let password_hash = state.password_hasher.hash(&generate_token()).await?;
+    let user_id = user_repo.create(&email, &password_hash).await?;
+    if claims.email_verified {
+        user_repo.mark_email_verified(user_id).await?;
+    }
+    identity_repo
+        .create(user_id, provider, &claims.sub, Some(&email))
+        .await?;
+    info!(user_id = %user_id, provider = %provider, "User provisioned from OIDC");
+    events::record(
+        state,
+        AUTH_EVENT_REGISTER,
+        Some(user_id),
+        Some(&format!("oidc:{}", provider)),
+        client,
+    )
+    .await?;
+
+    Ok(user_id)
+}
+
+/// 認可コードフローの開始。IDプロバイダの認可エンドポイントへリダイレクトする
+#[instrument(skip(state))]
+pub async fn start(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+) -> AuthResult<Response> {
+    let config = provider_config(&state, &provider)?;
+    let metadata = fetch_metadata(&state, config).await?;
+
+    let login_state = generate_token();
+    let nonce = generate_token();
+    let code_verifier = generate_token();
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+    OidcLoginStateRepository::new(&state.pool)
+        .create(&OidcLoginState::new(
+            &provider,
+            hash_token(&login_state),
+            nonce.clone(),
+            code_verifier,
+            Duration::minutes(LOGIN_STATE_TTL_MINUTES),
+        ))
+        .await?;
+
+    let mut authorization_url = reqwest::Url::parse(&metadata.authorization_endpoint)
+        .map_err(|e| oidc_error(format!("invalid authorization endpoint: {}", e)))?;
+    authorization_url
+        .query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("redirect_uri", &redirect_url(&state, &provider, config))
+        .append_pair("scope", &config.scopes.join(" "))
+        .append_pair("state", &login_state)
+        .append_pair("nonce", &nonce)
+        .append_pair("code_challenge", &code_challenge)
+        .append_pair("code_challenge_method", "S256");
+
+    // Bind the flow to this browser
+    let cookie = state
+        .cookies
+        .builder(STATE_COOKIE_NAME, login_state)
+        .path(STATE_COOKIE_PATH)
+        .max_age(cookie::time::Duration::minutes(LOGIN_STATE_TTL_MINUTES))
+        .build();
+
+    let mut res = (StatusCode::SEE_OTHER).into_response();
+    res.headers_mut()
+        .insert(LOCATION, authorization_url.as_str().parse().unwrap());
+    set_cookie(&mut res, cookie);
+    Ok(res)
+}
+
+/// IDプロバイダからのコールバック。コードをトークンに交換し、セッションを発行してトップページへリダイレクトする
+#[instrument(skip(state, query, cookies, client))]
+pub async fn callback(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    Query(query): Query<CallbackQuery>,
+    cookies: Cookies,
+    client: ClientInfo,
+) -> AuthResult<Response> {
+    let config = provider_config(&state, &provider)?;
+
+    if let Some(error) = &query.error {
+        return Err(oidc_error(format!("provider returned error: {}", error)));
+    }
+    let (Some(code), Some(login_state)) = (&query.code, &query.state) else {
+        return Err(oidc_error("missing code or state"));
+    };
+    if cookies.get(STATE_COOKIE_NAME) != Some(login_state.as_str()) {
+        return Err(oidc_error("state does not match this browser"));
+    }
+
+    let saved_state = OidcLoginStateRepository::new(&state.pool)
+        .consume(&provider, &hash_token(login_state), Utc::now())
+        .await?
+        .ok_or(AuthError::InvalidToken)?;
+
+    let metadata = fetch_metadata(&state, config).await?;
+    let redirect_uri = redirect_url(&state, &provider, config);
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", redirect_uri.as_str()),
+        ("client_id", config.client_id.as_str()),
+        ("code_verifier", saved_state.code_verifier.as_str()),
+    ];
+    if let Some(client_secret) = &config.client_secret {
+        form.push(("client_secret", client_secret.as_str()));
+    }
+    let token_response: TokenResponse = state
+        .http_client
+        .post(&metadata.token_endpoint)
+        .form(&form)
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| oidc_error(format!("token request failed: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| oidc_error(format!("invalid token response: {}", e)))?;
+
+    let claims = verify_id_token(
+        &state,
+        config,
+        &metadata,
+        &token_response.id_token,
+        &saved_state.nonce,
+    )
+    .await?;
+
+    let user_id = resolve_user_id(&state, &provider, config, &claims, &cookies, &client).await?;
+    info!(user_id = %user_id, provider = %provider, "OIDC login succeeded");
+
+    let mut res = start_session(&state, user_id, &client, &cookies).await?;
+    events::record(
+        &state,
+        AUTH_EVENT_LOGIN_SUCCESS,
+        Some(user_id),
+        Some(&format!("oidc:{}", provider)),
+        &client,
+    )
+    .await?;
+    *res.status_mut() = StatusCode::SEE_OTHER;
+    res.headers_mut().insert(LOCATION, "/".parse().unwrap());
+    set_cookie(
+        &mut res,
+        state.cookies.removal(STATE_COOKIE_NAME, STATE_COOKIE_PATH),
+    );
+    Ok(res)
+}
@@ -0,0 +1,344 @@
+use crate::AppState;
+use crate::auth::client::ClientInfo;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::events;
+use crate::auth::handlers::start_session;
+use crate::auth::middleware::AuthenticatedUser;
+use crate::auth::token::{generate_token, hash_token};
+use crate::config::WebauthnConfig;
+use crate::cookie::Cookies;
+use crate::extract::Path;
+use crate::models::{
+    AUTH_EVENT_LOGIN_FAILURE, AUTH_EVENT_LOGIN_SUCCESS, PasskeyCredential,
+    WEBAUTHN_CEREMONY_AUTHENTICATION, WEBAUTHN_CEREMONY_REGISTRATION, WebauthnChallenge,
+};
+use crate::repositories::{
+    PasskeyCredentialRepository, UserRepository, WebauthnChallengeRepository,
+};
+use crate::validation::{Validate, ValidatedJson, ValidationErrors};
+use axum::{Extension, Json, extract::State, http::StatusCode, response::IntoResponse};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument};
+use webauthn_rs::prelude::*;
+
+/// 開始から完了までに許容する時間（webauthn-rsのデフォルトのタイムアウトに合わせる）
+const CHALLENGE_TTL_MINUTES: i64 = 5;
+
+#[derive(Debug, Serialize)]
+pub struct RegistrationStartResponse {
+    pub challenge_token: String,
+    pub options: CreationChallengeResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegistrationFinishRequest {
+    pub challenge_token: String,
+    pub name: Option<String>,
+    pub credential: RegisterPublicKeyCredential,
+}
+
+impl Validate for RegistrationFinishRequest {}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginStartRequest {
+    pub email: String,
+}
+
+impl Validate for LoginStartRequest {
+    fn validate(&mut self, errors: &mut ValidationErrors) {
+        errors.email("email", &mut self.email);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoginStartResponse {
+    pub challenge_token: String,
+    pub options: RequestChallengeResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginFinishRequest {
+    pub challenge_token: String,
+    pub credential: PublicKeyCredential,
+}
+
+impl Validate for LoginFinishRequest {}
+
+/// 設定からWebauthnのインスタンスを生成
+pub fn build_webauthn(config: &WebauthnConfig) -> Webauthn {
+    let origin = Url::parse(&config.rp_origin)
+        .unwrap_or_else(|e| panic!("Invalid auth.webauthn.rp_origin: {}", e));
+
+    WebauthnBuilder::new(&config.rp_id, &origin)
+        .and_then(|builder| builder.rp_name(&config.rp_name).build())
+        .unwrap_or_else(|e| panic!("Invalid auth.webauthn configuration: {}", e))
+}
+
+/// 認証器に渡すユーザーハンドル。ユーザーごとに固定の値にする
+fn user_handle(user_id: i64) -> Uuid {
+    Uuid::from_u64_pair(0, user_id as u64)
+}
+
+fn to_json<T: Serialize>(value: &T) -> AuthResult<String> {
+    Ok(serde_json::to_string(value).map_err(anyhow::Error::from)?)
+}
+
+fn from_json<T: for<'de> Deserialize<'de>>(value: &str) -> AuthResult<T> {
+    Ok(serde_json::from_str(value).map_err(anyhow::Error::from)?)
+}
+
+async fn save_challenge<T: Serialize>(
+    state: &AppState,
+    user_id: i64,
+    ceremony: &str,
+    ceremony_state: &T,
+) -> AuthResult<String> {
+    let token = generate_token();
+    WebauthnChallengeRepository::new(&state.pool)
+        .create(&WebauthnChallenge::new(
+            user_id,
+            ceremony,
+            hash_token(&token),
+            to_json(ceremony_state)?,
+            Duration::minutes(CHALLENGE_TTL_MINUTES),
+        ))
+        .await?;
+
+    Ok(token)
+}
+
+/// 登録済みのパスキー一覧
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+) -> AuthResult<Json<Vec<PasskeyCredential>>> {
+    auth_user.require_session()?;
+
+    let credentials = PasskeyCredentialRepository::new(&state.pool)
+        .find_by_user_id(auth_user.user.id)
+        .await?;
+
+    Ok(Json(credentials))
+}
+
+/// パスキー登録の開始。ブラウザの navigator.credentials.create() に渡すオプションを返す
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn start_registration(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+) -> AuthResult<Json<RegistrationStartResponse>> {
+    auth_user.require_session()?;
+
+    let user = &auth_user.user;
+
+    // Prevent registering the same authenticator twice
+    let exclude_credentials = PasskeyCredentialRepository::new(&state.pool)
+        .find_by_user_id(user.id)
+        .await?
+        .iter()
+        .map(|credential| from_json::<Passkey>(&credential.passkey))
+        .map(|passkey| passkey.map(|passkey| passkey.cred_id().clone()))
+        .collect::<AuthResult<Vec<_>>>()?;
+
+    let (options, registration) = state.webauthn.start_passkey_registration(
+        user_handle(user.id),
+        &user.email,
+        &user.email,
+        Some(exclude_credentials),
+    )?;
+
+    let challenge_token = save_challenge(
+        &state,
+        user.id,
+        WEBAUTHN_CEREMONY_REGISTRATION,
+        &registration,
+    )
+    .await?;
+
+    Ok(Json(RegistrationStartResponse {
+        challenge_token,
+        options,
+    }))
+}
+
+/// パスキー登録の完了。認証器の応答を検証して公開鍵を保存する
+#[instrument(skip(state, auth_user, request), fields(user_id = %auth_user.user.id))]
+pub async fn finish_registration(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    ValidatedJson(request): ValidatedJson<RegistrationFinishRequest>,
+) -> AuthResult<StatusCode> {
+    auth_user.require_session()?;
+
+    let credential_repo = PasskeyCredentialRepository::new(&state.pool);
+
+    let challenge = WebauthnChallengeRepository::new(&state.pool)
+        .consume(
+            &hash_token(&request.challenge_token),
+            WEBAUTHN_CEREMONY_REGISTRATION,
+            Utc::now(),
+        )
+        .await?
+        .filter(|challenge| challenge.user_id == auth_user.user.id)
+        .ok_or(AuthError::InvalidToken)?;
+    let registration: PasskeyRegistration = from_json(&challenge.state)?;
+
+    let passkey = state
+        .webauthn
+        .finish_passkey_registration(&request.credential, &registration)?;
+
+    let credential_id = hex::encode(passkey.cred_id());
+    if credential_repo
+        .exists_by_credential_id(&credential_id)
+        .await?
+    {
+        debug!("Passkey registration failed: credential already registered");
+        return Err(AuthError::PasskeyAlreadyRegistered);
+    }
+
+    credential_repo
+        .create(
+            auth_user.user.id,
+            &credential_id,
+            &to_json(passkey.get_public_key())?,
+            &to_json(&passkey)?,
+            request.name.as_deref(),
+        )
+        .await?;
+    info!("Passkey registered");
+
+    Ok(StatusCode::OK)
+}
+
+/// パスキーの削除
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn delete(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    Path(id): Path<i64>,
+) -> AuthResult<StatusCode> {
+    auth_user.require_session()?;
+
+    if !PasskeyCredentialRepository::new(&state.pool)
+        .delete(auth_user.user.id, id)
+        .await?
+    {
+        return Err(AuthError::PasskeyNotFound);
+    }
+    info!(passkey_id = %id, "Passkey deleted");
+
+    Ok(StatusCode::OK)
+}
+
+/// パスキーによるログインの開始。navigator.credentials.get() に渡すオプションを返す
+#[instrument(skip(state, request), fields(email = %request.email))]
+pub async fn start_login(
+    State(state): State<AppState>,
+    ValidatedJson(request): ValidatedJson<LoginStartRequest>,
+) -> AuthResult<Json<LoginStartResponse>> {
+    let user = UserRepository::new(&state.pool)
+        .find_by_email(&request.email)
+        .await?
+        .ok_or_else(|| {
+            debug!("Passkey login failed: user not found");
+            AuthError::InvalidCredentials
+        })?;
+
+    let passkeys = PasskeyCredentialRepository::new(&state.pool)
+        .find_by_user_id(user.id)
+        .await?
+        .iter()
+        .map(|credential| from_json::<Passkey>(&credential.passkey))
+        .collect::<AuthResult<Vec<_>>>()?;
+    if passkeys.is_empty() {
+        debug!("Passkey login failed: no passkeys registered");
+        return Err(AuthError::InvalidCredentials);
+    }
+
+    let (options, authentication) = state.webauthn.start_passkey_authentication(&passkeys)?;
+
+    let challenge_token = save_challenge(
+        &state,
+        user.id,
+        WEBAUTHN_CEREMONY_AUTHENTICATION,
+        &authentication,
+    )
+    .await?;
+
+    Ok(Json(LoginStartResponse {
+        challenge_token,
+        options,
+    }))
+}
+
+/// パスキーによるログインの完了。署名を検証し、パスワードログインと同じセッションを発行する
+#[instrument(skip(state, cookies, request))]
+pub async fn finish_login(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    client: ClientInfo,
+    ValidatedJson(request): ValidatedJson<LoginFinishRequest>,
+) -> AuthResult<impl IntoResponse> {
+    let credential_repo = PasskeyCredentialRepository::new(&state.pool);
+
+    let challenge = WebauthnChallengeRepository::new(&state.pool)
+        .consume(
+            &hash_token(&request.challenge_token),
+            WEBAUTHN_CEREMONY_AUTHENTICATION,
+            Utc::now(),
+        )
+        .await?
+        .ok_or(AuthError::InvalidToken)?;
+    let authentication: PasskeyAuthentication = from_json(&challenge.state)?;
+
+    let result = match state
+        .webauthn
+        .finish_passkey_authentication(&request.credential, &authentication)
+    {
+        Ok(result) => result,
+        Err(e) => {
+            events::record(
+                &state,
+                AUTH_EVENT_LOGIN_FAILURE,
+                Some(challenge.user_id),
+                Some("passkey_verification_failed"),
+                &client,
+            )
+            .await?;
+            return Err(e.into());
+        }
+    };
+
+    // Persist the new sign counter so that cloned authenticators can be detected
+    let credential_id = hex::encode(result.cred_id());
+    let credential = credential_repo
+        .find_by_user_id(challenge.user_id)
+        .await?
+        .into_iter()
+        .find(|credential| credential.credential_id == credential_id)
+        .ok_or(AuthError::PasskeyNotFound)?;
+    let mut passkey: Passkey = from_json(&credential.passkey)?;
+    passkey.update_credential(&result);
+    credential_repo
+        .update_after_authentication(
+            &credential_id,
+            &to_json(&passkey)?,
+            i64::from(result.counter()),
+            Utc::now(),
+        )
+        .await?;
+    info!(user_id = %challenge.user_id, "Passkey login succeeded");
+
+    let res = start_session(&state, challenge.user_id, &client, &cookies).await?;
+    events::record(
+        &state,
+        AUTH_EVENT_LOGIN_SUCCESS,
+        Some(challenge.user_id),
+        Some("passkey"),
+        &client,
+    )
+    .await?;
+    Ok(res)
+}
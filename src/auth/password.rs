@@ -0,0 +1,97 @@
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::config::PasswordHashConfig;
+use argon2::{
+    Algorithm, Argon2, Params, Version,
+    password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier, SaltString},
+};
+use rand::rngs::OsRng;
+use tokio::task::spawn_blocking;
+use tracing::error;
+
+/// パスワードハッシュの生成と検証。
+/// 新しいハッシュはArgon2idで生成し、既存のbcryptハッシュも検証できる。
+/// ハッシュ計算は重いため、Tokioのワーカーを止めないようブロッキング用のスレッドで実行する。
+#[derive(Clone)]
+pub struct PasswordHasher {
+    params: Params,
+}
+
+fn is_bcrypt_hash(password_hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| password_hash.starts_with(prefix))
+}
+
+impl PasswordHasher {
+    pub fn new(config: &PasswordHashConfig) -> Self {
+        let params = Params::new(
+            config.memory_kib,
+            config.iterations,
+            config.parallelism,
+            None,
+        )
+        .unwrap_or_else(|e| panic!("Invalid auth.password_hash configuration: {}", e));
+
+        Self { params }
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+
+    async fn run_blocking<T, F>(f: F) -> AuthResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> AuthResult<T> + Send + 'static,
+    {
+        spawn_blocking(f).await.map_err(|e| {
+            error!("Password hashing task failed: {}", e);
+            AuthError::InternalError
+        })?
+    }
+
+    /// パスワードをArgon2idでハッシュ化
+    pub async fn hash(&self, password: &str) -> AuthResult<String> {
+        let argon2 = self.argon2();
+        let password = password.to_string();
+
+        Self::run_blocking(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            Ok(argon2
+                .hash_password(password.as_bytes(), &salt)?
+                .to_string())
+        })
+        .await
+    }
+
+    /// パスワードを検証。ハッシュの形式（bcrypt / Argon2）は先頭の識別子で判別する
+    pub async fn verify(&self, password: &str, password_hash: &str) -> AuthResult<bool> {
+        let argon2 = self.argon2();
+        let password = password.to_string();
+        let password_hash = password_hash.to_string();
+
+        Self::run_blocking(move || {
+            if is_bcrypt_hash(&password_hash) {
+                return Ok(bcrypt::verify(&password, &password_hash)?);
+            }
+
+            let parsed = PasswordHash::new(&password_hash)?;
+            Ok(argon2.verify_password(password.as_bytes(), &parsed).is_ok())
+        })
+        .await
+    }
+
+    /// 現在の設定で作り直すべきハッシュか（bcryptや古いパラメータのArgon2）
+    pub fn needs_rehash(&self, password_hash: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(password_hash) else {
+            return true;
+        };
+
+        parsed.algorithm != Algorithm::Argon2id.ident()
+            || Params::try_from(&parsed).map_or(true, |params| {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            })
+    }
+}
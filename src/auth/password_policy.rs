@@ -0,0 +1,153 @@
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::config::PasswordPolicyConfig;
+use crate::i18n::{self, Locale};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::io::ErrorKind;
+use std::path::Path;
+use tokio::task::spawn_blocking;
+use tracing::{debug, error, warn};
+use zxcvbn::zxcvbn;
+
+pub const RULE_MIN_LENGTH: &str = "min_length";
+pub const RULE_MAX_BYTES: &str = "max_bytes";
+pub const RULE_STRENGTH: &str = "strength";
+pub const RULE_BREACHED: &str = "breached";
+
+/// 満たしていないパスワードの条件
+#[derive(Debug, Clone, Serialize)]
+pub struct PasswordViolation {
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl PasswordViolation {
+    fn new(rule: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            rule,
+            message: message.into(),
+        }
+    }
+}
+
+/// 新しいパスワードがポリシーを満たすか検証する。
+/// 満たしていない条件はまとめて `AuthError::PasswordPolicyViolation` で返す。
+/// `user_inputs` にはメールアドレスなど、パスワードに含めるべきでない語を渡す。
+pub async fn validate(
+    config: &PasswordPolicyConfig,
+    password: &str,
+    user_inputs: &[&str],
+) -> AuthResult<()> {
+    let locale = i18n::current();
+    let mut violations = Vec::new();
+
+    if password.chars().count() < config.min_length {
+        violations.push(PasswordViolation::new(
+            RULE_MIN_LENGTH,
+            i18n::text_with(
+                locale,
+                "password_min_length",
+                &[("min", &config.min_length)],
+            ),
+        ));
+    }
+
+    if password.len() > config.max_bytes {
+        violations.push(PasswordViolation::new(
+            RULE_MAX_BYTES,
+            i18n::text_with(locale, "password_max_bytes", &[("max", &config.max_bytes)]),
+        ));
+    } else if !password.is_empty()
+        && let Some(violation) = check_strength(config, locale, password, user_inputs).await?
+    {
+        // Scoring is skipped for oversized input to keep its cost bounded
+        violations.push(violation);
+    }
+
+    if let Some(dir) = &config.breached_passwords_dir
+        && is_breached(Path::new(dir), password).await
+    {
+        violations.push(PasswordViolation::new(
+            RULE_BREACHED,
+            i18n::text(locale, "password_breached"),
+        ));
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        debug!(rules = ?violations.iter().map(|v| v.rule).collect::<Vec<_>>(), "Password rejected by policy");
+        Err(AuthError::PasswordPolicyViolation(violations))
+    }
+}
+
+/// メールアドレス全体とローカル部を、パスワードに含めるべきでない語として返す
+pub fn email_inputs(email: &str) -> Vec<&str> {
+    let mut inputs = vec![email];
+    if let Some((local, _)) = email.split_once('@') {
+        inputs.push(local);
+    }
+    inputs
+}
+
+async fn check_strength(
+    config: &PasswordPolicyConfig,
+    locale: Locale,
+    password: &str,
+    user_inputs: &[&str],
+) -> AuthResult<Option<PasswordViolation>> {
+    let min_score = config.min_strength_score;
+    let password = password.to_string();
+    let user_inputs: Vec<String> = user_inputs.iter().map(|s| s.to_string()).collect();
+
+    // zxcvbn's matching is CPU bound, so keep it off the async workers
+    spawn_blocking(move || {
+        let user_inputs: Vec<&str> = user_inputs.iter().map(String::as_str).collect();
+        let entropy = zxcvbn(&password, &user_inputs);
+        let score = u8::from(entropy.score());
+        if score >= min_score {
+            return None;
+        }
+
+        let mut message = i18n::text_with(
+            locale,
+            "password_strength",
+            &[("score", &score), ("min", &min_score)],
+        );
+        // zxcvbn only ships English feedback
+        if locale == Locale::En
+            && let Some(warning) = entropy.feedback().and_then(|feedback| feedback.warning())
+        {
+            message = format!("{}: {}", message, warning);
+        }
+        Some(PasswordViolation::new(RULE_STRENGTH, message))
+    })
+    .await
+    .map_err(|e| {
+        error!("Password strength task failed: {}", e);
+        AuthError::InternalError
+    })
+}
+
+/// SHA-1の先頭5文字のファイルだけを読み、残りの35文字が一覧にあるか調べる。
+/// 一覧を読めない場合は登録を妨げないよう、漏洩していないものとして扱う。
+async fn is_breached(dir: &Path, password: &str) -> bool {
+    let digest = hex::encode_upper(Sha1::digest(password.as_bytes()));
+    let (prefix, suffix) = digest.split_at(5);
+    let path = dir.join(format!("{}.txt", prefix));
+
+    let contents = match tokio::fs::read_to_string(&path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return false,
+        Err(e) => {
+            warn!(path = %path.display(), "Failed to read breached password list: {}", e);
+            return false;
+        }
+    };
+
+    contents.lines().any(|line| {
+        let (hash_suffix, count) = line.trim().split_once(':').unwrap_or((line.trim(), "1"));
+        // Padding entries in the published ranges carry a count of zero
+        hash_suffix.eq_ignore_ascii_case(suffix) && count.trim().parse::<u64>().unwrap_or(1) > 0
+    })
+}
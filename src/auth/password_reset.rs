@@ -0,0 +1,147 @@
+use crate::AppState;
+use crate::auth::client::ClientInfo;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::events;
+use crate::auth::password_policy;
+use crate::auth::token::{generate_token, hash_token};
+use crate::mailer::Mail;
+use crate::models::{AUTH_EVENT_PASSWORD_CHANGE, PasswordResetToken, User};
+use crate::repositories::{PasswordResetTokenRepository, SessionRepository, UserRepository};
+use crate::validation::{Validate, ValidatedJson, ValidationErrors};
+use axum::{extract::State, http::StatusCode};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use tracing::{debug, error, info, instrument};
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+impl Validate for PasswordResetRequest {
+    fn validate(&mut self, errors: &mut ValidationErrors) {
+        errors.email("email", &mut self.email);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordResetConfirmRequest {
+    pub token: String,
+    pub password: String,
+}
+
+impl Validate for PasswordResetConfirmRequest {}
+
+/// パスワードリセット用のリンクをメールで送信する。
+/// メールアドレスの登録有無を推測されないよう、ユーザーが存在しなくても常に200を返す。
+#[instrument(skip(state, request), fields(email = %request.email))]
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    ValidatedJson(request): ValidatedJson<PasswordResetRequest>,
+) -> AuthResult<StatusCode> {
+    let user_repo = UserRepository::new(&state.pool);
+
+    let Some(user) = user_repo.find_by_email(&request.email).await? else {
+        debug!("Password reset requested for unknown email");
+        return Ok(StatusCode::OK);
+    };
+
+    // Answer as for an unknown address so that a failed delivery does not reveal the account
+    if let Err(e) = send_password_reset_mail(&state, &user).await {
+        error!(user_id = %user.id, "Failed to send password reset mail: {}", e);
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// パスワードリセット用のトークンを発行してメールを送信する
+pub async fn send_password_reset_mail(state: &AppState, user: &User) -> AuthResult<()> {
+    let token_repo = PasswordResetTokenRepository::new(&state.pool);
+
+    // Invalidate links that were sent before
+    token_repo.delete_unused_by_user_id(user.id).await?;
+
+    let token = generate_token();
+    let ttl = Duration::minutes(state.config.auth.password_reset_ttl_minutes);
+    token_repo
+        .create(&PasswordResetToken::new(user.id, hash_token(&token), ttl))
+        .await?;
+
+    let link = format!(
+        "{}/password-reset?token={}",
+        state.config.server.base_url(),
+        token
+    );
+    state
+        .mailer
+        .send(&Mail {
+            to: user.email.clone(),
+            subject: "パスワードの再設定".to_string(),
+            body: format!(
+                "以下のリンクからパスワードを再設定してください。\n{}\n\nこのリンクの有効期限は{}分です。",
+                link, state.config.auth.password_reset_ttl_minutes
+            ),
+        })
+        .await?;
+    info!(user_id = %user.id, "Password reset mail sent");
+
+    Ok(())
+}
+
+/// トークンを検証してパスワードを更新し、既存のセッションを全て破棄する。
+#[instrument(skip(state, request))]
+pub async fn confirm_password_reset(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    ValidatedJson(request): ValidatedJson<PasswordResetConfirmRequest>,
+) -> AuthResult<StatusCode> {
+    let user_repo = UserRepository::new(&state.pool);
+    let session_repo = SessionRepository::new(&state.pool);
+    let token_repo = PasswordResetTokenRepository::new(&state.pool);
+
+    let token_hash = hash_token(&request.token);
+
+    // Check the policy before consuming so a rejected password does not burn the link
+    let user = match token_repo
+        .find_valid_user_id(&token_hash, Utc::now())
+        .await?
+    {
+        Some(user_id) => user_repo.find_by_id(user_id).await?,
+        None => None,
+    };
+    let Some(user) = user else {
+        debug!("Password reset failed: invalid or expired token");
+        return Err(AuthError::InvalidToken);
+    };
+    password_policy::validate(
+        &state.config.auth.password_policy,
+        &request.password,
+        &password_policy::email_inputs(&user.email),
+    )
+    .await?;
+
+    let user_id = token_repo
+        .consume(&token_hash, Utc::now())
+        .await?
+        .ok_or_else(|| {
+            debug!("Password reset failed: invalid or expired token");
+            AuthError::InvalidToken
+        })?;
+
+    let password_hash = state.password_hasher.hash(&request.password).await?;
+    user_repo.update_password(user_id, &password_hash).await?;
+
+    // Log out from every device
+    session_repo.delete_by_user_id(user_id).await?;
+    info!(user_id = %user_id, "Password reset completed");
+    events::record(
+        &state,
+        AUTH_EVENT_PASSWORD_CHANGE,
+        Some(user_id),
+        Some("reset"),
+        &client,
+    )
+    .await?;
+
+    Ok(StatusCode::OK)
+}
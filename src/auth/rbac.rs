@@ -0,0 +1,118 @@
+use crate::AppState;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::middleware::AuthenticatedUser;
+use crate::models::ROLE_ADMIN;
+use crate::repositories::{RoleRepository, SessionRepository, UserRepository};
+use crate::validation::normalize_email;
+use axum::{extract::Request, middleware::Next, response::Response};
+use chrono::Utc;
+use tracing::{debug, info, warn};
+
+/// 指定した権限を持つユーザーだけを通すミドルウェア。`session_auth_middleware` の内側に置く
+///
+/// ```ignore
+/// .layer(axum::middleware::from_fn(|request, next| {
+///     require_permission(PERMISSION_USERS_MANAGE, request, next)
+/// }))
+/// ```
+pub async fn require_permission(
+    permission: &'static str,
+    request: Request,
+    next: Next,
+) -> AuthResult<Response> {
+    let auth_user = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .ok_or(AuthError::NotLogined)?;
+    auth_user.require_permission(permission)?;
+
+    Ok(next.run(request).await)
+}
+
+/// メールアドレスで指定したユーザーに管理者ロールを付与する。
+/// `require_verified_email` の場合、メールアドレスを確認済みのユーザーにしか付与しない
+pub async fn grant_admin(
+    state: &AppState,
+    email: &str,
+    require_verified_email: bool,
+) -> anyhow::Result<bool> {
+    let Ok(email) = normalize_email(email) else {
+        warn!(email = %email, "Cannot grant admin role: invalid email");
+        return Ok(false);
+    };
+    let Some(user) = UserRepository::new(&state.pool)
+        .find_by_email(&email)
+        .await?
+    else {
+        warn!(email = %email, "Cannot grant admin role: user not found");
+        return Ok(false);
+    };
+    // Anyone can register with the configured address before its owner does,
+    // so only trust it once the owner has proven control of the mailbox
+    if require_verified_email && user.email_verified_at.is_none() {
+        warn!(user_id = %user.id, "Cannot grant admin role: email is not verified");
+        return Ok(false);
+    }
+
+    if !RoleRepository::new(&state.pool)
+        .assign(user.id, ROLE_ADMIN)
+        .await?
+    {
+        warn!("Cannot grant admin role: role is missing from the database");
+        return Ok(false);
+    }
+    info!(user_id = %user.id, "Admin role granted");
+
+    // Tokens handed out before the privilege change must not carry over to it
+    SessionRepository::new(&state.pool)
+        .rotate_csrf_tokens_by_user_id(user.id, Utc::now())
+        .await?;
+
+    Ok(true)
+}
+
+/// メールアドレスで指定したユーザーから管理者ロールを外す
+pub async fn revoke_admin(state: &AppState, email: &str) -> anyhow::Result<bool> {
+    let Ok(email) = normalize_email(email) else {
+        warn!(email = %email, "Cannot revoke admin role: invalid email");
+        return Ok(false);
+    };
+    let Some(user) = UserRepository::new(&state.pool)
+        .find_by_email(&email)
+        .await?
+    else {
+        warn!(email = %email, "Cannot revoke admin role: user not found");
+        return Ok(false);
+    };
+
+    if !RoleRepository::new(&state.pool)
+        .unassign(user.id, ROLE_ADMIN)
+        .await?
+    {
+        warn!(user_id = %user.id, "Cannot revoke admin role: user is not an admin");
+        return Ok(false);
+    }
+    info!(user_id = %user.id, "Admin role revoked");
+
+    // Tokens issued while the user was an admin must not outlive the privilege
+    SessionRepository::new(&state.pool)
+        .rotate_csrf_tokens_by_user_id(user.id, Utc::now())
+        .await?;
+
+    Ok(true)
+}
+
+impl AuthenticatedUser {
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == permission)
+    }
+
+    /// 権限がなければ403を返す
+    pub fn require_permission(&self, permission: &str) -> AuthResult<()> {
+        if !self.has_permission(permission) {
+            debug!(user_id = %self.user.id, permission = %permission, "Permission denied");
+            return Err(AuthError::PermissionDenied);
+        }
+        Ok(())
+    }
+}
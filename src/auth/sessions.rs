@@ -0,0 +1,128 @@
+use crate::AppState;
+use crate::auth::client::ClientInfo;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::events;
+use crate::auth::middleware::AuthenticatedUser;
+use crate::extract::Path;
+use crate::models::{AUTH_EVENT_SESSION_REVOKED, Session};
+use crate::repositories::SessionRepository;
+use axum::{Extension, Json, extract::State, http::StatusCode};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{info, instrument};
+
+/// ログイン中のセッション（端末）の情報。セッションUUIDやCSRFトークンは含めない
+#[derive(Debug, Serialize)]
+pub struct SessionSummary {
+    pub id: i64,
+    pub device_info: Option<String>,
+    pub ip_address: Option<String>,
+    pub issued_at: DateTime<Utc>,
+    pub last_seen_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// このリクエストを送っているセッションかどうか
+    pub current: bool,
+    /// 管理者によるなりすましのセッションかどうか
+    pub impersonated: bool,
+}
+
+impl SessionSummary {
+    pub fn new(session: Session, current_id: Option<i64>) -> Self {
+        Self {
+            current: Some(session.id) == current_id,
+            impersonated: session.impersonator_user_id.is_some(),
+            id: session.id,
+            device_info: session.device_info,
+            ip_address: session.ip_address,
+            issued_at: session.issued_at,
+            last_seen_at: session.last_seen_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeOthersResponse {
+    pub revoked: u64,
+}
+
+/// セッションの管理はブラウザのセッションからのみ許可する
+fn current_session_id(auth_user: &AuthenticatedUser) -> AuthResult<i64> {
+    auth_user.session_id.ok_or(AuthError::SessionRequired)
+}
+
+/// ログイン中のセッションの一覧
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+) -> AuthResult<Json<Vec<SessionSummary>>> {
+    let current_id = current_session_id(&auth_user)?;
+
+    let sessions = SessionRepository::new(&state.pool)
+        .find_active_by_user_id(
+            auth_user.user.id,
+            Utc::now(),
+            state.config.auth.session.idle_timeout(),
+        )
+        .await?
+        .into_iter()
+        .map(|session| SessionSummary::new(session, Some(current_id)))
+        .collect();
+
+    Ok(Json(sessions))
+}
+
+/// 指定したセッションを失効させる（その端末からログアウトさせる）
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn revoke(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+    Path(id): Path<i64>,
+) -> AuthResult<StatusCode> {
+    current_session_id(&auth_user)?;
+
+    if !SessionRepository::new(&state.pool)
+        .delete_by_id(auth_user.user.id, id)
+        .await?
+    {
+        return Err(AuthError::SessionNotFound);
+    }
+    info!(session_id = %id, "Session revoked");
+    events::record(
+        &state,
+        AUTH_EVENT_SESSION_REVOKED,
+        Some(auth_user.user.id),
+        Some(&format!("session_id={}", id)),
+        &client,
+    )
+    .await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// 現在のセッション以外をすべて失効させる
+#[instrument(skip(state, auth_user, client), fields(user_id = %auth_user.user.id))]
+pub async fn revoke_others(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    client: ClientInfo,
+) -> AuthResult<Json<RevokeOthersResponse>> {
+    let current_id = current_session_id(&auth_user)?;
+
+    let revoked = SessionRepository::new(&state.pool)
+        .delete_others_by_user_id(auth_user.user.id, current_id)
+        .await?;
+    info!(revoked = %revoked, "Other sessions revoked");
+    events::record(
+        &state,
+        AUTH_EVENT_SESSION_REVOKED,
+        Some(auth_user.user.id),
+        Some("others"),
+        &client,
+    )
+    .await?;
+
+    Ok(Json(RevokeOthersResponse { revoked }))
+}
@@ -0,0 +1,117 @@
+use crate::AppState;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::config::LoginThrottleConfig;
+use crate::repositories::LoginAttemptRepository;
+use chrono::{Duration, Utc};
+use std::net::IpAddr;
+use tracing::warn;
+
+const IP_KEY_PREFIX: &str = "ip:";
+
+fn email_key(email: &str) -> String {
+    format!("email:{}", email.trim().to_lowercase())
+}
+
+fn ip_key(ip: IpAddr) -> String {
+    format!("{}{}", IP_KEY_PREFIX, ip)
+}
+
+/// キーごとのロックするまでの失敗回数
+fn max_attempts(config: &LoginThrottleConfig, key: &str) -> i64 {
+    if key.starts_with(IP_KEY_PREFIX) {
+        config.ip_max_attempts
+    } else {
+        config.max_attempts
+    }
+}
+
+fn keys(email: &str, ip: Option<IpAddr>) -> Vec<String> {
+    let mut keys = vec![email_key(email)];
+    keys.extend(ip.map(ip_key));
+    keys
+}
+
+/// メールアドレスまたはIPアドレスがロック中ならエラーを返す
+pub async fn check(state: &AppState, email: &str, ip: Option<IpAddr>) -> AuthResult<()> {
+    check_keys(state, keys(email, ip)).await
+}
+
+/// IPアドレスがロック中ならエラーを返す（メールアドレスが分からない段階で使う）
+pub async fn check_ip(state: &AppState, ip: Option<IpAddr>) -> AuthResult<()> {
+    check_keys(state, ip.map(ip_key).into_iter().collect()).await
+}
+
+async fn check_keys(state: &AppState, keys: Vec<String>) -> AuthResult<()> {
+    let attempt_repo = LoginAttemptRepository::new(&state.pool);
+    let now = Utc::now();
+
+    for key in keys {
+        if let Some(locked_until) = attempt_repo
+            .find_by_key(&key)
+            .await?
+            .and_then(|attempt| attempt.locked_until)
+            && locked_until > now
+        {
+            let retry_after_secs = (locked_until - now).num_seconds() + 1;
+            return Err(AuthError::TooManyAttempts { retry_after_secs });
+        }
+    }
+
+    Ok(())
+}
+
+/// 失敗を記録する。しきい値を超えると、超えた回数に応じて指数的に長くロックする
+pub async fn record_failure(state: &AppState, email: &str, ip: Option<IpAddr>) -> AuthResult<()> {
+    record_failure_keys(state, keys(email, ip)).await
+}
+
+/// IPアドレスについてだけ失敗を記録する
+pub async fn record_ip_failure(state: &AppState, ip: Option<IpAddr>) -> AuthResult<()> {
+    record_failure_keys(state, ip.map(ip_key).into_iter().collect()).await
+}
+
+async fn record_failure_keys(state: &AppState, keys: Vec<String>) -> AuthResult<()> {
+    let config = &state.config.auth.login_throttle;
+    let attempt_repo = LoginAttemptRepository::new(&state.pool);
+    let now = Utc::now();
+
+    for key in keys {
+        let failure_count = match attempt_repo.find_by_key(&key).await? {
+            Some(attempt)
+                if now - attempt.last_failed_at < Duration::minutes(config.window_minutes) =>
+            {
+                attempt.failure_count + 1
+            }
+            _ => 1,
+        };
+
+        let max_attempts = max_attempts(config, &key);
+        let locked_until = (failure_count >= max_attempts).then(|| {
+            let exponent = (failure_count - max_attempts).min(30) as u32;
+            let lockout_secs = config
+                .base_lockout_seconds
+                .saturating_mul(2_i64.saturating_pow(exponent))
+                .min(config.max_lockout_seconds);
+            now + Duration::seconds(lockout_secs)
+        });
+        if locked_until.is_some() {
+            warn!(key = %key, failure_count = %failure_count, "Login locked out");
+        }
+
+        attempt_repo
+            .save(&key, failure_count, now, locked_until)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// ログイン成功時にメールアドレスの失敗記録を消す。
+/// IPアドレスの記録は、正規のアカウントを持つ攻撃者が他人宛ての試行回数を戻せないよう残す
+pub async fn reset(state: &AppState, email: &str) -> AuthResult<()> {
+    LoginAttemptRepository::new(&state.pool)
+        .delete_by_key(&email_key(email))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use crate::auth::errors::{AuthError, AuthResult};
+use chrono::{DateTime, Duration, Utc};
+use rand::{RngCore, rngs::OsRng};
+use sha2::{Digest, Sha256};
+
+/// URLやメールに載せるためのランダムなトークンを生成（32バイトの16進数文字列）
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// データベースに保存するためのトークンのハッシュ値（SHA-256の16進数文字列）
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// 今から `days` 日後の有効期限。Noneの場合は無期限
+pub fn expires_in_days(days: Option<i64>) -> AuthResult<Option<DateTime<Utc>>> {
+    days.map(|days| {
+        Utc::now()
+            .checked_add_signed(Duration::days(days))
+            .ok_or(AuthError::InternalError)
+    })
+    .transpose()
+}
@@ -0,0 +1,332 @@
+use crate::AppState;
+use crate::auth::client::ClientInfo;
+use crate::auth::errors::{AuthError, AuthResult};
+use crate::auth::events;
+use crate::auth::handlers::start_session;
+use crate::auth::middleware::AuthenticatedUser;
+use crate::auth::throttle;
+use crate::auth::token::{generate_token, hash_token};
+use crate::cookie::Cookies;
+use crate::models::{AUTH_EVENT_LOGIN_FAILURE, AUTH_EVENT_LOGIN_SUCCESS, TwoFactorChallenge};
+use crate::repositories::{
+    RecoveryCodeRepository, TotpCredentialRepository, TwoFactorChallengeRepository, UserRepository,
+};
+use crate::validation::{Validate, ValidatedJson};
+use axum::{
+    Extension, Json,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use rand::{RngCore, rngs::OsRng};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, Secret, TOTP};
+use tracing::{debug, error, info, instrument};
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECONDS: u64 = 30;
+const RECOVERY_CODE_COUNT: usize = 10;
+const MAX_CHALLENGE_ATTEMPTS: i64 = 5;
+
+#[derive(Debug, Serialize)]
+pub struct EnrollResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmRequest {
+    pub code: String,
+}
+
+impl Validate for ConfirmRequest {}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisableRequest {
+    pub password: String,
+}
+
+impl Validate for DisableRequest {}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorRequiredResponse {
+    pub two_factor_required: bool,
+    pub challenge_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+impl Validate for TwoFactorLoginRequest {}
+
+fn build_totp(state: &AppState, secret: &str, account_name: &str) -> AuthResult<TOTP> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| {
+            error!("Invalid TOTP secret: {:?}", e);
+            AuthError::InternalError
+        })?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        1,
+        TOTP_STEP_SECONDS,
+        secret,
+        Some(state.config.auth.totp_issuer.clone()),
+        account_name.to_string(),
+    )
+    .map_err(|e| {
+        error!("Failed to build TOTP: {:?}", e);
+        AuthError::InternalError
+    })
+}
+
+/// 前後1ステップまでの時刻ずれを許容してコードを検証し、一致したタイムステップを返す
+fn find_matching_step(totp: &TOTP, code: &str) -> Option<i64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let current = now / TOTP_STEP_SECONDS;
+
+    [current - 1, current, current + 1]
+        .into_iter()
+        .find(|step| totp.generate(step * TOTP_STEP_SECONDS) == code.trim())
+        .map(|step| step as i64)
+}
+
+/// TOTPコードを検証する。同じコードは二度受け付けない
+async fn verify_totp_code(
+    state: &AppState,
+    user_id: i64,
+    secret: &str,
+    code: &str,
+) -> AuthResult<bool> {
+    let totp = build_totp(state, secret, "")?;
+    let Some(step) = find_matching_step(&totp, code) else {
+        return Ok(false);
+    };
+
+    Ok(TotpCredentialRepository::new(&state.pool)
+        .record_used_step(user_id, step)
+        .await?)
+}
+
+fn generate_recovery_code() -> String {
+    let mut bytes = [0u8; 5];
+    OsRng.fill_bytes(&mut bytes);
+    let code = hex::encode(bytes);
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+/// 入力の揺れ（ハイフンの有無、大文字小文字）を吸収してからハッシュ化する
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    hash_token(&normalized)
+}
+
+/// パスワード認証に成功したユーザーに、二要素目の入力を求めるレスポンスを返す
+pub async fn start_two_factor_challenge(state: &AppState, user_id: i64) -> AuthResult<Response> {
+    let token = generate_token();
+    let ttl = Duration::minutes(state.config.auth.two_factor_challenge_ttl_minutes);
+    TwoFactorChallengeRepository::new(&state.pool)
+        .create(&TwoFactorChallenge::new(user_id, hash_token(&token), ttl))
+        .await?;
+    debug!(user_id = %user_id, "Two-factor challenge issued");
+
+    Ok(Json(TwoFactorRequiredResponse {
+        two_factor_required: true,
+        challenge_token: token,
+    })
+    .into_response())
+}
+
+/// 新しいシークレットを発行する（確認されるまでは有効にならない）
+#[instrument(skip(state, auth_user), fields(user_id = %auth_user.user.id))]
+pub async fn enroll(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+) -> AuthResult<Json<EnrollResponse>> {
+    auth_user.require_session()?;
+
+    let totp_repo = TotpCredentialRepository::new(&state.pool);
+
+    if totp_repo
+        .find_confirmed_by_user_id(auth_user.user.id)
+        .await?
+        .is_some()
+    {
+        return Err(AuthError::TwoFactorAlreadyEnabled);
+    }
+
+    let Secret::Encoded(secret) = Secret::generate_secret().to_encoded() else {
+        return Err(AuthError::InternalError);
+    };
+    totp_repo.save_pending(auth_user.user.id, &secret).await?;
+
+    let totp = build_totp(&state, &secret, &auth_user.user.email)?;
+    Ok(Json(EnrollResponse {
+        secret,
+        otpauth_uri: totp.get_url(),
+    }))
+}
+
+/// 最初のコードで登録を確定し、リカバリーコードを発行する
+#[instrument(skip(state, auth_user, request), fields(user_id = %auth_user.user.id))]
+pub async fn confirm(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    ValidatedJson(request): ValidatedJson<ConfirmRequest>,
+) -> AuthResult<Json<RecoveryCodesResponse>> {
+    auth_user.require_session()?;
+
+    let user_id = auth_user.user.id;
+    let totp_repo = TotpCredentialRepository::new(&state.pool);
+    let recovery_repo = RecoveryCodeRepository::new(&state.pool);
+
+    let credential = totp_repo
+        .find_by_user_id(user_id)
+        .await?
+        .ok_or(AuthError::TwoFactorNotEnrolled)?;
+    if credential.confirmed_at.is_some() {
+        return Err(AuthError::TwoFactorAlreadyEnabled);
+    }
+
+    if !verify_totp_code(&state, user_id, &credential.secret, &request.code).await? {
+        debug!("TOTP confirmation failed: invalid code");
+        return Err(AuthError::InvalidTwoFactorCode);
+    }
+
+    let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+    let code_hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|code| hash_recovery_code(code))
+        .collect();
+    recovery_repo.replace_all(user_id, &code_hashes).await?;
+    totp_repo.confirm(user_id).await?;
+    info!("Two-factor authentication enabled");
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+/// 現在のパスワードを確認してから二要素認証を無効化する
+#[instrument(skip(state, auth_user, request), fields(user_id = %auth_user.user.id))]
+pub async fn disable(
+    State(state): State<AppState>,
+    Extension(auth_user): Extension<AuthenticatedUser>,
+    ValidatedJson(request): ValidatedJson<DisableRequest>,
+) -> AuthResult<StatusCode> {
+    auth_user.require_session()?;
+
+    let user_id = auth_user.user.id;
+
+    if !state
+        .password_hasher
+        .verify(&request.password, &auth_user.user.password)
+        .await?
+    {
+        debug!("Disabling two-factor failed: invalid password");
+        return Err(AuthError::InvalidCredentials);
+    }
+
+    TotpCredentialRepository::new(&state.pool)
+        .delete_by_user_id(user_id)
+        .await?;
+    RecoveryCodeRepository::new(&state.pool)
+        .delete_by_user_id(user_id)
+        .await?;
+    info!("Two-factor authentication disabled");
+
+    Ok(StatusCode::OK)
+}
+
+/// ログインの二段階目。TOTPコードまたはリカバリーコードを検証してセッションを発行する
+#[instrument(skip(state, cookies, request))]
+pub async fn verify_login(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    client: ClientInfo,
+    ValidatedJson(request): ValidatedJson<TwoFactorLoginRequest>,
+) -> AuthResult<impl IntoResponse> {
+    let challenge_repo = TwoFactorChallengeRepository::new(&state.pool);
+    let totp_repo = TotpCredentialRepository::new(&state.pool);
+
+    let challenge = challenge_repo
+        .find_valid(&hash_token(&request.challenge_token), Utc::now())
+        .await?
+        .ok_or_else(|| {
+            debug!("Two-factor login failed: invalid or expired challenge");
+            AuthError::InvalidToken
+        })?;
+    let user_id = challenge.user_id;
+    let user = UserRepository::new(&state.pool).get_by_id(user_id).await?;
+
+    // Failed codes count towards the same lockout as failed passwords, across challenges
+    throttle::check(&state, &user.email, client.ip).await?;
+
+    let credential = totp_repo
+        .find_confirmed_by_user_id(user_id)
+        .await?
+        .ok_or(AuthError::TwoFactorNotEnrolled)?;
+
+    let verified = match (&request.code, &request.recovery_code) {
+        (Some(code), _) => verify_totp_code(&state, user_id, &credential.secret, code).await?,
+        (None, Some(recovery_code)) => {
+            RecoveryCodeRepository::new(&state.pool)
+                .consume(user_id, &hash_recovery_code(recovery_code), Utc::now())
+                .await?
+        }
+        (None, None) => false,
+    };
+
+    if !verified {
+        throttle::record_failure(&state, &user.email, client.ip).await?;
+        events::record(
+            &state,
+            AUTH_EVENT_LOGIN_FAILURE,
+            Some(user_id),
+            Some("invalid_two_factor_code"),
+            &client,
+        )
+        .await?;
+        let attempts = challenge_repo.increment_attempts(challenge.id).await?;
+        if attempts >= MAX_CHALLENGE_ATTEMPTS {
+            debug!("Two-factor challenge discarded after too many attempts");
+            challenge_repo.delete(challenge.id).await?;
+        }
+        return Err(AuthError::InvalidTwoFactorCode);
+    }
+
+    // The challenge is single-use even if two requests race
+    if !challenge_repo.delete(challenge.id).await? {
+        return Err(AuthError::InvalidToken);
+    }
+    info!(user_id = %user_id, "Two-factor login succeeded");
+    throttle::reset(&state, &user.email).await?;
+
+    let res = start_session(&state, user_id, &client, &cookies).await?;
+    events::record(
+        &state,
+        AUTH_EVENT_LOGIN_SUCCESS,
+        Some(user_id),
+        Some("two_factor"),
+        &client,
+    )
+    .await?;
+    Ok(res)
+}
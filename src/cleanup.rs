@@ -0,0 +1,82 @@
+use crate::AppState;
+use crate::repositories::{
+    AuthEventRepository, EmailVerificationTokenRepository, LoginAttemptRepository,
+    MagicLinkTokenRepository, OidcLoginStateRepository, PasswordResetTokenRepository,
+    SessionRepository, TwoFactorChallengeRepository, UserRepository, WebauthnChallengeRepository,
+};
+use chrono::{Duration, Utc};
+use tracing::{error, info};
+
+/// 定期的に実行する間隔
+const CLEANUP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// 期限切れのデータを削除する
+pub async fn cleanup(state: &AppState) -> anyhow::Result<()> {
+    let now = Utc::now();
+    let deleted = SessionRepository::new(&state.pool)
+        .delete_expired(now, state.config.auth.session.idle_timeout())
+        .await?;
+    info!(deleted = %deleted, "Expired sessions deleted");
+
+    let deleted = MagicLinkTokenRepository::new(&state.pool)
+        .delete_expired(now)
+        .await?;
+    info!(deleted = %deleted, "Expired magic link tokens deleted");
+
+    let deleted = PasswordResetTokenRepository::new(&state.pool)
+        .delete_expired(now)
+        .await?;
+    info!(deleted = %deleted, "Expired password reset tokens deleted");
+
+    let deleted = EmailVerificationTokenRepository::new(&state.pool)
+        .delete_expired(now)
+        .await?;
+    info!(deleted = %deleted, "Expired email verification tokens deleted");
+
+    let deleted = TwoFactorChallengeRepository::new(&state.pool)
+        .delete_expired(now)
+        .await?;
+    info!(deleted = %deleted, "Expired two-factor challenges deleted");
+
+    let deleted = WebauthnChallengeRepository::new(&state.pool)
+        .delete_expired(now)
+        .await?;
+    info!(deleted = %deleted, "Expired WebAuthn challenges deleted");
+
+    let deleted = OidcLoginStateRepository::new(&state.pool)
+        .delete_expired(now)
+        .await?;
+    info!(deleted = %deleted, "Expired OIDC login states deleted");
+
+    // Failures older than the window no longer count towards a lockout
+    let window = Duration::minutes(state.config.auth.login_throttle.window_minutes);
+    let deleted = LoginAttemptRepository::new(&state.pool)
+        .delete_stale(now, window)
+        .await?;
+    info!(deleted = %deleted, "Stale login attempts deleted");
+
+    let retention = Duration::days(state.config.auth.auth_event_retention_days);
+    let deleted = AuthEventRepository::new(&state.pool)
+        .delete_older_than(now - retention)
+        .await?;
+    info!(deleted = %deleted, "Old auth events deleted");
+
+    let user_repo = UserRepository::new(&state.pool);
+    for user_id in user_repo.find_ids_due_for_deletion(now).await? {
+        user_repo.delete_with_related(user_id).await?;
+        info!(user_id = %user_id, "Account deleted after grace period");
+    }
+
+    Ok(())
+}
+
+/// バックグラウンドで定期的に掃除を行う
+pub async fn run_periodic_cleanup(state: AppState) {
+    let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = cleanup(&state).await {
+            error!("Cleanup failed: {}", e);
+        }
+    }
+}
@@ -1,11 +1,21 @@
+use chrono::Duration;
 use config::{Config, ConfigError, File};
 use serde::Deserialize;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AppConfig {
     pub database: DatabaseConfig,
     pub server: ServerConfig,
     pub debug: Option<DebugConfig>,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub mail: MailConfig,
+    #[serde(default)]
+    pub csrf: CsrfConfig,
+    #[serde(default)]
+    pub cookie: CookieConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -18,6 +28,16 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub base_url: Option<String>,
+    /// リバースプロキシの背後で動かす場合にtrueにする（X-Forwarded-Forをクライアントのアドレスとして扱う）
+    #[serde(default)]
+    pub trust_proxy_headers: bool,
+    /// 信頼するリバースプロキシの段数。X-Forwarded-Forの末尾からこの数だけ戻った位置をクライアントのアドレスとする
+    #[serde(default = "default_trusted_proxy_hops")]
+    pub trusted_proxy_hops: usize,
+}
+
+fn default_trusted_proxy_hops() -> usize {
+    1
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -26,6 +46,280 @@ pub struct DebugConfig {
     pub sleep_millis: u64,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// 新規登録の受付方法
+    pub registration: RegistrationMode,
+    pub password_reset_ttl_minutes: i64,
+    pub email_verification_ttl_hours: i64,
+    /// 再送を受け付けるまでの最小間隔
+    pub email_verification_resend_interval_seconds: i64,
+    /// trueの場合、メールアドレス未確認のユーザーは認証が必要なAPIを利用できない
+    pub require_email_verification: bool,
+    /// 認証アプリに表示されるサービス名
+    pub totp_issuer: String,
+    /// パスワード認証後、二要素目の入力を待つ時間
+    pub two_factor_challenge_ttl_minutes: i64,
+    /// 退会の申請から実際にアカウントを削除するまでの猶予期間（0の場合は即座に削除する）
+    pub account_deletion_grace_days: i64,
+    /// 起動時に管理者ロールを付与するユーザーのメールアドレス（登録済みで、メールアドレスを確認済みのユーザーに限る）
+    pub admin_email: Option<String>,
+    /// 認証イベントの記録を保持する日数。これより古いものは定期的に削除する
+    pub auth_event_retention_days: i64,
+    pub webauthn: WebauthnConfig,
+    pub login_throttle: LoginThrottleConfig,
+    pub password_hash: PasswordHashConfig,
+    pub password_policy: PasswordPolicyConfig,
+    pub session: SessionConfig,
+    pub impersonation: ImpersonationConfig,
+    pub magic_link: MagicLinkConfig,
+    /// OpenID Connectのプロバイダ設定。キーはURLに含めるプロバイダ名
+    pub oidc: HashMap<String, OidcProviderConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoginThrottleConfig {
+    /// 同じメールアドレスでこの回数連続で失敗するとロックする
+    pub max_attempts: i64,
+    /// 同じIPアドレスからこの回数連続で失敗するとロックする。
+    /// NATやプロキシの背後では多くの利用者が同じアドレスを共有するため、メールアドレスより高くする
+    pub ip_max_attempts: i64,
+    /// 最初のロック時間。以後、失敗するたびに倍になる
+    pub base_lockout_seconds: i64,
+    pub max_lockout_seconds: i64,
+    /// 最後の失敗からこの時間が経つと失敗回数を数え直す
+    pub window_minutes: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// ログインからの絶対的な有効期間
+    pub absolute_lifetime_days: i64,
+    /// 最後のアクセスからこの時間が経つとセッションは無効になる
+    pub idle_timeout_minutes: i64,
+    /// 最終アクセス日時を書き込む最小間隔（毎リクエストの書き込みを避ける）
+    pub renewal_interval_minutes: i64,
+}
+
+impl SessionConfig {
+    pub fn absolute_lifetime(&self) -> Duration {
+        Duration::days(self.absolute_lifetime_days)
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::minutes(self.idle_timeout_minutes)
+    }
+
+    pub fn renewal_interval(&self) -> Duration {
+        Duration::minutes(self.renewal_interval_minutes)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ImpersonationConfig {
+    /// なりすましセッションの有効期間
+    pub lifetime_minutes: i64,
+    /// trueの場合、なりすまし中はパスワード変更や退会などアカウントに関わる変更を拒否する
+    pub block_destructive: bool,
+}
+
+impl ImpersonationConfig {
+    pub fn lifetime(&self) -> Duration {
+        Duration::minutes(self.lifetime_minutes)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MagicLinkConfig {
+    /// falseの場合、メールのリンクによるログインを受け付けない
+    pub enabled: bool,
+    pub ttl_minutes: i64,
+    /// 同じユーザーにリンクを再送するまでの最小間隔（間隔内の要求は送信せずに200を返す）
+    pub resend_interval_seconds: i64,
+}
+
+/// Argon2idのパラメータ。変更すると、既存のハッシュは次回ログイン時に作り直される
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordHashConfig {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+/// 新しく設定するパスワードが満たすべき条件
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicyConfig {
+    /// 最小の文字数
+    pub min_length: usize,
+    /// 最大のバイト数。bcryptは72バイトを超える部分を無視するため、既定値は72
+    pub max_bytes: usize,
+    /// zxcvbnの強度スコア（0〜4）の下限
+    pub min_strength_score: u8,
+    /// 漏洩済みパスワードのハッシュを収めたディレクトリ。
+    /// SHA-1の先頭5文字をファイル名（例: `5BAA6.txt`）とし、各行に残りの35文字と出現回数を `SUFFIX:COUNT` の形式で並べる。
+    /// 省略時は照合しない
+    pub breached_passwords_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WebauthnConfig {
+    /// Relying Party ID（通常はドメイン名）
+    pub rp_id: String,
+    /// ブラウザから見たオリジン（スキーム・ホスト・ポート）
+    pub rp_origin: String,
+    /// 認証器に表示されるサービス名
+    pub rp_name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistrationMode {
+    /// 誰でも登録できる
+    Open,
+    /// 管理者が発行した招待コードを持つ人だけが登録できる
+    Invite,
+    /// 新規登録を受け付けない
+    Closed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsrfStrategy {
+    /// セッションに保存したトークンと `X-CSRF-Token` ヘッダを照合する
+    Synchronizer,
+    /// 署名付きのCookieと `X-CSRF-Token` ヘッダが一致するか確認する（データベースを参照しない）
+    DoubleSubmit,
+    /// `Sec-Fetch-Site`（古いブラウザでは `Origin`）ヘッダで同一オリジンからのリクエストか確認する
+    FetchMetadata,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CsrfConfig {
+    /// 使用する方式。複数指定した場合はすべてを満たす必要がある（トークンを使う方式はどちらか一方のみ）
+    pub strategies: Vec<CsrfStrategy>,
+    /// double_submitのトークンに署名する鍵。省略時は起動ごとに生成する（再起動でトークンが無効になる）
+    pub secret: Option<String>,
+    /// fetch_metadataで、`server.base_url` のほかに許可するオリジン
+    pub trusted_origins: Vec<String>,
+    /// trueの場合、fetch_metadataで同じサイトの別オリジン（`Sec-Fetch-Site: same-site`）からのリクエストも許可する
+    pub allow_same_site: bool,
+    /// synchronizerのトークンを更新した後、古いトークンも受け付ける時間（送信中のリクエストを失敗させないため）
+    pub rotation_grace_seconds: i64,
+}
+
+impl CsrfConfig {
+    pub fn rotation_grace(&self) -> Duration {
+        Duration::seconds(self.rotation_grace_seconds)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CookieSameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionIdProtection {
+    /// セッションIDをそのまま入れる
+    Plain,
+    /// HMACで署名し、改ざんされた値を受け付けない
+    Signed,
+    /// AES-GCMで暗号化し、CookieからセッションIDを読めないようにする
+    Encrypted,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CookieConfig {
+    /// セッションIDを入れるCookieの名前
+    pub session_name: String,
+    /// trueの場合、セッションとCSRFトークンのCookie名に `__Host-` を付ける（`secure` が必須で、`domain` は指定できない）
+    pub host_prefix: bool,
+    /// falseの場合、Secure属性を付けない（HTTPでのローカル開発用）
+    pub secure: bool,
+    /// セッションのCookieのSameSite属性
+    pub same_site: CookieSameSite,
+    /// セッションとCSRFトークンのCookieのDomain属性。省略時はリクエストしたホストのみ
+    pub domain: Option<String>,
+    /// セッションのCookieのMax-Ageの上限（秒）。省略時はセッションの残り時間
+    pub max_age_seconds: Option<i64>,
+    pub session_id_protection: SessionIdProtection,
+    /// 署名・暗号化の鍵。省略時は起動ごとに生成する（再起動でログインが切れる）
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    Log,
+    File,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MailConfig {
+    pub transport: MailTransport,
+    pub from: String,
+    pub file_path: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcProviderConfig {
+    /// IDトークンのissと一致する必要がある
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// 省略時は `{issuer}/.well-known/openid-configuration`
+    pub discovery_url: Option<String>,
+    /// 省略時はディスカバリの `jwks_uri` を使う
+    pub jwks_url: Option<String>,
+    /// 省略時は `{base_url}/api/auth/oidc/{provider}/callback`
+    pub redirect_url: Option<String>,
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: Vec<String>,
+    /// trueの場合、未連携のアカウントでログインした時にユーザーを自動作成する
+    #[serde(default)]
+    pub auto_provision: bool,
+}
+
+fn default_oidc_scopes() -> Vec<String> {
+    vec!["openid".to_string(), "email".to_string()]
+}
+
+impl OidcProviderConfig {
+    pub fn discovery_url(&self) -> String {
+        self.discovery_url.clone().unwrap_or_else(|| {
+            format!(
+                "{}/.well-known/openid-configuration",
+                self.issuer.trim_end_matches('/')
+            )
+        })
+    }
+}
+
+impl ServerConfig {
+    /// メール本文などに載せる絶対URLの起点
+    pub fn base_url(&self) -> String {
+        match &self.base_url {
+            Some(base_url) => base_url.trim_end_matches('/').to_string(),
+            None => format!("http://{}:{}", self.host, self.port),
+        }
+    }
+}
+
 impl AppConfig {
     pub fn load() -> Result<Self, ConfigError> {
         let config = Config::builder()
@@ -50,8 +344,149 @@ impl Default for AppConfig {
                 host: "0.0.0.0".to_string(),
                 port: 8080,
                 base_url: None,
+                trust_proxy_headers: false,
+                trusted_proxy_hops: default_trusted_proxy_hops(),
             },
             debug: None,
+            auth: AuthConfig::default(),
+            mail: MailConfig::default(),
+            csrf: CsrfConfig::default(),
+            cookie: CookieConfig::default(),
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            registration: RegistrationMode::Open,
+            password_reset_ttl_minutes: 30,
+            email_verification_ttl_hours: 24,
+            email_verification_resend_interval_seconds: 60,
+            require_email_verification: false,
+            totp_issuer: "Kore Douyo".to_string(),
+            account_deletion_grace_days: 14,
+            admin_email: None,
+            auth_event_retention_days: 90,
+            two_factor_challenge_ttl_minutes: 5,
+            webauthn: WebauthnConfig::default(),
+            login_throttle: LoginThrottleConfig::default(),
+            password_hash: PasswordHashConfig::default(),
+            password_policy: PasswordPolicyConfig::default(),
+            session: SessionConfig::default(),
+            impersonation: ImpersonationConfig::default(),
+            magic_link: MagicLinkConfig::default(),
+            oidc: HashMap::new(),
+        }
+    }
+}
+
+impl Default for LoginThrottleConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            ip_max_attempts: 50,
+            base_lockout_seconds: 30,
+            max_lockout_seconds: 3600,
+            window_minutes: 15,
+        }
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            absolute_lifetime_days: 30,
+            idle_timeout_minutes: 7 * 24 * 60,
+            renewal_interval_minutes: 10,
+        }
+    }
+}
+
+impl Default for ImpersonationConfig {
+    fn default() -> Self {
+        Self {
+            lifetime_minutes: 60,
+            block_destructive: true,
+        }
+    }
+}
+
+impl Default for MagicLinkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_minutes: 10,
+            resend_interval_seconds: 60,
+        }
+    }
+}
+
+impl Default for PasswordHashConfig {
+    fn default() -> Self {
+        // OWASP Password Storage Cheat Sheet recommendation
+        Self {
+            memory_kib: 19456,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl Default for PasswordPolicyConfig {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_bytes: 72,
+            min_strength_score: 2,
+            breached_passwords_dir: None,
+        }
+    }
+}
+
+impl Default for WebauthnConfig {
+    fn default() -> Self {
+        Self {
+            rp_id: "localhost".to_string(),
+            rp_origin: "http://localhost:8080".to_string(),
+            rp_name: "Kore Douyo".to_string(),
+        }
+    }
+}
+
+impl Default for CsrfConfig {
+    fn default() -> Self {
+        Self {
+            strategies: vec![CsrfStrategy::Synchronizer],
+            secret: None,
+            trusted_origins: Vec::new(),
+            allow_same_site: false,
+            rotation_grace_seconds: 60,
+        }
+    }
+}
+
+impl Default for CookieConfig {
+    fn default() -> Self {
+        Self {
+            session_name: "session_id".to_string(),
+            host_prefix: false,
+            secure: true,
+            same_site: CookieSameSite::Lax,
+            domain: None,
+            max_age_seconds: None,
+            session_id_protection: SessionIdProtection::Plain,
+            secret: None,
+        }
+    }
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::Log,
+            from: "no-reply@localhost".to_string(),
+            file_path: "mail.log".to_string(),
         }
     }
 }
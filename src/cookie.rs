@@ -0,0 +1,206 @@
+use crate::config::{AppConfig, CookieConfig, CookieSameSite, SessionIdProtection};
+use crate::csrf::protection::CSRF_COOKIE_NAME;
+use axum::{
+    extract::FromRequestParts,
+    http::{
+        HeaderMap, HeaderValue,
+        header::{COOKIE, SET_COOKIE},
+        request::Parts,
+    },
+    response::Response,
+};
+use cookie::{Cookie, CookieBuilder, CookieJar, Key, SameSite, time::Duration};
+use sha2::{Digest, Sha512};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tracing::warn;
+
+/// この接頭辞の付いたCookieは、Secure・Path=/・Domainなしの場合だけブラウザに保存される
+const HOST_PREFIX: &str = "__Host-";
+
+/// リクエストで送られてきたCookie
+#[derive(Debug, Clone, Default)]
+pub struct Cookies(CookieJar);
+
+impl Cookies {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut jar = CookieJar::new();
+        for value in headers.get_all(COOKIE) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            for cookie in Cookie::split_parse_encoded(value.to_string()).flatten() {
+                jar.add_original(cookie);
+            }
+        }
+        Self(jar)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(Cookie::value)
+    }
+}
+
+impl<S> FromRequestParts<S> for Cookies
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+/// レスポンスにSet-Cookieを追加する
+pub fn set_cookie(res: &mut Response, cookie: Cookie<'static>) {
+    res.headers_mut().append(
+        SET_COOKIE,
+        HeaderValue::from_str(&cookie.encoded().to_string()).unwrap(),
+    );
+}
+
+/// レスポンスが指定した名前のCookieを既に設定しているか
+pub fn sets_cookie(res: &Response, name: &str) -> bool {
+    res.headers().get_all(SET_COOKIE).iter().any(|value| {
+        value
+            .to_str()
+            .is_ok_and(|value| value.split_once('=').is_some_and(|(n, _)| n == name))
+    })
+}
+
+/// 起動時に設定から組み立てるCookieの属性と鍵
+#[derive(Clone)]
+pub struct CookieSettings {
+    config: Arc<CookieConfig>,
+    session_name: Arc<str>,
+    csrf_name: Arc<str>,
+    key: Key,
+}
+
+impl CookieSettings {
+    pub fn new(config: &AppConfig) -> Self {
+        let cookie = &config.cookie;
+        if cookie.host_prefix && (!cookie.secure || cookie.domain.is_some()) {
+            panic!(
+                "Invalid cookie configuration: host_prefix requires secure = true and no domain"
+            );
+        }
+        if cookie.same_site == CookieSameSite::None && !cookie.secure {
+            panic!("Invalid cookie configuration: same_site = \"none\" requires secure = true");
+        }
+
+        let key = match &cookie.secret {
+            // Stretch any passphrase to the 64 bytes the signing and encryption keys are split from
+            Some(secret) => Key::from(&Sha512::digest(secret.as_bytes())),
+            None => {
+                if cookie.session_id_protection != SessionIdProtection::Plain {
+                    warn!("cookie.secret is not set; sessions will not survive a restart");
+                }
+                Key::generate()
+            }
+        };
+
+        let prefix = if cookie.host_prefix { HOST_PREFIX } else { "" };
+        Self {
+            session_name: format!("{}{}", prefix, cookie.session_name).into(),
+            csrf_name: format!("{}{}", prefix, CSRF_COOKIE_NAME).into(),
+            config: Arc::new(cookie.clone()),
+            key,
+        }
+    }
+
+    /// HttpOnly・SameSite=Laxと、設定に応じたSecureを付けたCookie
+    pub fn builder(&self, name: &str, value: impl Into<String>) -> CookieBuilder<'static> {
+        Cookie::build((name.to_string(), value.into()))
+            .http_only(true)
+            .secure(self.config.secure)
+            .same_site(SameSite::Lax)
+            .path("/")
+    }
+
+    /// 有効期限切れにして、ブラウザからCookieを消す
+    pub fn removal(&self, name: &str, path: &str) -> Cookie<'static> {
+        self.builder(name, "")
+            .path(path.to_string())
+            .max_age(Duration::ZERO)
+            .build()
+    }
+
+    fn session_same_site(&self) -> SameSite {
+        match self.config.same_site {
+            CookieSameSite::Strict => SameSite::Strict,
+            CookieSameSite::Lax => SameSite::Lax,
+            CookieSameSite::None => SameSite::None,
+        }
+    }
+
+    /// ホスト全体で使うCookie（セッション・CSRFトークン）。Domainを設定していれば付ける
+    fn site_builder(&self, name: &str, value: impl Into<String>) -> CookieBuilder<'static> {
+        let builder = self.builder(name, value);
+        match &self.config.domain {
+            Some(domain) => builder.domain(domain.clone()),
+            None => builder,
+        }
+    }
+
+    pub fn session_name(&self) -> &str {
+        &self.session_name
+    }
+
+    /// セッションIDを書き込むCookie
+    pub fn session_cookie(&self, session_uuid: &str, max_age_secs: i64) -> Cookie<'static> {
+        let max_age_secs = match self.config.max_age_seconds {
+            Some(limit) => max_age_secs.min(limit),
+            None => max_age_secs,
+        };
+        let cookie = self
+            .site_builder(&self.session_name, session_uuid)
+            .same_site(self.session_same_site())
+            .max_age(Duration::seconds(max_age_secs))
+            .build();
+
+        let mut jar = CookieJar::new();
+        match self.config.session_id_protection {
+            SessionIdProtection::Plain => return cookie,
+            SessionIdProtection::Signed => jar.signed_mut(&self.key).add(cookie),
+            SessionIdProtection::Encrypted => jar.private_mut(&self.key).add(cookie),
+        }
+        jar.get(&self.session_name).cloned().unwrap()
+    }
+
+    /// セッションIDのCookieを消す
+    pub fn session_removal(&self) -> Cookie<'static> {
+        self.site_builder(&self.session_name, "")
+            .same_site(self.session_same_site())
+            .max_age(Duration::ZERO)
+            .build()
+    }
+
+    /// Cookieから取り出したセッションID。署名や暗号化を確認できない値は無視する
+    pub fn session_id(&self, cookies: &Cookies) -> Option<String> {
+        let jar = &cookies.0;
+        let cookie = match self.config.session_id_protection {
+            SessionIdProtection::Plain => jar.get(&self.session_name).cloned(),
+            SessionIdProtection::Signed => jar.signed(&self.key).get(&self.session_name),
+            SessionIdProtection::Encrypted => jar.private(&self.key).get(&self.session_name),
+        }?;
+        Some(cookie.value().to_string()).filter(|value| !value.is_empty())
+    }
+
+    /// double_submitのトークンを書き込むCookie。JavaScriptから読めるようHttpOnlyは付けない
+    pub fn csrf_cookie(&self, token: &str) -> Cookie<'static> {
+        self.site_builder(&self.csrf_name, token)
+            .http_only(false)
+            .same_site(SameSite::Strict)
+            .build()
+    }
+
+    pub fn csrf_name(&self) -> &str {
+        &self.csrf_name
+    }
+
+    pub fn csrf_token<'a>(&self, cookies: &'a Cookies) -> Option<&'a str> {
+        cookies.get(&self.csrf_name)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::error::AppError;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CsrfError {
+    #[error("Missing CSRF token")]
+    MissingToken,
+
+    #[error("Missing session cookie")]
+    MissingSession,
+
+    #[error("Session expired")]
+    SessionExpired,
+
+    #[error("CSRF token mismatch")]
+    TokenMismatch,
+
+    #[error("Missing CSRF cookie")]
+    MissingCookie,
+
+    #[error("Cross-site request")]
+    CrossSiteRequest,
+
+    #[error("Missing Origin header")]
+    MissingOrigin,
+}
+
+impl CsrfError {
+    /// エラーコード・ステータス・メッセージの対応表
+    fn parts(&self) -> (&'static str, StatusCode, &'static str) {
+        match self {
+            CsrfError::MissingToken => (
+                "csrf_token_missing",
+                StatusCode::BAD_REQUEST,
+                "CSRF token is missing",
+            ),
+            CsrfError::MissingSession => (
+                "csrf_session_missing",
+                StatusCode::BAD_REQUEST,
+                "Session cookie is missing",
+            ),
+            CsrfError::SessionExpired => (
+                "csrf_session_expired",
+                StatusCode::UNAUTHORIZED,
+                "Session has expired",
+            ),
+            CsrfError::TokenMismatch => (
+                "csrf_token_mismatch",
+                StatusCode::FORBIDDEN,
+                "CSRF token does not match",
+            ),
+            CsrfError::MissingCookie => (
+                "csrf_cookie_missing",
+                StatusCode::FORBIDDEN,
+                "CSRF cookie is missing",
+            ),
+            CsrfError::CrossSiteRequest => (
+                "csrf_cross_site",
+                StatusCode::FORBIDDEN,
+                "Cross-site request rejected",
+            ),
+            CsrfError::MissingOrigin => (
+                "csrf_origin_missing",
+                StatusCode::FORBIDDEN,
+                "Origin header is missing",
+            ),
+        }
+    }
+
+    /// クライアントが判別に使うエラーコード。変更しないこと
+    pub fn code(&self) -> &'static str {
+        self.parts().0
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.parts().1
+    }
+
+    /// クライアントに返すメッセージ
+    pub fn message(&self) -> &'static str {
+        self.parts().2
+    }
+}
+
+impl IntoResponse for CsrfError {
+    fn into_response(self) -> Response {
+        AppError::from(self).into_response()
+    }
+}
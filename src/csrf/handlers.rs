@@ -0,0 +1,68 @@
+use crate::AppState;
+use crate::auth::errors::AuthError;
+use crate::config::CsrfStrategy;
+use crate::cookie::set_cookie;
+use crate::error::AppResult;
+use crate::models::Session;
+use crate::repositories::SessionRepository;
+use axum::{
+    Extension, Json,
+    extract::State,
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use serde::Serialize;
+use tracing::{debug, instrument};
+
+#[derive(Debug, Serialize)]
+pub struct CsrfTokenResponse {
+    /// `X-CSRF-Token` ヘッダに付けるトークン。トークンを使わない設定（fetch_metadataのみ）の場合はnull
+    pub csrf_token: Option<String>,
+}
+
+/// 新しいCSRFトークンを発行する。synchronizerでは更新前のトークンも猶予期間の間は受け付け、
+/// 猶予期間中に再度呼ばれた場合は更新せずに現在のトークンを返す
+#[instrument(skip_all)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    session: Option<Extension<Session>>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    // The route is exempt from the token check (a lost token is what it recovers from), so
+    // cross-site requests are refused here to stop other sites from forcing a rotation
+    state.csrf.check_fetch_metadata(&headers)?;
+
+    // API tokens are exempt from CSRF checks, so only browser sessions have a token
+    let Extension(session) = session.ok_or(AuthError::SessionRequired)?;
+    let protection = &state.csrf;
+
+    if protection.uses(CsrfStrategy::Synchronizer) {
+        let csrf_token = SessionRepository::new(&state.pool)
+            .refresh_csrf_token(
+                session.id,
+                &Session::generate_csrf_token(),
+                Utc::now(),
+                state.config.csrf.rotation_grace(),
+            )
+            .await?;
+        debug!(session_id = %session.id, "CSRF token refreshed");
+        return Ok(Json(CsrfTokenResponse {
+            csrf_token: Some(csrf_token),
+        })
+        .into_response());
+    }
+
+    if protection.uses(CsrfStrategy::DoubleSubmit) {
+        let csrf_token = protection.issue_double_submit_token(&session.uuid);
+        let cookie = state.cookies.csrf_cookie(&csrf_token);
+        let mut res = Json(CsrfTokenResponse {
+            csrf_token: Some(csrf_token),
+        })
+        .into_response();
+        set_cookie(&mut res, cookie);
+        return Ok(res);
+    }
+
+    Ok(Json(CsrfTokenResponse { csrf_token: None }).into_response())
+}
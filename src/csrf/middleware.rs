@@ -1,57 +1,153 @@
 use crate::AppState;
+use crate::auth::AuthenticatedUser;
+use crate::config::CsrfStrategy;
+use crate::cookie::Cookies;
+use crate::csrf::errors::CsrfError;
+use crate::csrf::protection::tokens_match;
+use crate::error::AppResult;
+use crate::models::Session;
 use crate::repositories::SessionRepository;
 use axum::{
     extract::{Request, State},
-    http::{HeaderMap, Method, StatusCode},
+    http::{HeaderMap, Method},
     middleware::Next,
     response::Response,
 };
+use chrono::Utc;
+use std::sync::Arc;
 
-pub async fn csrf_protection_middleware(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    method: Method,
-    request: Request,
-    next: Next,
-) -> Result<Response, StatusCode> {
-    // Skip CSRF check for GET requests
-    if method == Method::GET {
-        return Ok(next.run(request).await);
+/// ルーターに `csrf_protection_middleware` を付けるときの状態。
+/// 検査を省くルートはルーターごとに `exempt` で宣言する。
+#[derive(Clone)]
+pub struct CsrfGuard {
+    state: AppState,
+    exempt_routes: Arc<Vec<&'static str>>,
+}
+
+impl CsrfGuard {
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            exempt_routes: Arc::new(Vec::new()),
+        }
+    }
+
+    /// 検査を省くルート。ルーターからの相対パスで、`/hooks/{id}` のようにパラメータも書ける
+    pub fn exempt(mut self, route: &'static str) -> Self {
+        Arc::make_mut(&mut self.exempt_routes).push(route);
+        self
+    }
+
+    fn is_exempt(&self, path: &str) -> bool {
+        self.exempt_routes
+            .iter()
+            .any(|route| route_matches(route, path))
     }
+}
+
+/// `{param}` を任意の1セグメントとみなしてパスを照合する
+fn route_matches(route: &str, path: &str) -> bool {
+    let route_segments = route.trim_matches('/').split('/');
+    let path_segments = path.trim_matches('/').split('/');
+    route_segments.clone().count() == path_segments.clone().count()
+        && route_segments
+            .zip(path_segments)
+            .all(|(route, path)| (route.starts_with('{') && route.ends_with('}')) || route == path)
+}
+
+/// RFC 9110で安全と定められたメソッド（サーバの状態を変えない）
+fn is_safe_method(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
 
-    // Get CSRF token from headers
-    let csrf_token = headers
+fn csrf_header(headers: &HeaderMap) -> Result<&str, CsrfError> {
+    headers
         .get("X-CSRF-Token")
         .and_then(|header| header.to_str().ok())
-        .ok_or(StatusCode::BAD_REQUEST)?;
+        .ok_or(CsrfError::MissingToken)
+}
 
-    // Get session cookie
-    let cookie_header = headers
-        .get("Cookie")
-        .and_then(|header| header.to_str().ok())
-        .ok_or(StatusCode::BAD_REQUEST)?;
-
-    let mut session_id: Option<&str> = None;
-    for cookie_pair in cookie_header.split(';') {
-        let cookie_pair = cookie_pair.trim();
-        if let Some(id) = cookie_pair.strip_prefix("session_id=") {
-            session_id = Some(id);
-            break;
-        }
+/// `session_auth_middleware` が読み込んだセッション。なければCookieから探す
+async fn current_session(
+    state: &AppState,
+    session: Option<&Session>,
+    cookies: &Cookies,
+) -> AppResult<Session> {
+    if let Some(session) = session {
+        return Ok(session.clone());
     }
 
-    let session_id = session_id.ok_or(StatusCode::BAD_REQUEST)?;
-    let session_repo = SessionRepository::new(&state.pool);
+    let session_id = state
+        .cookies
+        .session_id(cookies)
+        .ok_or(CsrfError::MissingSession)?;
+    let session = SessionRepository::new(&state.pool)
+        .find_active_by_uuid(
+            &session_id,
+            Utc::now(),
+            state.config.auth.session.idle_timeout(),
+        )
+        .await?
+        .ok_or(CsrfError::SessionExpired)?;
 
-    // Verify CSRF token matches session
-    let session = session_repo
-        .find_by_uuid(session_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+    Ok(session)
+}
 
-    if session.csrf_token != csrf_token {
-        return Err(StatusCode::FORBIDDEN);
+pub async fn csrf_protection_middleware(
+    State(guard): State<CsrfGuard>,
+    request: Request,
+    next: Next,
+) -> AppResult<Response> {
+    if is_safe_method(request.method()) || guard.is_exempt(request.uri().path()) {
+        return Ok(next.run(request).await);
+    }
+
+    // Bearer tokens are not sent automatically by browsers, so they cannot be forged cross-site
+    if request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .is_some_and(|auth_user| auth_user.api_token.is_some())
+    {
+        return Ok(next.run(request).await);
+    }
+
+    let state = &guard.state;
+    let protection = &state.csrf;
+    let headers = request.headers();
+    let session = request.extensions().get::<Session>();
+    let cookies = Cookies::from_headers(headers);
+
+    for strategy in protection.strategies() {
+        match strategy {
+            CsrfStrategy::Synchronizer => {
+                let csrf_token = csrf_header(headers)?;
+                let session = current_session(state, session, &cookies).await?;
+                if !protection.accepts_session_token(&session, csrf_token, Utc::now()) {
+                    return Err(CsrfError::TokenMismatch.into());
+                }
+            }
+            CsrfStrategy::DoubleSubmit => {
+                let csrf_token = csrf_header(headers)?;
+                let cookie_token = state
+                    .cookies
+                    .csrf_token(&cookies)
+                    .ok_or(CsrfError::MissingCookie)?;
+                let session_id = state
+                    .cookies
+                    .session_id(&cookies)
+                    .ok_or(CsrfError::MissingSession)?;
+                // The signature binds the cookie to this session, so a planted cookie is useless
+                if !tokens_match(cookie_token, csrf_token)
+                    || !protection.verify_double_submit_token(&session_id, csrf_token)
+                {
+                    return Err(CsrfError::TokenMismatch.into());
+                }
+            }
+            CsrfStrategy::FetchMetadata => protection.check_fetch_metadata(headers)?,
+        }
     }
 
     Ok(next.run(request).await)
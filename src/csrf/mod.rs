@@ -1 +1,4 @@
+pub mod errors;
+pub mod handlers;
 pub mod middleware;
+pub mod protection;
@@ -0,0 +1,168 @@
+use crate::config::{AppConfig, CsrfStrategy};
+use crate::csrf::errors::CsrfError;
+use crate::models::Session;
+use axum::http::{HeaderMap, header::ORIGIN};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::{RngCore, rngs::OsRng};
+use sha2::Sha256;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tracing::warn;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// double_submitのトークンを入れるCookie（`cookie.host_prefix` が有効な場合は `__Host-` が付く）
+pub const CSRF_COOKIE_NAME: &str = "csrf_token";
+
+/// 起動時に設定から組み立てるCSRF対策の設定と鍵
+#[derive(Clone)]
+pub struct CsrfProtection {
+    strategies: Arc<[CsrfStrategy]>,
+    key: Arc<[u8]>,
+    allowed_origins: Arc<[String]>,
+    allow_same_site: bool,
+    rotation_grace: Duration,
+}
+
+/// 比較にかかる時間から一致した長さを推測されないよう、定数時間で比較する
+pub fn tokens_match(expected: &str, actual: &str) -> bool {
+    expected.as_bytes().ct_eq(actual.as_bytes()).into()
+}
+
+/// URLからオリジン（スキーム・ホスト・ポート）の部分を取り出す
+fn origin_of(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let host_start = url.find("://").map(|i| i + 3).unwrap_or(0);
+    match url[host_start..].find('/') {
+        Some(path_start) => url[..host_start + path_start].to_string(),
+        None => url.to_string(),
+    }
+    .to_ascii_lowercase()
+}
+
+impl CsrfProtection {
+    pub fn new(config: &AppConfig) -> Self {
+        let csrf = &config.csrf;
+        if csrf.strategies.is_empty() {
+            panic!("Invalid csrf configuration: at least one strategy is required");
+        }
+        if csrf.strategies.contains(&CsrfStrategy::Synchronizer)
+            && csrf.strategies.contains(&CsrfStrategy::DoubleSubmit)
+        {
+            panic!(
+                "Invalid csrf configuration: synchronizer and double_submit both use X-CSRF-Token and cannot be combined"
+            );
+        }
+
+        let key: Arc<[u8]> = match &csrf.secret {
+            Some(secret) => secret.as_bytes().into(),
+            None => {
+                if csrf.strategies.contains(&CsrfStrategy::DoubleSubmit) {
+                    warn!(
+                        "csrf.secret is not set; double-submit tokens will not survive a restart"
+                    );
+                }
+                let mut key = [0u8; 32];
+                OsRng.fill_bytes(&mut key);
+                key.into()
+            }
+        };
+
+        let allowed_origins = std::iter::once(config.server.base_url())
+            .chain(csrf.trusted_origins.iter().cloned())
+            .map(|url| origin_of(&url))
+            .collect();
+
+        Self {
+            strategies: csrf.strategies.clone().into(),
+            key,
+            allowed_origins,
+            allow_same_site: csrf.allow_same_site,
+            rotation_grace: csrf.rotation_grace(),
+        }
+    }
+
+    pub fn strategies(&self) -> &[CsrfStrategy] {
+        &self.strategies
+    }
+
+    pub fn uses(&self, strategy: CsrfStrategy) -> bool {
+        self.strategies.contains(&strategy)
+    }
+
+    /// synchronizerのトークンを確認する。更新直後は猶予期間の間だけ更新前のトークンも受け付ける
+    pub fn accepts_session_token(
+        &self,
+        session: &Session,
+        token: &str,
+        now: DateTime<Utc>,
+    ) -> bool {
+        if tokens_match(&session.csrf_token, token) {
+            return true;
+        }
+
+        match (&session.previous_csrf_token, session.csrf_token_rotated_at) {
+            (Some(previous), Some(rotated_at)) => {
+                now < rotated_at + self.rotation_grace && tokens_match(previous, token)
+            }
+            _ => false,
+        }
+    }
+
+    fn mac(&self, session_uuid: &str, nonce: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(session_uuid.as_bytes());
+        mac.update(b"!");
+        mac.update(nonce.as_bytes());
+        mac
+    }
+
+    /// セッションに結び付けた署名付きトークン（`nonce.signature`）を発行する
+    pub fn issue_double_submit_token(&self, session_uuid: &str) -> String {
+        let mut nonce = [0u8; 16];
+        OsRng.fill_bytes(&mut nonce);
+        let nonce = hex::encode(nonce);
+        let signature = hex::encode(self.mac(session_uuid, &nonce).finalize().into_bytes());
+        format!("{}.{}", nonce, signature)
+    }
+
+    /// トークンの署名が、このセッションに対して発行したものか確認する
+    pub fn verify_double_submit_token(&self, session_uuid: &str, token: &str) -> bool {
+        let Some((nonce, signature)) = token.split_once('.') else {
+            return false;
+        };
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        self.mac(session_uuid, nonce)
+            .verify_slice(&signature)
+            .is_ok()
+    }
+
+    /// ブラウザが付けるFetch Metadata（なければOrigin）で、同一オリジンからのリクエストか確認する
+    pub fn check_fetch_metadata(&self, headers: &HeaderMap) -> Result<(), CsrfError> {
+        if let Some(site) = headers
+            .get("Sec-Fetch-Site")
+            .and_then(|value| value.to_str().ok())
+        {
+            return match site {
+                // "none" is a user-initiated navigation such as a bookmark
+                "same-origin" | "none" => Ok(()),
+                "same-site" if self.allow_same_site => Ok(()),
+                _ => Err(CsrfError::CrossSiteRequest),
+            };
+        }
+
+        // Browsers without Fetch Metadata still send Origin on state-changing requests
+        let origin = headers
+            .get(ORIGIN)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(CsrfError::MissingOrigin)?;
+        if self.allowed_origins.contains(&origin_of(origin)) {
+            Ok(())
+        } else {
+            Err(CsrfError::CrossSiteRequest)
+        }
+    }
+}
@@ -12,10 +12,10 @@ pub async fn debug_sleep_middleware(
     request: Request,
     next: Next,
 ) -> Response {
-    if let Some(debug_config) = &config.debug {
-        if debug_config.inject_sleep {
-            sleep(Duration::from_millis(debug_config.sleep_millis)).await;
-        }
+    if let Some(debug_config) = &config.debug
+        && debug_config.inject_sleep
+    {
+        sleep(Duration::from_millis(debug_config.sleep_millis)).await;
     }
 
     next.run(request).await
@@ -0,0 +1,148 @@
+use crate::auth::errors::AuthError;
+use crate::auth::password_policy::PasswordViolation;
+use crate::csrf::errors::CsrfError;
+use crate::i18n;
+use crate::request_id;
+use crate::validation::{FieldError, ValidationError};
+use axum::{
+    http::{
+        HeaderValue, StatusCode,
+        header::{CONTENT_TYPE, RETRY_AFTER},
+    },
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{debug, error};
+
+/// アプリケーション全体のエラー。
+/// どのエラーもRFC 9457の `application/problem+json` として返し、`code` で種類を判別できるようにする。
+#[derive(Error, Debug)]
+pub enum AppError {
+    #[error(transparent)]
+    Auth(#[from] AuthError),
+
+    #[error(transparent)]
+    Csrf(#[from] CsrfError),
+
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+
+    #[error("Not found")]
+    NotFound,
+
+    #[error("Internal error: {0}")]
+    Internal(#[from] anyhow::Error),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+/// RFC 9457のProblem Details。`code` 以降は拡張メンバー
+#[derive(Debug, Serialize)]
+struct ProblemDetails<'a> {
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: &'a str,
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    errors: &'a [FieldError],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    violations: &'a [PasswordViolation],
+}
+
+impl AppError {
+    /// クライアントが判別に使うエラーコード。変更しないこと
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Auth(e) => e.code(),
+            AppError::Csrf(e) => e.code(),
+            AppError::Validation(e) => e.code(),
+            AppError::NotFound => "not_found",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Auth(e) => e.status(),
+            AppError::Csrf(e) => e.status(),
+            AppError::Validation(e) => e.status(),
+            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// クライアントに返すメッセージ。内部の原因は含めない
+    pub fn message(&self) -> &'static str {
+        match self {
+            AppError::Auth(e) => e.message(),
+            AppError::Csrf(e) => e.message(),
+            AppError::Validation(e) => e.message(),
+            AppError::NotFound => "Not found",
+            AppError::Internal(_) => "Internal server error",
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let request_id = request_id::current();
+
+        // The cause of a server-side failure only goes to the log
+        if status.is_server_error() {
+            error!(
+                request_id = request_id.as_deref().unwrap_or_default(),
+                code = self.code(),
+                "Request failed: {}",
+                self
+            );
+        } else {
+            debug!(code = self.code(), "Request rejected: {}", self);
+        }
+
+        let retry_after = match &self {
+            AppError::Auth(AuthError::TooManyAttempts { retry_after_secs }) => {
+                Some(*retry_after_secs)
+            }
+            _ => None,
+        };
+        let errors = match &self {
+            AppError::Validation(ValidationError::Invalid(errors)) => errors.as_slice(),
+            _ => &[],
+        };
+        let violations = match &self {
+            AppError::Auth(AuthError::PasswordPolicyViolation(violations)) => violations.as_slice(),
+            _ => &[],
+        };
+
+        // The catalog is keyed by the same codes; the built-in English text is the fallback
+        let detail = i18n::lookup(i18n::current(), self.code()).unwrap_or(self.message());
+        let problem = ProblemDetails {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or_default(),
+            status: status.as_u16(),
+            detail,
+            code: self.code(),
+            request_id,
+            errors,
+            violations,
+        };
+        let body = serde_json::to_vec(&problem).unwrap_or_default();
+
+        let mut res = (status, body).into_response();
+        res.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        if let Some(retry_after_secs) = retry_after {
+            res.headers_mut()
+                .insert(RETRY_AFTER, retry_after_secs.into());
+        }
+        res
+    }
+}
@@ -0,0 +1,65 @@
+//! axumの `Query` と `Path` の代わりに使うextractor。
+//! 受け付けなかったときにaxumのプレーンテキストではなく、`AppError` のproblem+jsonを返す
+
+use crate::error::AppError;
+use crate::validation::ValidationError;
+use axum::extract::{
+    FromRequestParts,
+    rejection::{PathRejection, QueryRejection},
+};
+use axum::http::request::Parts;
+use serde::de::DeserializeOwned;
+
+/// クエリ文字列を読み込む。不正な場合は `invalid_query` を返す
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+/// パスのパラメータを読み込む。不正な場合は `invalid_path` を返す
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Path<T>(pub T);
+
+impl From<QueryRejection> for AppError {
+    fn from(_: QueryRejection) -> Self {
+        AppError::Validation(ValidationError::InvalidQuery)
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        match rejection {
+            PathRejection::FailedToDeserializePathParams(_) => {
+                AppError::Validation(ValidationError::InvalidPath)
+            }
+            // The route and the handler disagree, which is a bug on our side
+            rejection => AppError::Internal(anyhow::anyhow!(rejection.body_text())),
+        }
+    }
+}
+
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) =
+            axum::extract::Query::<T>::from_request_parts(parts, state).await?;
+        Ok(Self(value))
+    }
+}
+
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Path(value) =
+            axum::extract::Path::<T>::from_request_parts(parts, state).await?;
+        Ok(Self(value))
+    }
+}
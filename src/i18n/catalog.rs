@@ -0,0 +1,334 @@
+use super::Locale;
+
+/// メッセージの一覧。キーはエラーコードなど、クライアントと共有する変わらない文字列。
+/// `{name}` の部分は `text_with` で置き換える
+const MESSAGES: &[(&str, &str, &str)] = &[
+    // (key, ja, en)
+    ("app_title", "これどうよ", "Kore Douyo"),
+    // AuthError
+    (
+        "invalid_credentials",
+        "メールアドレスまたはパスワードが正しくありません",
+        "Invalid credentials",
+    ),
+    (
+        "email_already_exists",
+        "このメールアドレスは既に登録されています",
+        "Email already exists",
+    ),
+    ("not_logged_in", "ログインしていません", "Not logged in"),
+    (
+        "missing_cookie",
+        "Cookieが送信されていません",
+        "Cookie header not found",
+    ),
+    (
+        "missing_session_id",
+        "セッションIDが正しくありません",
+        "Invalid session id",
+    ),
+    (
+        "user_not_found",
+        "ユーザーが見つかりません",
+        "User not found",
+    ),
+    (
+        "invalid_token",
+        "リンクが無効か、有効期限が切れています",
+        "Invalid or expired token",
+    ),
+    (
+        "email_not_verified",
+        "メールアドレスの確認が済んでいません",
+        "Email not verified",
+    ),
+    (
+        "too_many_attempts",
+        "失敗が続いたため一時的にロックしています。しばらくしてから再度お試しください",
+        "Too many failed attempts. Please try again later.",
+    ),
+    (
+        "invalid_two_factor_code",
+        "確認コードが正しくありません",
+        "Invalid two-factor code",
+    ),
+    (
+        "two_factor_already_enabled",
+        "二要素認証は既に有効です",
+        "Two-factor authentication is already enabled",
+    ),
+    (
+        "two_factor_not_enrolled",
+        "二要素認証が設定されていません",
+        "Two-factor authentication is not enrolled",
+    ),
+    (
+        "passkey_verification_failed",
+        "パスキーを確認できませんでした",
+        "Passkey verification failed",
+    ),
+    (
+        "passkey_already_registered",
+        "このパスキーは既に登録されています",
+        "Passkey already registered",
+    ),
+    (
+        "passkey_not_found",
+        "パスキーが見つかりません",
+        "Passkey not found",
+    ),
+    (
+        "api_token_not_found",
+        "APIトークンが見つかりません",
+        "API token not found",
+    ),
+    (
+        "session_not_found",
+        "セッションが見つかりません",
+        "Session not found",
+    ),
+    (
+        "invalid_scope",
+        "APIトークンのスコープが正しくありません",
+        "Invalid API token scope",
+    ),
+    (
+        "insufficient_scope",
+        "APIトークンにこの操作の権限がありません",
+        "Insufficient API token scope",
+    ),
+    (
+        "session_required",
+        "ブラウザからログインして操作してください",
+        "Browser session required",
+    ),
+    (
+        "permission_denied",
+        "この操作を行う権限がありません",
+        "Permission denied",
+    ),
+    (
+        "account_disabled",
+        "このアカウントは無効になっています",
+        "Account disabled",
+    ),
+    (
+        "registration_closed",
+        "現在、新規登録を受け付けていません",
+        "Registration closed",
+    ),
+    (
+        "invalid_invitation",
+        "招待コードが正しくありません",
+        "Invalid invitation code",
+    ),
+    (
+        "invitation_not_found",
+        "招待が見つかりません",
+        "Invitation not found",
+    ),
+    (
+        "magic_link_disabled",
+        "メールのリンクによるログインは利用できません",
+        "Magic link login is disabled",
+    ),
+    (
+        "not_impersonating",
+        "なりすまし中ではありません",
+        "Not impersonating",
+    ),
+    (
+        "impersonation_forbidden",
+        "なりすまし中はこの操作を行えません",
+        "Not allowed while impersonating",
+    ),
+    (
+        "admin_lockout",
+        "自分自身や最後の管理者のアカウントは無効化できません",
+        "You cannot disable your own account or the last administrator",
+    ),
+    (
+        "password_policy_violation",
+        "パスワードが条件を満たしていません",
+        "Password does not meet the policy",
+    ),
+    (
+        "oidc_provider_not_found",
+        "ログインプロバイダが見つかりません",
+        "OIDC provider not found",
+    ),
+    (
+        "oidc_failed",
+        "外部サービスでのログインに失敗しました",
+        "OIDC authentication failed",
+    ),
+    (
+        "internal_error",
+        "サーバでエラーが発生しました",
+        "Internal server error",
+    ),
+    // CsrfError
+    (
+        "csrf_token_missing",
+        "CSRFトークンが送信されていません",
+        "CSRF token is missing",
+    ),
+    (
+        "csrf_session_missing",
+        "セッションのCookieが送信されていません",
+        "Session cookie is missing",
+    ),
+    (
+        "csrf_session_expired",
+        "セッションの有効期限が切れています",
+        "Session has expired",
+    ),
+    (
+        "csrf_token_mismatch",
+        "CSRFトークンが一致しません。ページを再読み込みしてください",
+        "CSRF token does not match",
+    ),
+    (
+        "csrf_cookie_missing",
+        "CSRFトークンのCookieがありません。ページを再読み込みしてください",
+        "CSRF cookie is missing",
+    ),
+    (
+        "csrf_cross_site",
+        "別のサイトからのリクエストは受け付けられません",
+        "Cross-site request rejected",
+    ),
+    (
+        "csrf_origin_missing",
+        "リクエストの送信元を確認できません",
+        "Origin header is missing",
+    ),
+    // ValidationError
+    (
+        "unsupported_media_type",
+        "JSON形式（Content-Type: application/json）で送信してください",
+        "Expected request with `Content-Type: application/json`",
+    ),
+    (
+        "malformed_body",
+        "リクエストの内容がJSONとして正しくありません",
+        "Request body is not valid JSON",
+    ),
+    (
+        "validation_failed",
+        "入力内容に誤りがあります",
+        "Validation failed",
+    ),
+    (
+        "invalid_query",
+        "URLのクエリ文字列が正しくありません",
+        "Query string is malformed",
+    ),
+    (
+        "invalid_path",
+        "URLのパスが正しくありません",
+        "Path parameter is malformed",
+    ),
+    ("not_found", "見つかりません", "Not found"),
+    // Field errors
+    (
+        "field_required",
+        "入力してください",
+        "This field is required",
+    ),
+    (
+        "field_invalid",
+        "形式が正しくありません",
+        "This field is invalid",
+    ),
+    (
+        "email_required",
+        "メールアドレスを入力してください",
+        "Email is required",
+    ),
+    (
+        "email_missing_at",
+        "メールアドレスに@が含まれていません",
+        "Email must contain '@'",
+    ),
+    (
+        "email_local_part_missing",
+        "メールアドレスの@より前が空です",
+        "Email must have a local part",
+    ),
+    (
+        "email_local_part_too_long",
+        "メールアドレスの@より前が長すぎます",
+        "Email local part is too long",
+    ),
+    (
+        "email_local_part_invalid",
+        "メールアドレスに使用できない文字が含まれています",
+        "Email local part contains invalid characters",
+    ),
+    (
+        "email_domain_invalid",
+        "メールアドレスのドメインが正しくありません",
+        "Email domain is invalid",
+    ),
+    (
+        "email_too_long",
+        "メールアドレスが長すぎます",
+        "Email is too long",
+    ),
+    (
+        "expires_in_days_out_of_range",
+        "有効期限は1〜3650日の範囲で指定してください",
+        "Expiry must be between 1 and 3650 days",
+    ),
+    (
+        "max_uses_out_of_range",
+        "使用回数は1〜10000回の範囲で指定してください",
+        "Max uses must be between 1 and 10000",
+    ),
+    // Password policy
+    (
+        "password_min_length",
+        "パスワードは{min}文字以上にしてください",
+        "Password must be at least {min} characters long",
+    ),
+    (
+        "password_max_bytes",
+        "パスワードは{max}バイト以下にしてください",
+        "Password must be at most {max} bytes long",
+    ),
+    (
+        "password_strength",
+        "パスワードが推測されやすいです（強度{score}、{min}以上が必要）",
+        "Password is too weak (score {score} of 4, at least {min} required)",
+    ),
+    (
+        "password_breached",
+        "このパスワードは過去に漏洩したことがあります",
+        "Password has appeared in a data breach",
+    ),
+];
+
+/// キーに対応するメッセージ
+pub fn lookup(locale: Locale, key: &str) -> Option<&'static str> {
+    MESSAGES
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|(_, ja, en)| match locale {
+            Locale::Ja => *ja,
+            Locale::En => *en,
+        })
+}
+
+/// キーに対応するメッセージ。未登録のキーはそのまま返す
+pub fn text(locale: Locale, key: &str) -> &str {
+    lookup(locale, key).unwrap_or(key)
+}
+
+/// `{name}` の部分を置き換えたメッセージ
+pub fn text_with(locale: Locale, key: &str, args: &[(&str, &dyn std::fmt::Display)]) -> String {
+    args.iter()
+        .fold(text(locale, key).to_string(), |message, (name, value)| {
+            message.replace(&format!("{{{}}}", name), &value.to_string())
+        })
+}
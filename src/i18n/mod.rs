@@ -0,0 +1,107 @@
+pub mod catalog;
+
+pub use catalog::{lookup, text, text_with};
+
+use axum::{
+    extract::Request,
+    http::{HeaderValue, header::ACCEPT_LANGUAGE, header::CONTENT_LANGUAGE},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+/// 対応している表示言語
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    /// 利用者の多くは日本語話者のため、判別できない場合は日本語にする
+    #[default]
+    Ja,
+    En,
+}
+
+impl Locale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::Ja => "ja",
+            Locale::En => "en",
+        }
+    }
+
+    /// `ja`・`en-US` のような言語タグから判別する（地域などのサブタグは無視する）
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next().unwrap_or_default();
+        if primary.eq_ignore_ascii_case("ja") {
+            Some(Locale::Ja)
+        } else if primary.eq_ignore_ascii_case("en") {
+            Some(Locale::En)
+        } else {
+            None
+        }
+    }
+
+    /// Accept-Languageヘッダから、品質値が最も高い対応言語を選ぶ
+    pub fn negotiate(accept_language: Option<&str>) -> Self {
+        let Some(accept_language) = accept_language else {
+            return Locale::default();
+        };
+
+        let mut best: Option<(Locale, f32)> = None;
+        for range in accept_language.split(',') {
+            let mut parts = range.split(';');
+            let tag = parts.next().unwrap_or_default();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if quality <= 0.0 {
+                continue;
+            }
+            // Earlier entries win ties, as the header lists them in order of preference
+            if let Some(locale) = Locale::from_tag(tag)
+                && best.is_none_or(|(_, best_quality)| quality > best_quality)
+            {
+                best = Some((locale, quality));
+            }
+        }
+
+        best.map(|(locale, _)| locale).unwrap_or_default()
+    }
+}
+
+tokio::task_local! {
+    static LOCALE: Cell<Locale>;
+}
+
+/// 処理中のリクエストの表示言語
+pub fn current() -> Locale {
+    LOCALE.try_with(Cell::get).unwrap_or_default()
+}
+
+/// ログインユーザーの設定など、Accept-Languageより優先する表示言語に切り替える
+pub fn set_current(locale: Locale) {
+    let _ = LOCALE.try_with(|current| current.set(locale));
+}
+
+/// Accept-Languageから表示言語を決め、レスポンスにContent-Languageを付ける。
+/// ログインユーザーの設定は認証のミドルウェアで上書きする。
+pub async fn locale_middleware(request: Request, next: Next) -> Response {
+    let locale = Locale::negotiate(
+        request
+            .headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok()),
+    );
+
+    LOCALE
+        .scope(Cell::new(locale), async move {
+            let mut response = next.run(request).await;
+            response
+                .headers_mut()
+                .entry(CONTENT_LANGUAGE)
+                .or_insert(HeaderValue::from_static(current().as_str()));
+            response
+        })
+        .await
+}
@@ -0,0 +1,88 @@
+use crate::config::{MailConfig, MailTransport};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// メール送信の抽象。SMTPサーバがない環境でも動くように実装を差し替えられるようにする。
+/// リクエストを処理するスレッドで呼ばれるため、実装はブロックしないこと
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, mail: &Mail) -> Result<()>;
+}
+
+/// ログにメールの内容を出力するだけの実装
+pub struct LogMailer {
+    from: String,
+}
+
+impl LogMailer {
+    pub fn new(from: impl Into<String>) -> Self {
+        Self { from: from.into() }
+    }
+}
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, mail: &Mail) -> Result<()> {
+        info!(
+            from = %self.from,
+            to = %mail.to,
+            subject = %mail.subject,
+            "Sending mail:\n{}",
+            mail.body
+        );
+        Ok(())
+    }
+}
+
+/// ファイルにメールの内容を追記する実装（テストや開発用）
+pub struct FileMailer {
+    from: String,
+    path: String,
+}
+
+impl FileMailer {
+    pub fn new(from: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            from: from.into(),
+            path: path.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, mail: &Mail) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        // One write per mail so that concurrent sends do not interleave
+        let message = format!(
+            "From: {}\nTo: {}\nSubject: {}\n\n{}\n\n",
+            self.from, mail.to, mail.subject, mail.body
+        );
+        file.write_all(message.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// 設定に応じたMailerを生成
+pub fn build_mailer(config: &MailConfig) -> Arc<dyn Mailer> {
+    match config.transport {
+        MailTransport::Log => Arc::new(LogMailer::new(&config.from)),
+        MailTransport::File => Arc::new(FileMailer::new(&config.from, &config.file_path)),
+    }
+}
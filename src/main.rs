@@ -1,80 +1,164 @@
+pub mod admin;
 pub mod auth;
+pub mod cleanup;
 pub mod config;
+pub mod cookie;
 pub mod csrf;
 pub mod debug_middleware;
+pub mod error;
+pub mod extract;
+pub mod i18n;
+pub mod mailer;
 pub mod manifest;
+pub mod migration;
 pub mod models;
+pub mod pagination;
 pub mod repositories;
+pub mod request_id;
+pub mod validation;
 
+#[cfg(test)]
+mod tests;
+
+use auth::password::PasswordHasher;
 use axum::{
     Router,
     extract::State,
-    response::Html,
-    routing::{get, post},
+    response::{Html, IntoResponse, Response},
+    routing::{delete, get, patch, post},
 };
-use config::AppConfig;
+use chrono::Utc;
+use config::{AppConfig, CsrfStrategy};
+use cookie::{CookieSettings, Cookies, set_cookie};
+use csrf::protection::CsrfProtection;
+use mailer::Mailer;
 use maud::{DOCTYPE, html};
-use repositories::SessionRepository;
+use repositories::{InvitationRepository, SessionRepository, UserRepository};
 use sqlx::SqlitePool;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use tower_http::{services::ServeDir, trace::TraceLayer};
-use tracing::info;
+use tracing::{error, info};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use webauthn_rs::Webauthn;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: SqlitePool,
     pub config: AppConfig,
+    pub mailer: Arc<dyn Mailer>,
+    pub webauthn: Arc<Webauthn>,
+    pub http_client: reqwest::Client,
+    pub password_hasher: PasswordHasher,
+    pub csrf: CsrfProtection,
+    pub cookies: CookieSettings,
 }
 
-async fn index(State(state): State<AppState>, req: axum::extract::Request) -> Html<String> {
-    // Try to get CSRF token from session
-    let csrf_token = if let Some(cookie_header) = req.headers().get("Cookie") {
-        if let Ok(cookie_str) = cookie_header.to_str() {
-            // Parse cookies to find session_id
-            let mut session_id: Option<&str> = None;
-            for cookie_pair in cookie_str.split(';') {
-                let cookie_pair = cookie_pair.trim();
-                if let Some(id) = cookie_pair.strip_prefix("session_id=") {
-                    session_id = Some(id);
-                    break;
+async fn index(State(state): State<AppState>, req: axum::extract::Request) -> Response {
+    // Try to get the session to expose its CSRF token
+    let cookies = Cookies::from_headers(req.headers());
+    let session = match state.cookies.session_id(&cookies) {
+        Some(session_id) => SessionRepository::new(&state.pool)
+            .find_active_by_uuid(
+                &session_id,
+                Utc::now(),
+                state.config.auth.session.idle_timeout(),
+            )
+            .await
+            .ok()
+            .flatten(),
+        None => None,
+    };
+
+    // Tell the SPA when an administrator is acting as another user
+    let impersonation = match &session {
+        Some(session) => match session.impersonator_user_id {
+            Some(impersonator_id) => {
+                let user_repo = UserRepository::new(&state.pool);
+                match (
+                    user_repo.find_by_id(session.user_id).await,
+                    user_repo.find_by_id(impersonator_id).await,
+                ) {
+                    (Ok(Some(user)), Ok(Some(impersonator))) => Some((user, impersonator)),
+                    _ => None,
                 }
             }
+            None => None,
+        },
+        None => None,
+    };
 
-            if let Some(session_id) = session_id {
-                let session_repo = SessionRepository::new(&state.pool);
-                if let Ok(Some(session)) = session_repo.find_by_uuid(session_id).await {
-                    Some(session.csrf_token)
-                } else {
-                    None
+    // A logged-in user's language setting takes precedence over Accept-Language
+    let user_locale = match &session {
+        Some(session) => UserRepository::new(&state.pool)
+            .find_by_id(session.impersonator_user_id.unwrap_or(session.user_id))
+            .await
+            .ok()
+            .flatten()
+            .and_then(|user| user.locale)
+            .and_then(|locale| i18n::Locale::from_tag(&locale)),
+        None => None,
+    };
+    let locale = user_locale.unwrap_or_else(i18n::current);
+    i18n::set_current(locale);
+
+    // Hand the SPA the token that the configured CSRF strategy expects in X-CSRF-Token
+    let mut csrf_cookie = None;
+    let csrf_token = match session {
+        Some(session) if state.csrf.uses(CsrfStrategy::Synchronizer) => Some(session.csrf_token),
+        Some(session) if state.csrf.uses(CsrfStrategy::DoubleSubmit) => {
+            // Keep a still-valid cookie so that other open tabs keep working
+            match state
+                .cookies
+                .csrf_token(&cookies)
+                .filter(|token| state.csrf.verify_double_submit_token(&session.uuid, token))
+            {
+                Some(token) => Some(token.to_string()),
+                None => {
+                    let token = state.csrf.issue_double_submit_token(&session.uuid);
+                    csrf_cookie = Some(state.cookies.csrf_cookie(&token));
+                    Some(token)
                 }
-            } else {
-                None
             }
-        } else {
-            None
         }
-    } else {
-        None
+        _ => None,
     };
 
     let markup = html! {
         (DOCTYPE)
-        html {
+        html lang=(locale.as_str()) {
             head {
                 meta charset="utf-8";
                 meta name="viewport" content="width=device-width, initial-scale=1";
                 @if let Some(token) = csrf_token {
                     meta name="csrf-token" content=(token);
                 }
+                @if let Some((user, impersonator)) = &impersonation {
+                    meta name="impersonated-user-email" content=(user.email);
+                    meta name="impersonator-email" content=(impersonator.email);
+                }
                 script defer src={ "/public/" (manifest::javascript_filename()) } {}
-                title { "Kore Douyo" }
+                title { (i18n::text(locale, "app_title")) }
             }
             body {
                 div id="root" {}
             }
         }
     };
-    Html(markup.into_string())
+    let mut res = Html(markup.into_string()).into_response();
+    if let Some(cookie) = csrf_cookie {
+        set_cookie(&mut res, cookie);
+    }
+    res
+}
+
+/// 該当するルートがない場合。APIはProblem Detailsの404を返し、それ以外はSPAのHTMLを返す
+async fn fallback(state: State<AppState>, req: axum::extract::Request) -> Response {
+    if req.uri().path().starts_with("/api/") {
+        return error::AppError::NotFound.into_response();
+    }
+
+    index(state, req).await
 }
 
 async fn get_database_conn_pool(database_url: &str) -> SqlitePool {
@@ -90,47 +174,234 @@ async fn get_database_conn_pool(database_url: &str) -> SqlitePool {
     }
 }
 
-#[tokio::main]
-async fn main() {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-                "kore_douyo=debug,tower_http=debug,axum::rejection=trace,sqlx=debug".into()
-            }),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    // Load configuration
-    let config = AppConfig::load_or_default();
-    info!(
-        "Loaded configuration: Server {}:{}, Database URL: {}",
-        config.server.host, config.server.port, config.database.url
-    );
-
-    // Connect to database
-    info!("Connecting to database...");
-    let pool = get_database_conn_pool(&config.database.url).await;
-    info!("Database connection established");
-
-    // Create app state
-    let state = AppState {
-        pool,
-        config: config.clone(),
-    };
-
+/// APIとSPAのルートをまとめたルーター
+fn router(state: AppState) -> Router {
     // Create auth routes
     let auth_routes = Router::new()
         .route("/register", post(auth::handlers::register))
         .route("/login", post(auth::handlers::login))
+        .route("/login/2fa", post(auth::two_factor::verify_login))
+        .route("/login/passkey/start", post(auth::passkey::start_login))
+        .route("/login/passkey/finish", post(auth::passkey::finish_login))
+        .route("/magic-link/request", post(auth::magic_link::request_link))
+        .route("/magic-link/verify", post(auth::magic_link::verify))
         .route("/logout", post(auth::handlers::logout))
+        .route("/oidc/{provider}/start", get(auth::oidc::start))
+        .route("/oidc/{provider}/callback", get(auth::oidc::callback))
+        .route(
+            "/verify-email",
+            post(auth::email_verification::verify_email),
+        )
+        .route(
+            "/verify-email/resend",
+            post(auth::email_verification::resend_verification_email),
+        )
+        .route(
+            "/password-reset/request",
+            post(auth::password_reset::request_password_reset),
+        )
+        .route(
+            "/password-reset/confirm",
+            post(auth::password_reset::confirm_password_reset),
+        )
+        .with_state(state.clone());
+
+    // Create two-factor management routes (login required)
+    let two_factor_routes = Router::new()
+        .route("/totp/enroll", post(auth::two_factor::enroll))
+        .route("/totp/confirm", post(auth::two_factor::confirm))
+        .route("/totp/disable", post(auth::two_factor::disable))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::deny_during_impersonation,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            csrf::middleware::CsrfGuard::new(state.clone()),
+            csrf::middleware::csrf_protection_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::session_auth_middleware,
+        ))
+        .with_state(state.clone());
+
+    // Create passkey management routes (login required)
+    let passkey_routes = Router::new()
+        .route("/", get(auth::passkey::list))
+        .route("/{id}", delete(auth::passkey::delete))
+        .route("/register/start", post(auth::passkey::start_registration))
+        .route("/register/finish", post(auth::passkey::finish_registration))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::deny_during_impersonation,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            csrf::middleware::CsrfGuard::new(state.clone()),
+            csrf::middleware::csrf_protection_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::session_auth_middleware,
+        ))
+        .with_state(state.clone());
+
+    // Create API token management routes (login required)
+    let api_token_routes = Router::new()
+        .route(
+            "/",
+            get(auth::api_tokens::list).post(auth::api_tokens::create),
+        )
+        .route("/{id}", delete(auth::api_tokens::revoke))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::deny_during_impersonation,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            csrf::middleware::CsrfGuard::new(state.clone()),
+            csrf::middleware::csrf_protection_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::session_auth_middleware,
+        ))
+        .with_state(state.clone());
+
+    // Create current user routes (login required)
+    let account_routes = Router::new()
+        .route("/", get(auth::account::me))
+        .route("/email", patch(auth::account::change_email))
+        .route("/password", patch(auth::account::change_password))
+        .route("/locale", patch(auth::account::change_locale))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::deny_during_impersonation,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            csrf::middleware::CsrfGuard::new(state.clone()),
+            csrf::middleware::csrf_protection_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::session_auth_middleware,
+        ))
+        .with_state(state.clone());
+
+    // Create account deletion and data export routes (login required)
+    let account_deletion_routes = Router::new()
+        .route("/", delete(auth::account::delete_account))
+        .route("/export", get(auth::account::export))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::deny_during_impersonation,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            csrf::middleware::CsrfGuard::new(state.clone()),
+            csrf::middleware::csrf_protection_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::session_auth_middleware,
+        ))
+        .with_state(state.clone());
+
+    // Create authentication activity routes (login required)
+    let activity_routes = Router::new()
+        .route("/", get(auth::events::activity))
+        .layer(axum::middleware::from_fn_with_state(
+            csrf::middleware::CsrfGuard::new(state.clone()),
+            csrf::middleware::csrf_protection_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::session_auth_middleware,
+        ))
+        .with_state(state.clone());
+
+    // Create active session management routes (login required)
+    let session_routes = Router::new()
+        .route("/", get(auth::sessions::list))
+        .route("/{id}", delete(auth::sessions::revoke))
+        .route("/revoke-others", post(auth::sessions::revoke_others))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::deny_during_impersonation,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            csrf::middleware::CsrfGuard::new(state.clone()),
+            csrf::middleware::csrf_protection_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::session_auth_middleware,
+        ))
+        .with_state(state.clone());
+
+    // Create impersonation routes (login required)
+    let impersonation_routes = Router::new()
+        .route("/end", post(admin::impersonation::end))
+        .layer(axum::middleware::from_fn_with_state(
+            csrf::middleware::CsrfGuard::new(state.clone()),
+            csrf::middleware::csrf_protection_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::session_auth_middleware,
+        ))
+        .with_state(state.clone());
+
+    // Create admin routes (users.manage permission required)
+    let admin_routes = Router::new()
+        .route("/users", get(admin::users::list))
+        .route("/users/{id}", get(admin::users::show))
+        .route("/users/{id}/sessions", get(admin::users::sessions))
+        .route("/users/{id}/logout", post(admin::users::force_logout))
+        .route("/users/{id}/disable", post(admin::users::disable))
+        .route("/users/{id}/enable", post(admin::users::enable))
+        .route(
+            "/users/{id}/password-reset",
+            post(admin::users::password_reset),
+        )
+        .route("/users/{id}/impersonate", post(admin::impersonation::start))
+        .route(
+            "/invitations",
+            get(admin::invitations::list).post(admin::invitations::create),
+        )
+        .route("/invitations/{id}", delete(admin::invitations::revoke))
+        .route("/users/{id}/activity", get(admin::users::activity))
+        .route("/audit-logs", get(admin::audit::list))
+        .route("/auth-events", get(admin::audit::auth_events))
+        .layer(axum::middleware::from_fn(|request, next| {
+            auth::rbac::require_permission(models::PERMISSION_USERS_MANAGE, request, next)
+        }))
+        .layer(axum::middleware::from_fn_with_state(
+            csrf::middleware::CsrfGuard::new(state.clone()),
+            csrf::middleware::csrf_protection_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            auth::middleware::session_auth_middleware,
+        ))
         .with_state(state.clone());
 
     // Create board routes
     let board_routes = Router::new()
+        .layer(axum::middleware::from_fn_with_state(
+            csrf::middleware::CsrfGuard::new(state.clone()),
+            csrf::middleware::csrf_protection_middleware,
+        ))
         .layer(axum::middleware::from_fn_with_state(
             state.clone(),
+            auth::middleware::session_auth_middleware,
+        ))
+        .with_state(state.clone());
+
+    // Create CSRF token routes (login required)
+    let csrf_routes = Router::new()
+        // Not a safe method, since it changes the session
+        .route("/", post(csrf::handlers::refresh))
+        .layer(axum::middleware::from_fn_with_state(
+            // A client holding a stale token must still be able to get a new one
+            csrf::middleware::CsrfGuard::new(state.clone()).exempt("/"),
             csrf::middleware::csrf_protection_middleware,
         ))
         .layer(axum::middleware::from_fn_with_state(
@@ -142,23 +413,39 @@ async fn main() {
     // Create public routes (no authentication required)
     let public_routes = Router::new().with_state(state.clone());
 
-    let app = Router::new()
+    Router::new()
         .route("/", get(index))
+        .nest("/api/auth/2fa", two_factor_routes)
+        .nest("/api/auth/passkeys", passkey_routes)
+        .nest("/api/auth/tokens", api_token_routes)
+        .nest("/api/auth/sessions", session_routes)
+        .nest("/api/auth/activity", activity_routes)
+        .nest("/api/auth/me", account_routes)
+        .nest("/api/auth/account", account_deletion_routes)
         .nest("/api/auth", auth_routes)
+        .nest("/api/auth/impersonation", impersonation_routes)
+        .nest("/api/admin", admin_routes)
         .nest("/api/boards", board_routes)
+        .nest("/api/csrf", csrf_routes)
         .nest("/api/shared", public_routes)
         .nest_service("/public", ServeDir::new("frontend/dist"))
-        .fallback(index)
+        .fallback(fallback)
         .with_state(state.clone())
         .layer(axum::middleware::from_fn_with_state(
-            config.clone(),
+            state.config.clone(),
             debug_middleware::debug_sleep_middleware,
         ))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(|request: &axum::extract::Request| {
+                    let request_id = request
+                        .headers()
+                        .get(&request_id::REQUEST_ID_HEADER)
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default();
                     tracing::info_span!(
                         "http_request",
+                        request_id = %request_id,
                         method = %request.method(),
                         uri = %request.uri(),
                         version = ?request.version(),
@@ -174,7 +461,142 @@ async fn main() {
                         tracing::info!(latency = ?latency, "Request completed")
                     },
                 ),
-        );
+        )
+        .layer(axum::middleware::from_fn(i18n::locale_middleware))
+        .layer(axum::middleware::from_fn_with_state(
+            state.config.clone(),
+            request_id::request_id_middleware,
+        ))
+}
+
+#[tokio::main]
+async fn main() {
+    // Initialize tracing
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+                "kore_douyo=debug,tower_http=debug,axum::rejection=trace,sqlx=debug".into()
+            }),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    // Load configuration
+    let config = AppConfig::load_or_default();
+    info!(
+        "Loaded configuration: Server {}:{}, Database URL: {}",
+        config.server.host, config.server.port, config.database.url
+    );
+
+    // Connect to database
+    info!("Connecting to database...");
+    let pool = get_database_conn_pool(&config.database.url).await;
+    info!("Database connection established");
+    migration::seed_roles(&pool)
+        .await
+        .expect("Failed to seed roles");
+
+    // Create app state
+    let state = AppState {
+        pool,
+        config: config.clone(),
+        mailer: mailer::build_mailer(&config.mail),
+        webauthn: Arc::new(auth::passkey::build_webauthn(&config.auth.webauthn)),
+        http_client: reqwest::Client::new(),
+        password_hasher: PasswordHasher::new(&config.auth.password_hash),
+        csrf: CsrfProtection::new(&config),
+        cookies: CookieSettings::new(&config),
+    };
+
+    // `basic-web-app grant-admin <email>` grants the admin role and exits
+    let args: Vec<String> = std::env::args().collect();
+    if let [_, command, email] = args.as_slice()
+        && command == "grant-admin"
+    {
+        match auth::rbac::grant_admin(&state, email, false).await {
+            Ok(true) => {
+                println!("Granted admin role to {}", email);
+                return;
+            }
+            Ok(false) => {
+                eprintln!("Could not grant admin role to {}", email);
+                std::process::exit(1);
+            }
+            Err(e) => panic!("Failed to grant admin role: {}", e),
+        }
+    }
+
+    // `basic-web-app revoke-admin <email>` revokes the admin role and exits
+    if let [_, command, email] = args.as_slice()
+        && command == "revoke-admin"
+    {
+        match auth::rbac::revoke_admin(&state, email).await {
+            Ok(true) => {
+                println!("Revoked admin role from {}", email);
+                return;
+            }
+            Ok(false) => {
+                eprintln!("Could not revoke admin role from {}", email);
+                std::process::exit(1);
+            }
+            Err(e) => panic!("Failed to revoke admin role: {}", e),
+        }
+    }
+
+    // `basic-web-app normalize-emails` rewrites stored addresses into their normalized form.
+    // Run before applying the case-insensitive unique index; it refuses to change anything
+    // while two accounts would collide
+    if let [_, command] = args.as_slice()
+        && command == "normalize-emails"
+    {
+        let report = migration::normalize_user_emails(&state.pool)
+            .await
+            .expect("Failed to normalize emails");
+        for (id, email) in &report.invalid {
+            eprintln!("Invalid email left unchanged: user {} <{}>", id, email);
+        }
+        if !report.collisions.is_empty() {
+            for users in &report.collisions {
+                let users: Vec<String> = users
+                    .iter()
+                    .map(|(id, email)| format!("user {} <{}>", id, email))
+                    .collect();
+                eprintln!("Colliding emails: {}", users.join(", "));
+            }
+            eprintln!("No emails were changed. Resolve the collisions and run again.");
+            std::process::exit(1);
+        }
+        println!("Normalized {} emails", report.updated);
+        return;
+    }
+
+    // `basic-web-app create-invitation <email>` prints a single-use invitation code and exits,
+    // so that the first administrator can sign up when registration is invite-only
+    if let [_, command, email] = args.as_slice()
+        && command == "create-invitation"
+    {
+        let email = validation::normalize_email(email)
+            .unwrap_or_else(|message| panic!("Invalid email: {}", message));
+        let code = auth::token::generate_token();
+        InvitationRepository::new(&state.pool)
+            .create(&auth::token::hash_token(&code), Some(&email), 1, None, None)
+            .await
+            .expect("Failed to create invitation");
+        println!("{}", code);
+        return;
+    }
+
+    // Make sure the configured administrator can manage a fresh deployment
+    if let Some(email) = &config.auth.admin_email
+        && let Err(e) = auth::rbac::grant_admin(&state, email, true).await
+    {
+        error!("Failed to grant admin role: {}", e);
+    }
+
+    // Delete expired data in the background
+    tokio::spawn(cleanup::run_periodic_cleanup(state.clone()));
+
+    let app = router(state);
 
     let bind_address = format!("{}:{}", config.server.host, config.server.port);
     let listener = tokio::net::TcpListener::bind(&bind_address).await.unwrap();
@@ -183,5 +605,10 @@ async fn main() {
         config.server.host, config.server.port
     );
 
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
@@ -0,0 +1,67 @@
+use crate::models::{PERMISSION_USERS_MANAGE, ROLE_ADMIN};
+use crate::repositories::{RoleRepository, UserRepository};
+use crate::validation::normalize_email;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+
+/// 登録済みメールアドレスの正規化の結果
+#[derive(Debug, Default)]
+pub struct EmailNormalizationReport {
+    /// 書き換えたユーザーの数
+    pub updated: usize,
+    /// 正規化できなかったアドレス（書き換えずに残す）
+    pub invalid: Vec<(i64, String)>,
+    /// 正規化すると大文字・小文字を区別せず同じになるアドレスの組。1組でもあれば何も書き換えない
+    pub collisions: Vec<Vec<(i64, String)>>,
+}
+
+/// 登録済みのメールアドレスを正規化する。
+/// `users.email` の一意制約を大文字・小文字を区別しないものに変える前に実行し、
+/// 衝突するアカウントがあれば書き換えずに報告する（どちらを残すかは人が判断する）。
+pub async fn normalize_user_emails(pool: &SqlitePool) -> anyhow::Result<EmailNormalizationReport> {
+    let user_repo = UserRepository::new(pool);
+    let mut report = EmailNormalizationReport::default();
+
+    let mut groups: BTreeMap<String, Vec<(i64, String)>> = BTreeMap::new();
+    let mut updates = Vec::new();
+    for (id, email) in user_repo.find_all_emails().await? {
+        let normalized = match normalize_email(&email) {
+            Ok(normalized) => normalized,
+            Err(_) => {
+                report.invalid.push((id, email.clone()));
+                email.clone()
+            }
+        };
+        if normalized != email {
+            updates.push((id, normalized.clone()));
+        }
+        // Matches the NOCASE collation of the unique index, which only folds ASCII
+        groups
+            .entry(normalized.to_ascii_lowercase())
+            .or_default()
+            .push((id, email));
+    }
+
+    report.collisions = groups
+        .into_values()
+        .filter(|users| users.len() > 1)
+        .collect();
+    if !report.collisions.is_empty() {
+        return Ok(report);
+    }
+
+    user_repo.rewrite_emails(&updates).await?;
+    report.updated = updates.len();
+
+    Ok(report)
+}
+
+/// 組み込みのロールと権限を作成する。sqlite3defはDDLしか扱わないため、起動のたびにここで補う
+pub async fn seed_roles(pool: &SqlitePool) -> anyhow::Result<()> {
+    let role_repo = RoleRepository::new(pool);
+    role_repo.ensure_role(ROLE_ADMIN, "Administrator").await?;
+    role_repo
+        .ensure_permission(ROLE_ADMIN, PERMISSION_USERS_MANAGE, "Manage user accounts")
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+pub const ADMIN_ACTION_SEARCH: &str = "user.search";
+pub const ADMIN_ACTION_VIEW: &str = "user.view";
+pub const ADMIN_ACTION_VIEW_SESSIONS: &str = "user.view_sessions";
+pub const ADMIN_ACTION_VIEW_ACTIVITY: &str = "user.view_activity";
+pub const ADMIN_ACTION_FORCE_LOGOUT: &str = "user.force_logout";
+pub const ADMIN_ACTION_DISABLE: &str = "user.disable";
+pub const ADMIN_ACTION_ENABLE: &str = "user.enable";
+pub const ADMIN_ACTION_PASSWORD_RESET: &str = "user.password_reset";
+pub const ADMIN_ACTION_IMPERSONATE_START: &str = "user.impersonate_start";
+pub const ADMIN_ACTION_IMPERSONATE_END: &str = "user.impersonate_end";
+pub const ADMIN_ACTION_INVITATION_CREATE: &str = "invitation.create";
+pub const ADMIN_ACTION_INVITATION_REVOKE: &str = "invitation.revoke";
+
+/// 管理者の操作の記録
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AdminAuditLog {
+    pub id: i64,
+    /// 操作した管理者（削除された場合はNone）
+    pub admin_user_id: Option<i64>,
+    pub action: String,
+    /// 操作対象のユーザー（削除された場合はNone）
+    pub target_user_id: Option<i64>,
+    pub details: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
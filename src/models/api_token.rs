@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+pub const API_TOKEN_SCOPE_READ: &str = "read";
+pub const API_TOKEN_SCOPE_WRITE: &str = "write";
+pub const API_TOKEN_SCOPES: [&str; 2] = [API_TOKEN_SCOPE_READ, API_TOKEN_SCOPE_WRITE];
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    /// 一覧でトークンを見分けるための先頭部分（平文）
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    /// スペース区切りのスコープ
+    pub scopes: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiToken {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.split_whitespace().any(|s| s == scope)
+    }
+}
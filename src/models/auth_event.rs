@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+pub const AUTH_EVENT_REGISTER: &str = "register";
+pub const AUTH_EVENT_LOGIN_SUCCESS: &str = "login_success";
+pub const AUTH_EVENT_LOGIN_FAILURE: &str = "login_failure";
+pub const AUTH_EVENT_LOGOUT: &str = "logout";
+pub const AUTH_EVENT_PASSWORD_CHANGE: &str = "password_change";
+pub const AUTH_EVENT_SESSION_REVOKED: &str = "session_revoked";
+/// 外部アカウント（OIDC）をログイン中のユーザーに連携した
+pub const AUTH_EVENT_IDENTITY_LINKED: &str = "identity_linked";
+/// 認証済みのリクエストを拒否した（無効化されたアカウント・スコープ不足など）
+pub const AUTH_EVENT_ACCESS_DENIED: &str = "access_denied";
+
+/// 認証に関するイベントの記録
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AuthEvent {
+    pub id: i64,
+    /// 存在しないアカウントへのログイン失敗などではNone
+    pub user_id: Option<i64>,
+    pub event_type: String,
+    /// ログイン方法や失敗の理由などの補足
+    pub details: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct EmailVerificationToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub token_hash: String,
+    /// 確認後に切り替える新しいメールアドレス。登録済みのアドレスの確認の場合はNone
+    pub new_email: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl EmailVerificationToken {
+    pub fn new(user_id: i64, token_hash: String, new_email: Option<String>, ttl: Duration) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: 0, // will be set by database
+            user_id,
+            token_hash,
+            new_email,
+            expires_at: now + ttl,
+            created_at: now,
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// 招待コード。招待制で登録する際に必要になる
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Invitation {
+    pub id: i64,
+    #[serde(skip_serializing)]
+    pub code_hash: String,
+    /// 指定されている場合、このメールアドレスでしか登録できない
+    pub email: Option<String>,
+    pub max_uses: i64,
+    pub use_count: i64,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_by_user_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+/// ログイン失敗の記録。keyは `email:<メールアドレス>` または `ip:<IPアドレス>`
+#[derive(Debug, Clone, FromRow)]
+pub struct LoginAttempt {
+    pub id: i64,
+    pub key: String,
+    pub failure_count: i64,
+    pub last_failed_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
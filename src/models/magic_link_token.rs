@@ -0,0 +1,29 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+
+/// メールで送るログイン用リンクのトークン
+#[derive(Debug, Clone, FromRow)]
+pub struct MagicLinkToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub token_hash: String,
+    /// リンクを要求したブラウザに渡したCookieのハッシュ。別のブラウザでは使えない
+    pub binding_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MagicLinkToken {
+    pub fn new(user_id: i64, token_hash: String, binding_hash: String, ttl: Duration) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: 0, // will be set by database
+            user_id,
+            token_hash,
+            binding_hash,
+            expires_at: now + ttl,
+            created_at: now,
+        }
+    }
+}
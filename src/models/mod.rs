@@ -1,5 +1,37 @@
+pub mod admin_audit_log;
+pub mod api_token;
+pub mod auth_event;
+pub mod email_verification_token;
+pub mod invitation;
+pub mod login_attempt;
+pub mod magic_link_token;
+pub mod oidc_identity;
+pub mod oidc_login_state;
+pub mod passkey_credential;
+pub mod password_reset_token;
+pub mod recovery_code;
+pub mod role;
 pub mod session;
+pub mod totp_credential;
+pub mod two_factor_challenge;
 pub mod user;
+pub mod webauthn_challenge;
 
+pub use admin_audit_log::*;
+pub use api_token::*;
+pub use auth_event::*;
+pub use email_verification_token::*;
+pub use invitation::*;
+pub use login_attempt::*;
+pub use magic_link_token::*;
+pub use oidc_identity::*;
+pub use oidc_login_state::*;
+pub use passkey_credential::*;
+pub use password_reset_token::*;
+pub use recovery_code::*;
+pub use role::*;
 pub use session::*;
+pub use totp_credential::*;
+pub use two_factor_challenge::*;
 pub use user::*;
+pub use webauthn_challenge::*;
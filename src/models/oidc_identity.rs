@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+/// 外部IDプロバイダのアカウント（provider + subject）とユーザーの紐付け
+#[derive(Debug, Clone, FromRow)]
+pub struct OidcIdentity {
+    pub id: i64,
+    pub user_id: i64,
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
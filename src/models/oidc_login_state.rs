@@ -0,0 +1,36 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+
+/// 認可リクエストからコールバックまでの間に保持する値
+#[derive(Debug, Clone, FromRow)]
+pub struct OidcLoginState {
+    pub id: i64,
+    pub provider: String,
+    pub state_hash: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OidcLoginState {
+    pub fn new(
+        provider: &str,
+        state_hash: String,
+        nonce: String,
+        code_verifier: String,
+        ttl: Duration,
+    ) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: 0, // will be set by database
+            provider: provider.to_string(),
+            state_hash,
+            nonce,
+            code_verifier,
+            expires_at: now + ttl,
+            created_at: now,
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct PasskeyCredential {
+    pub id: i64,
+    pub user_id: i64,
+    /// 認証器が発行したクレデンシャルID（16進数）
+    pub credential_id: String,
+    /// COSE形式の公開鍵（JSON）
+    #[serde(skip_serializing)]
+    pub public_key: String,
+    /// webauthn-rsのPasskeyをシリアライズしたもの。検証にはこちらを使う
+    #[serde(skip_serializing)]
+    pub passkey: String,
+    pub sign_count: i64,
+    pub name: Option<String>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,27 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct PasswordResetToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PasswordResetToken {
+    pub fn new(user_id: i64, token_hash: String, ttl: Duration) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: 0, // will be set by database
+            user_id,
+            token_hash,
+            expires_at: now + ttl,
+            used_at: None,
+            created_at: now,
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct RecoveryCode {
+    pub id: i64,
+    pub user_id: i64,
+    pub code_hash: String,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+pub const ROLE_ADMIN: &str = "admin";
+
+/// ユーザーの管理（一覧・無効化・セッションの強制失効など）
+pub const PERMISSION_USERS_MANAGE: &str = "users.manage";
+
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Role {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use sqlx::FromRow;
 use uuid::Uuid;
 
@@ -8,33 +8,74 @@ pub struct Session {
     pub user_id: i64,
     pub uuid: String,
     pub csrf_token: String,
+    /// 更新前のCSRFトークン。更新から猶予期間の間だけ受け付ける
+    pub previous_csrf_token: Option<String>,
+    pub csrf_token_rotated_at: Option<DateTime<Utc>>,
     pub issued_at: DateTime<Utc>,
     pub device_info: Option<String>,
     pub ip_address: Option<String>,
+    /// 最後にリクエストを受けた日時（アイドルタイムアウトの判定に使う）
+    pub last_seen_at: Option<DateTime<Utc>>,
+    /// 絶対的な有効期限。延長されない
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 管理者がなりすましている場合、その管理者のユーザーID
+    pub impersonator_user_id: Option<i64>,
+    /// なりすましを終えたときに戻る管理者自身のセッションID
+    pub impersonator_session_id: Option<i64>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
 impl Session {
-    pub fn new(user_id: i64, device_info: Option<String>, ip_address: Option<String>) -> Self {
+    pub fn new(
+        user_id: i64,
+        device_info: Option<String>,
+        ip_address: Option<String>,
+        lifetime: Duration,
+    ) -> Self {
         let session_uuid = Uuid::new_v4().to_string();
-        let csrf_token_uuid = Uuid::new_v4().to_string();
         let now = Utc::now();
 
         Self {
             id: 0, // will be set by database
             user_id,
             uuid: session_uuid,
-            csrf_token: csrf_token_uuid,
+            csrf_token: Self::generate_csrf_token(),
+            previous_csrf_token: None,
+            csrf_token_rotated_at: None,
             issued_at: now,
             device_info,
             ip_address,
+            last_seen_at: Some(now),
+            expires_at: Some(now + lifetime),
+            impersonator_user_id: None,
+            impersonator_session_id: None,
             created_at: now,
             updated_at: now,
         }
     }
 
+    /// synchronizerで使うCSRFトークンを生成する
+    pub fn generate_csrf_token() -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    /// 管理者によるなりすましのセッションにする
+    pub fn impersonated_by(mut self, admin_user_id: i64, admin_session_id: i64) -> Self {
+        self.impersonator_user_id = Some(admin_user_id);
+        self.impersonator_session_id = Some(admin_session_id);
+        self
+    }
+
     pub fn get_session_uuid(&self) -> &str {
         &self.uuid
     }
+
+    /// Cookieに設定するMax-Age（秒）。アイドルタイムアウトと絶対的な有効期限の早い方
+    pub fn cookie_max_age(&self, now: DateTime<Utc>, idle_timeout: Duration) -> i64 {
+        let remaining = self
+            .expires_at
+            .map_or(Duration::zero(), |expires_at| expires_at - now);
+        remaining.min(idle_timeout).num_seconds().max(0)
+    }
 }
@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct TotpCredential {
+    pub id: i64,
+    pub user_id: i64,
+    /// Base32エンコードされた共有シークレット
+    pub secret: String,
+    /// 最初のコードで確認されるまではNone（登録途中）
+    pub confirmed_at: Option<DateTime<Utc>>,
+    /// 最後に受け付けたタイムステップ（同じコードの再利用を防ぐ）
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
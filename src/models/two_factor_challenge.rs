@@ -0,0 +1,28 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+
+/// パスワード認証は済んだが、二要素目の入力を待っている状態
+#[derive(Debug, Clone, FromRow)]
+pub struct TwoFactorChallenge {
+    pub id: i64,
+    pub user_id: i64,
+    pub token_hash: String,
+    pub attempts: i64,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TwoFactorChallenge {
+    pub fn new(user_id: i64, token_hash: String, ttl: Duration) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: 0, // will be set by database
+            user_id,
+            token_hash,
+            attempts: 0,
+            expires_at: now + ttl,
+            created_at: now,
+        }
+    }
+}
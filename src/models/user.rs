@@ -8,6 +8,13 @@ pub struct User {
     pub email: String,
     #[serde(skip_serializing)]
     pub password: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// 退会を申請している場合、アカウントを削除する日時
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
+    /// 管理者によって無効化された日時
+    pub disabled_at: Option<DateTime<Utc>>,
+    /// 表示言語の設定（`ja` または `en`）。未設定の場合はブラウザのAccept-Languageに従う
+    pub locale: Option<String>,
     pub updated_at: DateTime<Utc>,
     pub created_at: DateTime<Utc>,
 }
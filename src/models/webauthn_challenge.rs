@@ -0,0 +1,39 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+
+pub const WEBAUTHN_CEREMONY_REGISTRATION: &str = "registration";
+pub const WEBAUTHN_CEREMONY_AUTHENTICATION: &str = "authentication";
+
+/// WebAuthnの登録・認証の途中状態。開始から完了までサーバ側に保持する
+#[derive(Debug, Clone, FromRow)]
+pub struct WebauthnChallenge {
+    pub id: i64,
+    pub user_id: i64,
+    pub ceremony: String,
+    pub token_hash: String,
+    pub state: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl WebauthnChallenge {
+    pub fn new(
+        user_id: i64,
+        ceremony: &str,
+        token_hash: String,
+        state: String,
+        ttl: Duration,
+    ) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: 0, // will be set by database
+            user_id,
+            ceremony: ceremony.to_string(),
+            token_hash,
+            state,
+            expires_at: now + ttl,
+            created_at: now,
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! 一覧APIの `page` と `per_page` の解釈
+
+/// これより後ろのページは要求されても最後のページとして扱う（OFFSETの桁あふれを防ぐ）
+const MAX_PAGE: i64 = 100_000;
+
+/// クランプ済みのページ位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    /// 1始まりのページ番号
+    pub number: i64,
+    pub per_page: i64,
+}
+
+impl Page {
+    /// 未指定の値には既定値を使い、範囲外の値は範囲内に収める
+    pub fn new(
+        page: Option<i64>,
+        per_page: Option<i64>,
+        default_per_page: i64,
+        max_per_page: i64,
+    ) -> Self {
+        Self {
+            number: page.unwrap_or(1).clamp(1, MAX_PAGE),
+            per_page: per_page.unwrap_or(default_per_page).clamp(1, max_per_page),
+        }
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.per_page
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.number - 1).saturating_mul(self.per_page)
+    }
+}
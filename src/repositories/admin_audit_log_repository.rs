@@ -0,0 +1,59 @@
+use crate::models::AdminAuditLog;
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+pub struct AdminAuditLogRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> AdminAuditLogRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    const SELECT_FIELDS: &'static str = "SELECT id, admin_user_id, action, target_user_id, details, ip_address, created_at FROM admin_audit_logs";
+
+    /// 操作を記録
+    pub async fn create(
+        &self,
+        admin_user_id: i64,
+        action: &str,
+        target_user_id: Option<i64>,
+        details: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO admin_audit_logs (admin_user_id, action, target_user_id, details, ip_address) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(admin_user_id)
+        .bind(action)
+        .bind(target_user_id)
+        .bind(details)
+        .bind(ip_address)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// 新しい順に取得（ページング）。対象ユーザーで絞り込める
+    pub async fn find_recent(
+        &self,
+        target_user_id: Option<i64>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<AdminAuditLog>> {
+        let logs = sqlx::query_as::<_, AdminAuditLog>(&format!(
+            "{} WHERE ? IS NULL OR target_user_id = ? ORDER BY id DESC LIMIT ? OFFSET ?",
+            Self::SELECT_FIELDS
+        ))
+        .bind(target_user_id)
+        .bind(target_user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(logs)
+    }
+}
@@ -0,0 +1,104 @@
+use crate::models::ApiToken;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+pub struct ApiTokenRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> ApiTokenRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    const SELECT_FIELDS: &'static str = "SELECT id, user_id, name, prefix, token_hash, scopes, expires_at, last_used_at, created_at FROM api_tokens";
+
+    /// トークンを保存
+    pub async fn create(
+        &self,
+        user_id: i64,
+        name: &str,
+        prefix: &str,
+        token_hash: &str,
+        scopes: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO api_tokens (user_id, name, prefix, token_hash, scopes, expires_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(prefix)
+        .bind(token_hash)
+        .bind(scopes)
+        .bind(expires_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// IDで検索
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<ApiToken>> {
+        let token = sqlx::query_as::<_, ApiToken>(&format!("{} WHERE id = ?", Self::SELECT_FIELDS))
+            .bind(id)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(token)
+    }
+
+    /// 有効期限内のトークンをハッシュで検索
+    pub async fn find_valid_by_hash(
+        &self,
+        token_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<ApiToken>> {
+        let token = sqlx::query_as::<_, ApiToken>(&format!(
+            "{} WHERE token_hash = ? AND (expires_at IS NULL OR expires_at > ?)",
+            Self::SELECT_FIELDS
+        ))
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// ユーザーのトークン一覧
+    pub async fn find_by_user_id(&self, user_id: i64) -> Result<Vec<ApiToken>> {
+        let tokens = sqlx::query_as::<_, ApiToken>(&format!(
+            "{} WHERE user_id = ? ORDER BY id",
+            Self::SELECT_FIELDS
+        ))
+        .bind(user_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    /// 最終利用日時を更新
+    pub async fn touch(&self, id: i64, now: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE api_tokens SET last_used_at = ? WHERE id = ?")
+            .bind(now)
+            .bind(id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// ユーザーのトークンを削除（失効）。該当するものがなければfalseを返す
+    pub async fn delete(&self, user_id: i64, id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
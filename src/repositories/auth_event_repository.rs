@@ -0,0 +1,105 @@
+use crate::models::AuthEvent;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+pub struct AuthEventRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> AuthEventRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    const SELECT_FIELDS: &'static str = "SELECT id, user_id, event_type, details, ip_address, user_agent, created_at FROM auth_events";
+
+    /// イベントを記録
+    pub async fn create(
+        &self,
+        user_id: Option<i64>,
+        event_type: &str,
+        details: Option<&str>,
+        ip_address: Option<&str>,
+        user_agent: Option<&str>,
+        created_at: DateTime<Utc>,
+    ) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO auth_events (user_id, event_type, details, ip_address, user_agent, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(user_id)
+        .bind(event_type)
+        .bind(details)
+        .bind(ip_address)
+        .bind(user_agent)
+        .bind(created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// ユーザーのイベントを新しい順に取得（ページング）
+    pub async fn find_by_user_id(
+        &self,
+        user_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<AuthEvent>> {
+        let events = sqlx::query_as::<_, AuthEvent>(&format!(
+            "{} WHERE user_id = ? ORDER BY id DESC LIMIT ? OFFSET ?",
+            Self::SELECT_FIELDS
+        ))
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// ユーザーのイベントをすべて古い順に取得（エクスポート用）
+    pub async fn find_all_by_user_id(&self, user_id: i64) -> Result<Vec<AuthEvent>> {
+        let events = sqlx::query_as::<_, AuthEvent>(&format!(
+            "{} WHERE user_id = ? ORDER BY id",
+            Self::SELECT_FIELDS
+        ))
+        .bind(user_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// 全ユーザーのイベントを新しい順に取得（ページング）。種類で絞り込める
+    pub async fn find_recent(
+        &self,
+        event_type: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<AuthEvent>> {
+        let events = sqlx::query_as::<_, AuthEvent>(&format!(
+            "{} WHERE ? IS NULL OR event_type = ? ORDER BY id DESC LIMIT ? OFFSET ?",
+            Self::SELECT_FIELDS
+        ))
+        .bind(event_type)
+        .bind(event_type)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// 指定した日時より古いイベントを削除
+    pub async fn delete_older_than(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM auth_events WHERE created_at < ?")
+            .bind(before)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
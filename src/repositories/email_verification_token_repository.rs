@@ -0,0 +1,85 @@
+use crate::models::EmailVerificationToken;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+pub struct EmailVerificationTokenRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> EmailVerificationTokenRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// トークンを保存
+    pub async fn create(&self, token: &EmailVerificationToken) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO email_verification_tokens (user_id, token_hash, new_email, expires_at, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(token.user_id)
+        .bind(&token.token_hash)
+        .bind(&token.new_email)
+        .bind(token.expires_at)
+        .bind(token.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// 有効なトークンを削除して返す（一度しか成功しない）
+    pub async fn consume(
+        &self,
+        token_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<EmailVerificationToken>> {
+        let token = sqlx::query_as(
+            "DELETE FROM email_verification_tokens WHERE token_hash = ? AND expires_at > ? RETURNING id, user_id, token_hash, new_email, expires_at, created_at",
+        )
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// 登録済みのアドレスの確認用に、ユーザーに最後にトークンを発行した日時
+    pub async fn latest_created_at_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let created_at: Option<(DateTime<Utc>,)> = sqlx::query_as(
+            "SELECT created_at FROM email_verification_tokens WHERE user_id = ? AND new_email IS NULL ORDER BY created_at DESC LIMIT 1",
+        )
+        .bind(user_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(created_at.map(|(created_at,)| created_at))
+    }
+
+    /// ユーザーのトークンのうち、同じ用途（登録済みのアドレスの確認か、アドレスの変更か）のものを削除
+    pub async fn delete_by_user_id(&self, user_id: i64, email_change: bool) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM email_verification_tokens WHERE user_id = ? AND (new_email IS NOT NULL) = ?",
+        )
+        .bind(user_id)
+        .bind(email_change)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 期限切れのトークンを削除
+    pub async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM email_verification_tokens WHERE expires_at <= ?")
+            .bind(now)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,69 @@
+use crate::models::Invitation;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+pub struct InvitationRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> InvitationRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    const SELECT_FIELDS: &'static str = "SELECT id, code_hash, email, max_uses, use_count, expires_at, created_by_user_id, created_at FROM invitations";
+
+    /// 招待コードを保存
+    pub async fn create(
+        &self,
+        code_hash: &str,
+        email: Option<&str>,
+        max_uses: i64,
+        expires_at: Option<DateTime<Utc>>,
+        created_by_user_id: Option<i64>,
+    ) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO invitations (code_hash, email, max_uses, expires_at, created_by_user_id) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(code_hash)
+        .bind(email)
+        .bind(max_uses)
+        .bind(expires_at)
+        .bind(created_by_user_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn find_by_id(&self, id: i64) -> Result<Option<Invitation>> {
+        let invitation =
+            sqlx::query_as::<_, Invitation>(&format!("{} WHERE id = ?", Self::SELECT_FIELDS))
+                .bind(id)
+                .fetch_optional(self.pool)
+                .await?;
+
+        Ok(invitation)
+    }
+
+    /// 新しい順に全件取得
+    pub async fn find_all(&self) -> Result<Vec<Invitation>> {
+        let invitations =
+            sqlx::query_as::<_, Invitation>(&format!("{} ORDER BY id DESC", Self::SELECT_FIELDS))
+                .fetch_all(self.pool)
+                .await?;
+
+        Ok(invitations)
+    }
+
+    /// 招待コードを削除（取り消し）。削除した場合はtrueを返す
+    pub async fn delete(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM invitations WHERE id = ?")
+            .bind(id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
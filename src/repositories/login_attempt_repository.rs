@@ -0,0 +1,73 @@
+use crate::models::LoginAttempt;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::SqlitePool;
+
+pub struct LoginAttemptRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> LoginAttemptRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    const SELECT_FIELDS: &'static str = "SELECT id, key, failure_count, last_failed_at, locked_until, created_at, updated_at FROM login_attempts";
+
+    /// キーで検索
+    pub async fn find_by_key(&self, key: &str) -> Result<Option<LoginAttempt>> {
+        let attempt =
+            sqlx::query_as::<_, LoginAttempt>(&format!("{} WHERE key = ?", Self::SELECT_FIELDS))
+                .bind(key)
+                .fetch_optional(self.pool)
+                .await?;
+
+        Ok(attempt)
+    }
+
+    /// 失敗回数とロック期限を保存
+    pub async fn save(
+        &self,
+        key: &str,
+        failure_count: i64,
+        last_failed_at: DateTime<Utc>,
+        locked_until: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO login_attempts (key, failure_count, last_failed_at, locked_until) VALUES (?, ?, ?, ?) \
+             ON CONFLICT (key) DO UPDATE SET failure_count = excluded.failure_count, last_failed_at = excluded.last_failed_at, \
+             locked_until = excluded.locked_until, updated_at = CURRENT_TIMESTAMP",
+        )
+        .bind(key)
+        .bind(failure_count)
+        .bind(last_failed_at)
+        .bind(locked_until)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 記録を削除（ログイン成功時）
+    pub async fn delete_by_key(&self, key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM login_attempts WHERE key = ?")
+            .bind(key)
+            .execute(self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// ロック中でなく、最後の失敗から `window` を過ぎた記録を削除（失敗回数の計算に使われないもの）
+    pub async fn delete_stale(&self, now: DateTime<Utc>, window: Duration) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM login_attempts WHERE last_failed_at <= ? AND (locked_until IS NULL OR locked_until <= ?)",
+        )
+        .bind(now - window)
+        .bind(now)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,96 @@
+use crate::models::MagicLinkToken;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+pub struct MagicLinkTokenRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> MagicLinkTokenRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// トークンを保存
+    pub async fn create(&self, token: &MagicLinkToken) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO magic_link_tokens (user_id, token_hash, binding_hash, expires_at, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(token.user_id)
+        .bind(&token.token_hash)
+        .bind(&token.binding_hash)
+        .bind(token.expires_at)
+        .bind(token.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// 有効なトークンを削除して、対象のユーザーIDを返す（一度しか成功しない）。
+    /// 要求したブラウザと異なる場合は削除せず、本来のブラウザでは引き続き使える
+    pub async fn consume(
+        &self,
+        token_hash: &str,
+        binding_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<i64>> {
+        let user_id: Option<(i64,)> = sqlx::query_as(
+            "DELETE FROM magic_link_tokens WHERE token_hash = ? AND binding_hash = ? AND expires_at > ? RETURNING user_id",
+        )
+        .bind(token_hash)
+        .bind(binding_hash)
+        .bind(now)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(user_id.map(|(user_id,)| user_id))
+    }
+
+    /// トークンの持ち主（期限や使用済みに関わらず）
+    pub async fn find_user_id_by_hash(&self, token_hash: &str) -> Result<Option<i64>> {
+        let user_id: Option<(i64,)> =
+            sqlx::query_as("SELECT user_id FROM magic_link_tokens WHERE token_hash = ?")
+                .bind(token_hash)
+                .fetch_optional(self.pool)
+                .await?;
+
+        Ok(user_id.map(|(user_id,)| user_id))
+    }
+
+    /// ユーザーに最後にトークンを発行した日時
+    pub async fn latest_created_at_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let created_at: Option<(DateTime<Utc>,)> = sqlx::query_as(
+            "SELECT created_at FROM magic_link_tokens WHERE user_id = ? ORDER BY created_at DESC LIMIT 1",
+        )
+        .bind(user_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(created_at.map(|(created_at,)| created_at))
+    }
+
+    /// ユーザーのトークンを全て削除
+    pub async fn delete_by_user_id(&self, user_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM magic_link_tokens WHERE user_id = ?")
+            .bind(user_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 期限切れのトークンを削除
+    pub async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM magic_link_tokens WHERE expires_at <= ?")
+            .bind(now)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
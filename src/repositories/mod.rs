@@ -1,5 +1,37 @@
+pub mod admin_audit_log_repository;
+pub mod api_token_repository;
+pub mod auth_event_repository;
+pub mod email_verification_token_repository;
+pub mod invitation_repository;
+pub mod login_attempt_repository;
+pub mod magic_link_token_repository;
+pub mod oidc_identity_repository;
+pub mod oidc_login_state_repository;
+pub mod passkey_credential_repository;
+pub mod password_reset_token_repository;
+pub mod recovery_code_repository;
+pub mod role_repository;
 pub mod session_repository;
+pub mod totp_credential_repository;
+pub mod two_factor_challenge_repository;
 pub mod user_repository;
+pub mod webauthn_challenge_repository;
 
+pub use admin_audit_log_repository::AdminAuditLogRepository;
+pub use api_token_repository::ApiTokenRepository;
+pub use auth_event_repository::AuthEventRepository;
+pub use email_verification_token_repository::EmailVerificationTokenRepository;
+pub use invitation_repository::InvitationRepository;
+pub use login_attempt_repository::LoginAttemptRepository;
+pub use magic_link_token_repository::MagicLinkTokenRepository;
+pub use oidc_identity_repository::OidcIdentityRepository;
+pub use oidc_login_state_repository::OidcLoginStateRepository;
+pub use passkey_credential_repository::PasskeyCredentialRepository;
+pub use password_reset_token_repository::PasswordResetTokenRepository;
+pub use recovery_code_repository::RecoveryCodeRepository;
+pub use role_repository::RoleRepository;
 pub use session_repository::SessionRepository;
+pub use totp_credential_repository::TotpCredentialRepository;
+pub use two_factor_challenge_repository::TwoFactorChallengeRepository;
 pub use user_repository::UserRepository;
+pub use webauthn_challenge_repository::WebauthnChallengeRepository;
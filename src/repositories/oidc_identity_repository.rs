@@ -0,0 +1,81 @@
+use crate::models::OidcIdentity;
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+pub struct OidcIdentityRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> OidcIdentityRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    const SELECT_FIELDS: &'static str =
+        "SELECT id, user_id, provider, subject, email, created_at, updated_at FROM oidc_identities";
+
+    /// プロバイダとsubjectで検索
+    pub async fn find_by_subject(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<OidcIdentity>> {
+        let identity = sqlx::query_as::<_, OidcIdentity>(&format!(
+            "{} WHERE provider = ? AND subject = ?",
+            Self::SELECT_FIELDS
+        ))
+        .bind(provider)
+        .bind(subject)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(identity)
+    }
+
+    /// ユーザーに紐づく外部アカウントを取得
+    pub async fn find_by_user_id(&self, user_id: i64) -> Result<Vec<OidcIdentity>> {
+        let identities = sqlx::query_as::<_, OidcIdentity>(&format!(
+            "{} WHERE user_id = ? ORDER BY created_at",
+            Self::SELECT_FIELDS
+        ))
+        .bind(user_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(identities)
+    }
+
+    /// ユーザーに外部アカウントを紐付ける
+    pub async fn create(
+        &self,
+        user_id: i64,
+        provider: &str,
+        subject: &str,
+        email: Option<&str>,
+    ) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO oidc_identities (user_id, provider, subject, email) VALUES (?, ?, ?, ?)",
+        )
+        .bind(user_id)
+        .bind(provider)
+        .bind(subject)
+        .bind(email)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// IDプロバイダ側のメールアドレスを最新の値に更新
+    pub async fn update_email(&self, id: i64, email: Option<&str>) -> Result<()> {
+        sqlx::query(
+            "UPDATE oidc_identities SET email = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(email)
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use crate::models::OidcLoginState;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+pub struct OidcLoginStateRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> OidcLoginStateRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 認可リクエストの状態を保存
+    pub async fn create(&self, login_state: &OidcLoginState) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO oidc_login_states (provider, state_hash, nonce, code_verifier, expires_at, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&login_state.provider)
+        .bind(&login_state.state_hash)
+        .bind(&login_state.nonce)
+        .bind(&login_state.code_verifier)
+        .bind(login_state.expires_at)
+        .bind(login_state.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// 有効な状態を削除して返す（一度しか成功しない）
+    pub async fn consume(
+        &self,
+        provider: &str,
+        state_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<OidcLoginState>> {
+        let login_state = sqlx::query_as::<_, OidcLoginState>(
+            "DELETE FROM oidc_login_states WHERE provider = ? AND state_hash = ? AND expires_at > ? \
+             RETURNING id, provider, state_hash, nonce, code_verifier, expires_at, created_at",
+        )
+        .bind(provider)
+        .bind(state_hash)
+        .bind(now)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(login_state)
+    }
+
+    /// 期限切れのログイン状態を削除
+    pub async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM oidc_login_states WHERE expires_at <= ?")
+            .bind(now)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,95 @@
+use crate::models::PasskeyCredential;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+pub struct PasskeyCredentialRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PasskeyCredentialRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    const SELECT_FIELDS: &'static str = "SELECT id, user_id, credential_id, public_key, passkey, sign_count, name, last_used_at, created_at, updated_at FROM passkey_credentials";
+
+    /// クレデンシャルを保存
+    pub async fn create(
+        &self,
+        user_id: i64,
+        credential_id: &str,
+        public_key: &str,
+        passkey: &str,
+        name: Option<&str>,
+    ) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO passkey_credentials (user_id, credential_id, public_key, passkey, sign_count, name) VALUES (?, ?, ?, ?, 0, ?)",
+        )
+        .bind(user_id)
+        .bind(credential_id)
+        .bind(public_key)
+        .bind(passkey)
+        .bind(name)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// ユーザーの全クレデンシャルを取得
+    pub async fn find_by_user_id(&self, user_id: i64) -> Result<Vec<PasskeyCredential>> {
+        let credentials = sqlx::query_as::<_, PasskeyCredential>(&format!(
+            "{} WHERE user_id = ? ORDER BY id",
+            Self::SELECT_FIELDS
+        ))
+        .bind(user_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(credentials)
+    }
+
+    /// クレデンシャルIDが登録済みか確認（別のユーザーに登録されたものも含む）
+    pub async fn exists_by_credential_id(&self, credential_id: &str) -> Result<bool> {
+        let count: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM passkey_credentials WHERE credential_id = ?")
+                .bind(credential_id)
+                .fetch_one(self.pool)
+                .await?;
+
+        Ok(count.0 > 0)
+    }
+
+    /// 認証成功後に署名カウンタと保存済みのPasskeyを更新
+    pub async fn update_after_authentication(
+        &self,
+        credential_id: &str,
+        passkey: &str,
+        sign_count: i64,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE passkey_credentials SET passkey = ?, sign_count = ?, last_used_at = ?, updated_at = CURRENT_TIMESTAMP WHERE credential_id = ?",
+        )
+        .bind(passkey)
+        .bind(sign_count)
+        .bind(now)
+        .bind(credential_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// ユーザーのクレデンシャルを削除。該当するものがなければfalseを返す
+    pub async fn delete(&self, user_id: i64, id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM passkey_credentials WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
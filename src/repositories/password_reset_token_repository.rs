@@ -0,0 +1,80 @@
+use crate::models::PasswordResetToken;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+pub struct PasswordResetTokenRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PasswordResetTokenRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// トークンを保存
+    pub async fn create(&self, token: &PasswordResetToken) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at, created_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(token.user_id)
+        .bind(&token.token_hash)
+        .bind(token.expires_at)
+        .bind(token.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// 有効なトークンの対象ユーザーIDを返す（使用済みにはしない）
+    pub async fn find_valid_user_id(
+        &self,
+        token_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<i64>> {
+        let user_id: Option<(i64,)> = sqlx::query_as(
+            "SELECT user_id FROM password_reset_tokens WHERE token_hash = ? AND used_at IS NULL AND expires_at > ?",
+        )
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(user_id.map(|(user_id,)| user_id))
+    }
+
+    /// 有効なトークンを使用済みにして、対象のユーザーIDを返す（一度しか成功しない）
+    pub async fn consume(&self, token_hash: &str, now: DateTime<Utc>) -> Result<Option<i64>> {
+        let user_id: Option<(i64,)> = sqlx::query_as(
+            "UPDATE password_reset_tokens SET used_at = ? WHERE token_hash = ? AND used_at IS NULL AND expires_at > ? RETURNING user_id",
+        )
+        .bind(now)
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(user_id.map(|(user_id,)| user_id))
+    }
+
+    /// ユーザーの未使用トークンを全て削除（再発行時に古いリンクを無効化する）
+    pub async fn delete_unused_by_user_id(&self, user_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = ? AND used_at IS NULL")
+            .bind(user_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 期限切れのトークンを削除
+    pub async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM password_reset_tokens WHERE expires_at <= ?")
+            .bind(now)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
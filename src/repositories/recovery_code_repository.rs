@@ -0,0 +1,58 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+pub struct RecoveryCodeRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> RecoveryCodeRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// ユーザーのリカバリーコードを全て置き換える
+    pub async fn replace_all(&self, user_id: i64, code_hashes: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM recovery_codes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for code_hash in code_hashes {
+            sqlx::query("INSERT INTO recovery_codes (user_id, code_hash) VALUES (?, ?)")
+                .bind(user_id)
+                .bind(code_hash)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// 未使用のリカバリーコードを使用済みにする。該当するコードがなければfalseを返す
+    pub async fn consume(&self, user_id: i64, code_hash: &str, now: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE recovery_codes SET used_at = ? WHERE user_id = ? AND code_hash = ? AND used_at IS NULL",
+        )
+        .bind(now)
+        .bind(user_id)
+        .bind(code_hash)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// ユーザーのリカバリーコードを全て削除
+    pub async fn delete_by_user_id(&self, user_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM recovery_codes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use crate::models::Role;
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+pub struct RoleRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> RoleRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// ユーザーに割り当てられたロールを取得
+    pub async fn find_by_user_id(&self, user_id: i64) -> Result<Vec<Role>> {
+        let roles = sqlx::query_as::<_, Role>(
+            "SELECT roles.id, roles.name, roles.description, roles.created_at FROM roles INNER JOIN user_roles ON user_roles.role_id = roles.id WHERE user_roles.user_id = ? ORDER BY roles.name",
+        )
+        .bind(user_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(roles)
+    }
+
+    /// ユーザーがロールを通じて持っている権限名を取得
+    pub async fn find_permission_names_by_user_id(&self, user_id: i64) -> Result<Vec<String>> {
+        let names: Vec<(String,)> = sqlx::query_as(
+            "SELECT DISTINCT permissions.name FROM permissions INNER JOIN role_permissions ON role_permissions.permission_id = permissions.id INNER JOIN user_roles ON user_roles.role_id = role_permissions.role_id WHERE user_roles.user_id = ? ORDER BY permissions.name",
+        )
+        .bind(user_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(names.into_iter().map(|(name,)| name).collect())
+    }
+
+    /// 権限を持つ、無効化されていないユーザーの数
+    pub async fn count_enabled_users_with_permission(&self, permission: &str) -> Result<i64> {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT count(DISTINCT users.id) FROM users INNER JOIN user_roles ON user_roles.user_id = users.id INNER JOIN role_permissions ON role_permissions.role_id = user_roles.role_id INNER JOIN permissions ON permissions.id = role_permissions.permission_id WHERE permissions.name = ? AND users.disabled_at IS NULL",
+        )
+        .bind(permission)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// ロールがなければ作成する
+    pub async fn ensure_role(&self, name: &str, description: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO roles (name, description) VALUES (?, ?) ON CONFLICT (name) DO NOTHING",
+        )
+        .bind(name)
+        .bind(description)
+        .execute(self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 権限がなければ作成し、ロールに結び付ける
+    pub async fn ensure_permission(
+        &self,
+        role_name: &str,
+        permission_name: &str,
+        description: &str,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO permissions (name, description) VALUES (?, ?) ON CONFLICT (name) DO NOTHING",
+        )
+        .bind(permission_name)
+        .bind(description)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT INTO role_permissions (role_id, permission_id) SELECT roles.id, permissions.id FROM roles, permissions WHERE roles.name = ? AND permissions.name = ? ON CONFLICT (role_id, permission_id) DO NOTHING",
+        )
+        .bind(role_name)
+        .bind(permission_name)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// ユーザーにロールを割り当てる。ロールが存在しない場合はfalseを返す
+    pub async fn assign(&self, user_id: i64, role_name: &str) -> Result<bool> {
+        let role_id: Option<(i64,)> = sqlx::query_as("SELECT id FROM roles WHERE name = ?")
+            .bind(role_name)
+            .fetch_optional(self.pool)
+            .await?;
+        let Some((role_id,)) = role_id else {
+            return Ok(false);
+        };
+
+        sqlx::query(
+            "INSERT INTO user_roles (user_id, role_id) VALUES (?, ?) ON CONFLICT (user_id, role_id) DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(role_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(true)
+    }
+
+    /// ユーザーからロールを外す。割り当てられていなかった場合はfalseを返す
+    pub async fn unassign(&self, user_id: i64, role_name: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM user_roles WHERE user_id = ? AND role_id IN (SELECT id FROM roles WHERE name = ?)",
+        )
+        .bind(user_id)
+        .bind(role_name)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::models::Session;
 use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
 use sqlx::SqlitePool;
 
 pub struct SessionRepository<'a> {
@@ -11,12 +12,12 @@ impl<'a> SessionRepository<'a> {
         Self { pool }
     }
 
-    const SELECT_FIELDS: &'static str = "SELECT id, user_id, uuid, csrf_token, issued_at, device_info, ip_address, created_at, updated_at FROM sessions";
+    const SELECT_FIELDS: &'static str = "SELECT id, user_id, uuid, csrf_token, previous_csrf_token, csrf_token_rotated_at, issued_at, device_info, ip_address, last_seen_at, expires_at, impersonator_user_id, impersonator_session_id, created_at, updated_at FROM sessions";
 
     /// セッションを保存
     pub async fn create(&self, session: &Session) -> Result<i64> {
         let result = sqlx::query(
-            "INSERT INTO sessions (user_id, uuid, csrf_token, issued_at, device_info, ip_address) VALUES (?, ?, ?, ?, ?, ?)",
+            "INSERT INTO sessions (user_id, uuid, csrf_token, issued_at, device_info, ip_address, last_seen_at, expires_at, impersonator_user_id, impersonator_session_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(session.user_id)
         .bind(&session.uuid)
@@ -24,23 +25,176 @@ impl<'a> SessionRepository<'a> {
         .bind(session.issued_at)
         .bind(&session.device_info)
         .bind(&session.ip_address)
+        .bind(session.last_seen_at)
+        .bind(session.expires_at)
+        .bind(session.impersonator_user_id)
+        .bind(session.impersonator_session_id)
         .execute(self.pool)
         .await?;
 
         Ok(result.last_insert_rowid())
     }
 
-    /// セッションUUIDで検索
-    pub async fn find_by_uuid(&self, session_uuid: &str) -> Result<Option<Session>> {
-        let session =
-            sqlx::query_as::<_, Session>(&format!("{} WHERE uuid = ?", Self::SELECT_FIELDS))
-                .bind(session_uuid)
-                .fetch_optional(self.pool)
-                .await?;
+    /// セッションUUIDで有効なセッションを検索（期限切れ・アイドルタイムアウトしたものは除く）
+    pub async fn find_active_by_uuid(
+        &self,
+        session_uuid: &str,
+        now: DateTime<Utc>,
+        idle_timeout: Duration,
+    ) -> Result<Option<Session>> {
+        let session = sqlx::query_as::<_, Session>(&format!(
+            "{} WHERE uuid = ? AND expires_at > ? AND last_seen_at > ?",
+            Self::SELECT_FIELDS
+        ))
+        .bind(session_uuid)
+        .bind(now)
+        .bind(now - idle_timeout)
+        .fetch_optional(self.pool)
+        .await?;
 
         Ok(session)
     }
 
+    /// セッションIDで有効なセッションを検索
+    pub async fn find_active_by_id(
+        &self,
+        id: i64,
+        now: DateTime<Utc>,
+        idle_timeout: Duration,
+    ) -> Result<Option<Session>> {
+        let session = sqlx::query_as::<_, Session>(&format!(
+            "{} WHERE id = ? AND expires_at > ? AND last_seen_at > ?",
+            Self::SELECT_FIELDS
+        ))
+        .bind(id)
+        .bind(now)
+        .bind(now - idle_timeout)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    /// ユーザーの有効なセッションを最終アクセスの新しい順に取得
+    pub async fn find_active_by_user_id(
+        &self,
+        user_id: i64,
+        now: DateTime<Utc>,
+        idle_timeout: Duration,
+    ) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(&format!(
+            "{} WHERE user_id = ? AND expires_at > ? AND last_seen_at > ? ORDER BY last_seen_at DESC",
+            Self::SELECT_FIELDS
+        ))
+        .bind(user_id)
+        .bind(now)
+        .bind(now - idle_timeout)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    /// 最終アクセス日時を更新（スライディング更新）
+    pub async fn touch(&self, id: i64, now: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            "UPDATE sessions SET last_seen_at = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(now)
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// CSRFトークンを新しくし、それまでのトークンを `previous_csrf_token` に残す
+    pub async fn rotate_csrf_token(
+        &self,
+        id: i64,
+        csrf_token: &str,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE sessions SET previous_csrf_token = csrf_token, csrf_token = ?, csrf_token_rotated_at = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(csrf_token)
+        .bind(now)
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 前回の更新から `grace` を過ぎていればCSRFトークンを更新し、現在のトークンを返す。
+    /// 猶予期間中に更新し直すと、まだ受け付けている更新前のトークンが使えなくなるため、期間内は更新しない
+    pub async fn refresh_csrf_token(
+        &self,
+        id: i64,
+        csrf_token: &str,
+        now: DateTime<Utc>,
+        grace: Duration,
+    ) -> Result<String> {
+        sqlx::query(
+            "UPDATE sessions SET previous_csrf_token = csrf_token, csrf_token = ?, csrf_token_rotated_at = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND (csrf_token_rotated_at IS NULL OR csrf_token_rotated_at <= ?)",
+        )
+        .bind(csrf_token)
+        .bind(now)
+        .bind(id)
+        .bind(now - grace)
+        .execute(self.pool)
+        .await?;
+
+        let current = sqlx::query_scalar("SELECT csrf_token FROM sessions WHERE id = ?")
+            .bind(id)
+            .fetch_one(self.pool)
+            .await?;
+
+        Ok(current)
+    }
+
+    /// ユーザーの全セッションのCSRFトークンを更新する（権限が変わったとき）
+    pub async fn rotate_csrf_tokens_by_user_id(
+        &self,
+        user_id: i64,
+        now: DateTime<Utc>,
+    ) -> Result<u64> {
+        let ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM sessions WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(self.pool)
+            .await?;
+        for id in &ids {
+            self.rotate_csrf_token(*id, &Session::generate_csrf_token(), now)
+                .await?;
+        }
+
+        Ok(ids.len() as u64)
+    }
+
+    /// 期限切れ・アイドルタイムアウトしたセッションを削除
+    pub async fn delete_expired(&self, now: DateTime<Utc>, idle_timeout: Duration) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM sessions WHERE expires_at IS NULL OR expires_at <= ? OR last_seen_at IS NULL OR last_seen_at <= ?",
+        )
+        .bind(now)
+        .bind(now - idle_timeout)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// セッションIDで削除
+    pub async fn delete(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM sessions WHERE id = ?")
+            .bind(id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     /// セッションUUIDで削除（ログアウト）
     pub async fn delete_by_uuid(&self, session_uuid: &str) -> Result<()> {
         sqlx::query("DELETE FROM sessions WHERE uuid = ?")
@@ -51,13 +205,35 @@ impl<'a> SessionRepository<'a> {
         Ok(())
     }
 
+    /// ユーザーのセッションをIDで削除。削除した場合はtrueを返す
+    pub async fn delete_by_id(&self, user_id: i64, id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM sessions WHERE id = ? AND user_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 指定したセッション以外のユーザーのセッションを削除（他のデバイスからログアウト）
+    pub async fn delete_others_by_user_id(&self, user_id: i64, keep_id: i64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM sessions WHERE user_id = ? AND id != ?")
+            .bind(user_id)
+            .bind(keep_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// ユーザーIDで全セッションを削除（全デバイスログアウト）
-    pub async fn delete_by_user_id(&self, user_id: i64) -> Result<()> {
-        sqlx::query("DELETE FROM sessions WHERE user_id = ?")
+    pub async fn delete_by_user_id(&self, user_id: i64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM sessions WHERE user_id = ?")
             .bind(user_id)
             .execute(self.pool)
             .await?;
 
-        Ok(())
+        Ok(result.rows_affected())
     }
 }
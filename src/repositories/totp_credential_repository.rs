@@ -0,0 +1,93 @@
+use crate::models::TotpCredential;
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+pub struct TotpCredentialRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> TotpCredentialRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    const SELECT_FIELDS: &'static str = "SELECT id, user_id, secret, confirmed_at, last_used_step, created_at, updated_at FROM totp_credentials";
+
+    /// ユーザーIDで検索（登録途中のものも含む）
+    pub async fn find_by_user_id(&self, user_id: i64) -> Result<Option<TotpCredential>> {
+        let credential = sqlx::query_as::<_, TotpCredential>(&format!(
+            "{} WHERE user_id = ?",
+            Self::SELECT_FIELDS
+        ))
+        .bind(user_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(credential)
+    }
+
+    /// ユーザーIDで有効化済みのものを検索
+    pub async fn find_confirmed_by_user_id(&self, user_id: i64) -> Result<Option<TotpCredential>> {
+        let credential = sqlx::query_as::<_, TotpCredential>(&format!(
+            "{} WHERE user_id = ? AND confirmed_at IS NOT NULL",
+            Self::SELECT_FIELDS
+        ))
+        .bind(user_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(credential)
+    }
+
+    /// 未確認のシークレットを保存（登録途中のものがあれば置き換える）
+    pub async fn save_pending(&self, user_id: i64, secret: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO totp_credentials (user_id, secret) VALUES (?, ?) \
+             ON CONFLICT (user_id) DO UPDATE SET secret = excluded.secret, confirmed_at = NULL, last_used_step = NULL, updated_at = CURRENT_TIMESTAMP",
+        )
+        .bind(user_id)
+        .bind(secret)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 登録を確定する
+    pub async fn confirm(&self, user_id: i64) -> Result<()> {
+        sqlx::query(
+            "UPDATE totp_credentials SET confirmed_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP WHERE user_id = ?",
+        )
+        .bind(user_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 使用したタイムステップを記録する。
+    /// 既に同じかより新しいステップが使われていた場合はfalseを返す（リプレイ防止）
+    pub async fn record_used_step(&self, user_id: i64, step: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE totp_credentials SET last_used_step = ?, updated_at = CURRENT_TIMESTAMP \
+             WHERE user_id = ? AND (last_used_step IS NULL OR last_used_step < ?)",
+        )
+        .bind(step)
+        .bind(user_id)
+        .bind(step)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// ユーザーのTOTP設定を削除（無効化）
+    pub async fn delete_by_user_id(&self, user_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM totp_credentials WHERE user_id = ?")
+            .bind(user_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use crate::models::TwoFactorChallenge;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+pub struct TwoFactorChallengeRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> TwoFactorChallengeRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    const SELECT_FIELDS: &'static str = "SELECT id, user_id, token_hash, attempts, expires_at, created_at FROM two_factor_challenges";
+
+    /// チャレンジを保存
+    pub async fn create(&self, challenge: &TwoFactorChallenge) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO two_factor_challenges (user_id, token_hash, attempts, expires_at, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(challenge.user_id)
+        .bind(&challenge.token_hash)
+        .bind(challenge.attempts)
+        .bind(challenge.expires_at)
+        .bind(challenge.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// 有効期限内のチャレンジをトークンのハッシュで検索
+    pub async fn find_valid(
+        &self,
+        token_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<TwoFactorChallenge>> {
+        let challenge = sqlx::query_as::<_, TwoFactorChallenge>(&format!(
+            "{} WHERE token_hash = ? AND expires_at > ?",
+            Self::SELECT_FIELDS
+        ))
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(challenge)
+    }
+
+    /// 失敗回数を加算し、加算後の回数を返す
+    pub async fn increment_attempts(&self, id: i64) -> Result<i64> {
+        let attempts: (i64,) = sqlx::query_as(
+            "UPDATE two_factor_challenges SET attempts = attempts + 1 WHERE id = ? RETURNING attempts",
+        )
+        .bind(id)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(attempts.0)
+    }
+
+    /// チャレンジを削除。既に削除されていた場合はfalseを返す
+    pub async fn delete(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM two_factor_challenges WHERE id = ?")
+            .bind(id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// 期限切れのチャレンジを削除
+    pub async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM two_factor_challenges WHERE expires_at <= ?")
+            .bind(now)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,7 +1,17 @@
 use crate::models::User;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use sqlx::SqlitePool;
 
+/// LIKEの特殊文字をエスケープした部分一致パターン
+fn like_pattern(query: &str) -> String {
+    let escaped = query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
 pub struct UserRepository<'a> {
     pool: &'a SqlitePool,
 }
@@ -12,15 +22,17 @@ impl<'a> UserRepository<'a> {
     }
 
     // 全てのクエリで使用する共通のSELECT句
-    const SELECT_FIELDS: &'static str =
-        "SELECT id, email, password, updated_at, created_at FROM users";
+    const SELECT_FIELDS: &'static str = "SELECT id, email, password, email_verified_at, deletion_scheduled_at, disabled_at, locale, updated_at, created_at FROM users";
 
     /// メールアドレスでユーザーを検索
     pub async fn find_by_email(&self, email: &str) -> Result<Option<User>> {
-        let user = sqlx::query_as::<_, User>(&format!("{} WHERE email = ?", Self::SELECT_FIELDS))
-            .bind(email)
-            .fetch_optional(self.pool)
-            .await?;
+        let user = sqlx::query_as::<_, User>(&format!(
+            "{} WHERE email = ? COLLATE NOCASE",
+            Self::SELECT_FIELDS
+        ))
+        .bind(email)
+        .fetch_optional(self.pool)
+        .await?;
 
         Ok(user)
     }
@@ -56,6 +68,39 @@ impl<'a> UserRepository<'a> {
         Ok(result.last_insert_rowid())
     }
 
+    /// 招待コードを1回分消費してユーザーを作成する。
+    /// コードが無効（期限切れ・使用回数の上限・メールアドレスの不一致）の場合は何もせずNoneを返す
+    pub async fn create_with_invitation(
+        &self,
+        email: &str,
+        password_hash: &str,
+        code_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<i64>> {
+        let mut tx = self.pool.begin().await?;
+
+        let consumed = sqlx::query(
+            "UPDATE invitations SET use_count = use_count + 1 WHERE code_hash = ? AND use_count < max_uses AND (expires_at IS NULL OR expires_at > ?) AND (email IS NULL OR lower(email) = lower(?))",
+        )
+        .bind(code_hash)
+        .bind(now)
+        .bind(email)
+        .execute(&mut *tx)
+        .await?;
+        if consumed.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        let result = sqlx::query("INSERT INTO users (email, password) VALUES (?, ?)")
+            .bind(email)
+            .bind(password_hash)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(Some(result.last_insert_rowid()))
+    }
+
     /// ユーザーのパスワードを更新
     pub async fn update_password(&self, id: i64, password_hash: &str) -> Result<()> {
         sqlx::query("UPDATE users SET password = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
@@ -67,12 +112,192 @@ impl<'a> UserRepository<'a> {
         Ok(())
     }
 
+    /// 確認の済んだ新しいメールアドレスに変更する
+    pub async fn update_email(&self, id: i64, email: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE users SET email = ?, email_verified_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(email)
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 全ユーザーのIDとメールアドレスを返す
+    pub async fn find_all_emails(&self) -> Result<Vec<(i64, String)>> {
+        let emails = sqlx::query_as("SELECT id, email FROM users ORDER BY id")
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok(emails)
+    }
+
+    /// メールアドレスをまとめて書き換える（正規化のみで、確認状態は変えない）
+    pub async fn rewrite_emails(&self, emails: &[(i64, String)]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for (id, email) in emails {
+            sqlx::query("UPDATE users SET email = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(email)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// 表示言語の設定を更新する（Noneで設定を解除）
+    pub async fn update_locale(&self, id: i64, locale: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE users SET locale = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(locale)
+            .bind(id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// メールアドレスを確認済みにする
+    pub async fn mark_email_verified(&self, id: i64) -> Result<()> {
+        sqlx::query(
+            "UPDATE users SET email_verified_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND email_verified_at IS NULL",
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// メールアドレスの部分一致でユーザーを検索（ページング）
+    pub async fn search_by_email(&self, query: &str, limit: i64, offset: i64) -> Result<Vec<User>> {
+        let users = sqlx::query_as::<_, User>(&format!(
+            "{} WHERE email LIKE ? ESCAPE '\\' ORDER BY id LIMIT ? OFFSET ?",
+            Self::SELECT_FIELDS
+        ))
+        .bind(like_pattern(query))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(users)
+    }
+
+    /// メールアドレスの部分一致で件数を数える
+    pub async fn count_by_email(&self, query: &str) -> Result<i64> {
+        let count: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM users WHERE email LIKE ? ESCAPE '\\'")
+                .bind(like_pattern(query))
+                .fetch_one(self.pool)
+                .await?;
+
+        Ok(count.0)
+    }
+
+    /// アカウントを無効化する（Noneで有効に戻す）
+    pub async fn set_disabled(&self, id: i64, disabled_at: Option<DateTime<Utc>>) -> Result<()> {
+        sqlx::query(
+            "UPDATE users SET disabled_at = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(disabled_at)
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 退会を申請し、指定した日時に削除する予定にする
+    pub async fn schedule_deletion(&self, id: i64, at: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            "UPDATE users SET deletion_scheduled_at = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(at)
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 退会の申請を取り消す。取り消した場合はtrueを返す
+    pub async fn cancel_deletion(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE users SET deletion_scheduled_at = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ? AND deletion_scheduled_at IS NOT NULL",
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 削除予定日時を過ぎたユーザーのIDを取得
+    pub async fn find_ids_due_for_deletion(&self, now: DateTime<Utc>) -> Result<Vec<i64>> {
+        let ids: Vec<(i64,)> = sqlx::query_as(
+            "SELECT id FROM users WHERE deletion_scheduled_at IS NOT NULL AND deletion_scheduled_at <= ?",
+        )
+        .bind(now)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(ids.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// ユーザーと、ユーザーに紐づくデータをすべて削除する。
+    /// sqlite3defは既存テーブルの列に外部キーを足せないため、関連テーブルはカスケードに頼らず明示的に削除する。
+    /// 認証イベントは監査のため保持期間まで残し、ユーザーとの紐付けだけを外す
+    pub async fn delete_with_related(&self, id: i64) -> Result<()> {
+        const RELATED_TABLES: [&str; 12] = [
+            "sessions",
+            "password_reset_tokens",
+            "email_verification_tokens",
+            "totp_credentials",
+            "recovery_codes",
+            "two_factor_challenges",
+            "passkey_credentials",
+            "webauthn_challenges",
+            "oidc_identities",
+            "api_tokens",
+            "user_roles",
+            "magic_link_tokens",
+        ];
+
+        let mut tx = self.pool.begin().await?;
+        for table in RELATED_TABLES {
+            sqlx::query(&format!("DELETE FROM {} WHERE user_id = ?", table))
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("UPDATE auth_events SET user_id = NULL WHERE user_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM sessions WHERE impersonator_user_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM users WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
     /// ユーザーの存在確認（メールアドレス）
     pub async fn exists_by_email(&self, email: &str) -> Result<bool> {
-        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE email = ?")
-            .bind(email)
-            .fetch_one(self.pool)
-            .await?;
+        let count: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM users WHERE email = ? COLLATE NOCASE")
+                .bind(email)
+                .fetch_one(self.pool)
+                .await?;
 
         Ok(count.0 > 0)
     }
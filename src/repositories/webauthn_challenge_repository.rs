@@ -0,0 +1,61 @@
+use crate::models::WebauthnChallenge;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+pub struct WebauthnChallengeRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> WebauthnChallengeRepository<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// チャレンジを保存
+    pub async fn create(&self, challenge: &WebauthnChallenge) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO webauthn_challenges (user_id, ceremony, token_hash, state, expires_at, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(challenge.user_id)
+        .bind(&challenge.ceremony)
+        .bind(&challenge.token_hash)
+        .bind(&challenge.state)
+        .bind(challenge.expires_at)
+        .bind(challenge.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// 有効なチャレンジを削除して返す（一度しか成功しない）
+    pub async fn consume(
+        &self,
+        token_hash: &str,
+        ceremony: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<WebauthnChallenge>> {
+        let challenge = sqlx::query_as::<_, WebauthnChallenge>(
+            "DELETE FROM webauthn_challenges WHERE token_hash = ? AND ceremony = ? AND expires_at > ? \
+             RETURNING id, user_id, ceremony, token_hash, state, expires_at, created_at",
+        )
+        .bind(token_hash)
+        .bind(ceremony)
+        .bind(now)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(challenge)
+    }
+
+    /// 期限切れのチャレンジを削除
+    pub async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM webauthn_challenges WHERE expires_at <= ?")
+            .bind(now)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
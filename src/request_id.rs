@@ -0,0 +1,55 @@
+use crate::config::AppConfig;
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// 受け付ける外部のリクエストIDの最大長
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// 処理中のリクエストのID（エラーレスポンスやログに載せる）
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// リクエストごとにIDを割り当て、`X-Request-Id` ヘッダで返す。
+/// リバースプロキシを信頼する設定の場合は、プロキシが付けたIDを引き継ぐ。
+pub async fn request_id_middleware(
+    State(config): State<AppConfig>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let forwarded = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            config.server.trust_proxy_headers
+                && !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.chars().all(|c| c.is_ascii_graphic())
+        })
+        .map(str::to_string);
+    let request_id = forwarded.unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    // Always a valid header value: either generated or checked to be visible ASCII above
+    let header_value = HeaderValue::from_str(&request_id).expect("request id is visible ASCII");
+    request
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER, header_value.clone());
+
+    let mut response = REQUEST_ID.scope(request_id, next.run(request)).await;
+    response
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER, header_value);
+    response
+}
@@ -0,0 +1,245 @@
+use super::{PASSWORD, TestApp};
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+#[tokio::test]
+async fn deleting_an_account_keeps_its_auth_events_until_retention() {
+    let mut app = TestApp::with_config(|config| {
+        config.auth.account_deletion_grace_days = 0;
+    })
+    .await;
+    let user_id = app.register("deleted@example.com").await;
+
+    let res = app
+        .request(
+            Method::DELETE,
+            "/api/auth/account",
+            Some(json!({ "password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+
+    let (orphaned, owned): (i64, i64) = sqlx::query_as(
+        "SELECT count(*) FILTER (WHERE user_id IS NULL), count(*) FILTER (WHERE user_id = ?) FROM auth_events",
+    )
+    .bind(user_id)
+    .fetch_one(&app.state.pool)
+    .await
+    .unwrap();
+    assert!(orphaned > 0);
+    assert_eq!(owned, 0);
+}
+
+#[tokio::test]
+async fn signing_in_during_the_grace_period_cancels_deletion() {
+    let mut app = TestApp::new().await;
+    let user_id = app.register("leaving@example.com").await;
+
+    let res = app
+        .request(
+            Method::DELETE,
+            "/api/auth/account",
+            Some(json!({ "password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert!(!res.json()["deletion_scheduled_at"].is_null());
+    assert_eq!(
+        app.get("/api/auth/me").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+
+    let res = app
+        .post(
+            "/api/auth/login",
+            json!({ "email": "leaving@example.com", "password": PASSWORD }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    let scheduled: Option<String> =
+        sqlx::query_scalar("SELECT deletion_scheduled_at FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(&app.state.pool)
+            .await
+            .unwrap();
+    assert_eq!(scheduled, None);
+}
+
+#[tokio::test]
+async fn deleting_without_a_grace_period_removes_related_rows() {
+    let mut app = TestApp::with_config(|config| {
+        config.auth.account_deletion_grace_days = 0;
+    })
+    .await;
+    let user_id = app.register("gone@example.com").await;
+    let res = app
+        .post(
+            "/api/auth/tokens",
+            json!({ "name": "cli", "scopes": ["read"] }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+
+    let res = app
+        .request(
+            Method::DELETE,
+            "/api/auth/account",
+            Some(json!({ "password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert!(res.json()["deletion_scheduled_at"].is_null());
+
+    for table in [
+        "users",
+        "sessions",
+        "api_tokens",
+        "email_verification_tokens",
+    ] {
+        let column = if table == "users" { "id" } else { "user_id" };
+        let remaining: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM {} WHERE {} = ?",
+            table, column
+        ))
+        .bind(user_id)
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+        assert_eq!(remaining, 0, "{}", table);
+    }
+}
+
+#[tokio::test]
+async fn export_contains_the_account_data_without_secrets() {
+    let mut app = TestApp::new().await;
+    app.register("export@example.com").await;
+
+    let res = app.get("/api/auth/account/export").await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert!(
+        res.headers["content-disposition"]
+            .to_str()
+            .unwrap()
+            .starts_with("attachment;")
+    );
+    let export = res.json();
+    assert_eq!(export["profile"]["email"], "export@example.com");
+    assert!(export["profile"].get("password").is_none());
+    assert_eq!(export["sessions"].as_array().unwrap().len(), 1);
+    assert_eq!(export["auth_events"][0]["event_type"], "register");
+    assert_eq!(export["two_factor_enabled"], false);
+}
+
+#[tokio::test]
+async fn changing_the_password_signs_out_other_devices() {
+    let mut app = TestApp::new().await;
+    app.register("rotate@example.com").await;
+    let old_session = app.session_id().unwrap();
+    let session_name = app.state.cookies.session_name().to_string();
+
+    let res = app
+        .request(
+            Method::PATCH,
+            "/api/auth/me/password",
+            Some(json!({ "current_password": "wrong password", "new_password": "another long passphrase" })),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.code(), "invalid_credentials");
+
+    let res = app
+        .request(
+            Method::PATCH,
+            "/api/auth/me/password",
+            Some(
+                json!({ "current_password": PASSWORD, "new_password": "another long passphrase" }),
+            ),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    let new_session = app.session_id().unwrap();
+    assert_ne!(new_session, old_session);
+
+    app.set_cookie(&session_name, &old_session);
+    assert_eq!(
+        app.get("/api/auth/me").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+
+    app.clear_cookies();
+    let res = app
+        .post(
+            "/api/auth/login",
+            json!({ "email": "rotate@example.com", "password": "another long passphrase" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+}
+
+#[tokio::test]
+async fn email_changes_take_effect_once_the_new_address_is_confirmed() {
+    let mut app = TestApp::new().await;
+    app.register("other@example.com").await;
+    app.register("current@example.com").await;
+
+    let res = app
+        .request(
+            Method::PATCH,
+            "/api/auth/me/email",
+            Some(json!({ "email": "Other@Example.com", "current_password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    assert_eq!(res.code(), "email_already_exists");
+
+    let res = app
+        .request(
+            Method::PATCH,
+            "/api/auth/me/email",
+            Some(json!({ "email": "new@example.com", "current_password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::ACCEPTED, "{:?}", res.json());
+    assert_eq!(
+        app.get("/api/auth/me").await.json()["email"],
+        "current@example.com"
+    );
+
+    let res = app
+        .post(
+            "/api/auth/verify-email",
+            json!({ "token": app.link_token("/verify-email") }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(
+        app.get("/api/auth/me").await.json()["email"],
+        "new@example.com"
+    );
+}
+
+#[tokio::test]
+async fn the_locale_can_be_set_and_cleared() {
+    let mut app = TestApp::new().await;
+    app.register("locale@example.com").await;
+
+    let res = app
+        .request(
+            Method::PATCH,
+            "/api/auth/me/locale",
+            Some(json!({ "locale": "en" })),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(app.get("/api/auth/me").await.json()["locale"], "en");
+
+    let res = app
+        .request(
+            Method::PATCH,
+            "/api/auth/me/locale",
+            Some(json!({ "locale": null })),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert!(app.get("/api/auth/me").await.json()["locale"].is_null());
+}
@@ -0,0 +1,194 @@
+use super::{PASSWORD, TestApp};
+use crate::auth::rbac::grant_admin;
+use crate::models::{PERMISSION_USERS_MANAGE, Session};
+use crate::repositories::{RoleRepository, SessionRepository, UserRepository};
+use axum::http::StatusCode;
+use serde_json::json;
+
+/// 管理者としてログインした状態のアプリと、管理者のユーザーID
+pub(super) async fn setup() -> (TestApp, i64) {
+    let mut app = TestApp::new().await;
+    let admin_id = app.register("admin@example.com").await;
+    assert!(
+        grant_admin(&app.state, "admin@example.com", false)
+            .await
+            .unwrap()
+    );
+    (app, admin_id)
+}
+
+pub(super) async fn create_user(app: &TestApp, email: &str) -> i64 {
+    let password_hash = app.state.password_hasher.hash(PASSWORD).await.unwrap();
+    UserRepository::new(&app.state.pool)
+        .create(email, &password_hash)
+        .await
+        .unwrap();
+    sqlx::query_scalar("SELECT id FROM users WHERE email = ?")
+        .bind(email)
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap()
+}
+
+async fn audited_actions(app: &TestApp) -> Vec<(String, Option<i64>, Option<String>)> {
+    sqlx::query_as("SELECT action, target_user_id, details FROM admin_audit_logs ORDER BY id")
+        .fetch_all(&app.state.pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn admin_routes_require_the_users_manage_permission() {
+    let mut app = TestApp::new().await;
+    app.register("member@example.com").await;
+
+    for uri in [
+        "/api/admin/users",
+        "/api/admin/audit-logs",
+        "/api/admin/auth-events",
+        "/api/admin/invitations",
+    ] {
+        let res = app.get(uri).await;
+        assert_eq!(res.status, StatusCode::FORBIDDEN, "{}", uri);
+        assert_eq!(res.code(), "permission_denied");
+    }
+
+    // The role takes effect on the next request of the same session
+    assert!(
+        grant_admin(&app.state, "member@example.com", false)
+            .await
+            .unwrap()
+    );
+    assert_eq!(app.get("/api/admin/users").await.status, StatusCode::OK);
+
+    // Unknown addresses are refused, and so are unverified ones when verification is required
+    assert!(
+        !grant_admin(&app.state, "nobody@example.com", false)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !grant_admin(&app.state, "member@example.com", true)
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn user_searches_and_views_are_audited() {
+    let (mut app, _) = setup().await;
+    let user_id = create_user(&app, "someone@example.com").await;
+
+    let res = app.get("/api/admin/users?email=someone").await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(res.json()["total"], 1);
+    let res = app.get(&format!("/api/admin/users/{}", user_id)).await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+
+    assert_eq!(
+        audited_actions(&app).await,
+        vec![
+            ("user.search".to_string(), None, Some("someone".to_string())),
+            ("user.view".to_string(), Some(user_id), None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn refuses_to_disable_yourself() {
+    let (mut app, admin_id) = setup().await;
+    let user_id = create_user(&app, "someone@example.com").await;
+
+    let res = app
+        .post(&format!("/api/admin/users/{}/disable", admin_id), json!({}))
+        .await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    assert_eq!(res.code(), "admin_lockout");
+    assert!(app.get("/api/auth/me").await.json()["disabled_at"].is_null());
+
+    let res = app
+        .post(&format!("/api/admin/users/{}/disable", user_id), json!({}))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert!(!res.json()["disabled_at"].is_null());
+}
+
+#[tokio::test]
+async fn disables_another_admin_while_one_remains() {
+    let (mut app, _) = setup().await;
+    let other_id = create_user(&app, "other-admin@example.com").await;
+    grant_admin(&app.state, "other-admin@example.com", false)
+        .await
+        .unwrap();
+
+    let res = app
+        .post(&format!("/api/admin/users/{}/disable", other_id), json!({}))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+
+    let admins = RoleRepository::new(&app.state.pool)
+        .count_enabled_users_with_permission(PERMISSION_USERS_MANAGE)
+        .await
+        .unwrap();
+    assert_eq!(admins, 1);
+}
+
+#[tokio::test]
+async fn huge_page_numbers_return_empty_pages() {
+    let (mut app, _) = setup().await;
+
+    for uri in [
+        "/api/admin/users?page=9223372036854775807&per_page=100",
+        "/api/admin/audit-logs?page=9223372036854775807",
+        "/api/admin/auth-events?page=9223372036854775807",
+        "/api/auth/activity?page=9223372036854775807",
+    ] {
+        let res = app.get(uri).await;
+        assert_eq!(res.status, StatusCode::OK, "{}: {:?}", uri, res.json());
+    }
+    let res = app.get("/api/admin/users?page=9223372036854775807").await;
+    assert_eq!(res.json()["users"], json!([]));
+    assert_eq!(res.json()["page"], 100_000);
+}
+
+#[tokio::test]
+async fn force_logout_reports_the_revoked_sessions() {
+    let (mut app, _) = setup().await;
+    let user_id = create_user(&app, "someone@example.com").await;
+    let session_repo = SessionRepository::new(&app.state.pool);
+    for _ in 0..2 {
+        session_repo
+            .create(&Session::new(
+                user_id,
+                None,
+                None,
+                app.state.config.auth.session.absolute_lifetime(),
+            ))
+            .await
+            .unwrap();
+    }
+
+    let res = app
+        .post(&format!("/api/admin/users/{}/logout", user_id), json!({}))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(res.json()["revoked"], 2);
+}
+
+#[tokio::test]
+async fn seeding_roles_again_changes_nothing() {
+    let (app, admin_id) = setup().await;
+
+    crate::migration::seed_roles(&app.state.pool).await.unwrap();
+
+    let links: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM role_permissions")
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+    assert_eq!(links, 1);
+    let permissions = RoleRepository::new(&app.state.pool)
+        .find_permission_names_by_user_id(admin_id)
+        .await
+        .unwrap();
+    assert_eq!(permissions, vec![PERMISSION_USERS_MANAGE.to_string()]);
+}
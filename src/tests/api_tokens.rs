@@ -0,0 +1,68 @@
+use super::{PASSWORD, TestApp};
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+/// 書き込みスコープのAPIトークンだけを持ち、Cookieを持たないクライアント
+async fn setup() -> TestApp {
+    let mut app = TestApp::new().await;
+    app.register("token@example.com").await;
+    let res = app
+        .post(
+            "/api/auth/tokens",
+            json!({ "name": "cli", "scopes": ["read", "write"] }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    let token = res.json()["token"].as_str().unwrap().to_string();
+
+    app.clear_cookies();
+    app.set_bearer_token(&token);
+    app
+}
+
+#[tokio::test]
+async fn api_tokens_authenticate_ordinary_requests() {
+    let mut app = setup().await;
+
+    let res = app.get("/api/auth/me").await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(res.json()["email"], "token@example.com");
+}
+
+#[tokio::test]
+async fn api_tokens_cannot_manage_authentication_methods() {
+    let mut app = setup().await;
+
+    for (method, uri, body) in [
+        (Method::GET, "/api/auth/passkeys", None),
+        (Method::POST, "/api/auth/passkeys/register/start", None),
+        (Method::DELETE, "/api/auth/passkeys/1", None),
+        (Method::POST, "/api/auth/2fa/totp/enroll", None),
+        (
+            Method::POST,
+            "/api/auth/2fa/totp/confirm",
+            Some(json!({ "code": "123456" })),
+        ),
+        (
+            Method::POST,
+            "/api/auth/2fa/totp/disable",
+            Some(json!({ "password": PASSWORD })),
+        ),
+        (Method::GET, "/api/auth/tokens", None),
+        (
+            Method::POST,
+            "/api/auth/tokens",
+            Some(json!({ "name": "another" })),
+        ),
+    ] {
+        let res = app.request(method.clone(), uri, body).await;
+        assert_eq!(res.status, StatusCode::FORBIDDEN, "{} {}", method, uri);
+        assert_eq!(res.code(), "session_required", "{} {}", method, uri);
+    }
+
+    let enrolled: i64 = sqlx::query_scalar("SELECT count(*) FROM totp_credentials")
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+    assert_eq!(enrolled, 0);
+}
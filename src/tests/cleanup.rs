@@ -0,0 +1,79 @@
+use super::TestApp;
+use crate::cleanup::cleanup;
+use chrono::{Duration, Utc};
+
+async fn count(app: &TestApp, table: &str) -> i64 {
+    sqlx::query_scalar(&format!("SELECT count(*) FROM {}", table))
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn deletes_expired_tokens_challenges_and_login_attempts() {
+    let app = TestApp::new().await;
+    let pool = &app.state.pool;
+    sqlx::query("INSERT INTO users (email, password) VALUES ('cleanup@example.com', '')")
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let now = Utc::now();
+    let expired = now - Duration::minutes(1);
+    let valid = now + Duration::minutes(10);
+    for expires_at in [expired, valid] {
+        let token = expires_at.to_rfc3339();
+        for sql in [
+            "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES (1, ?1, ?2)",
+            "INSERT INTO email_verification_tokens (user_id, token_hash, expires_at) VALUES (1, ?1, ?2)",
+            "INSERT INTO two_factor_challenges (user_id, token_hash, expires_at) VALUES (1, ?1, ?2)",
+            "INSERT INTO webauthn_challenges (user_id, ceremony, token_hash, state, expires_at) VALUES (1, 'authentication', ?1, '{}', ?2)",
+            "INSERT INTO oidc_login_states (provider, state_hash, nonce, code_verifier, expires_at) VALUES ('mock', ?1, '', '', ?2)",
+        ] {
+            sqlx::query(sql)
+                .bind(&token)
+                .bind(expires_at)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+    }
+
+    let window = Duration::minutes(app.state.config.auth.login_throttle.window_minutes);
+    for (key, last_failed_at, locked_until) in [
+        ("ip:stale", now - window - Duration::minutes(1), None),
+        ("ip:recent", now - Duration::minutes(1), None),
+        (
+            "ip:locked",
+            now - window - Duration::minutes(1),
+            Some(valid),
+        ),
+    ] {
+        sqlx::query(
+            "INSERT INTO login_attempts (key, failure_count, last_failed_at, locked_until) VALUES (?, 5, ?, ?)",
+        )
+        .bind(key)
+        .bind(last_failed_at)
+        .bind(locked_until)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    cleanup(&app.state).await.unwrap();
+
+    for table in [
+        "password_reset_tokens",
+        "email_verification_tokens",
+        "two_factor_challenges",
+        "webauthn_challenges",
+        "oidc_login_states",
+    ] {
+        assert_eq!(count(&app, table).await, 1, "{}", table);
+    }
+    let keys: Vec<String> = sqlx::query_scalar("SELECT key FROM login_attempts ORDER BY key")
+        .fetch_all(pool)
+        .await
+        .unwrap();
+    assert_eq!(keys, ["ip:locked", "ip:recent"]);
+}
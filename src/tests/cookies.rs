@@ -0,0 +1,89 @@
+use super::{PASSWORD, TestApp};
+use crate::config::{CookieSameSite, SessionIdProtection};
+use axum::http::{StatusCode, header::SET_COOKIE};
+use serde_json::json;
+
+async fn stored_session_uuid(app: &TestApp, user_id: i64) -> String {
+    sqlx::query_scalar("SELECT uuid FROM sessions WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn signed_session_cookies_carry_the_configured_attributes() {
+    let mut app = TestApp::with_config(|config| {
+        config.cookie.host_prefix = true;
+        config.cookie.same_site = CookieSameSite::Strict;
+        config.cookie.max_age_seconds = Some(600);
+        config.cookie.session_id_protection = SessionIdProtection::Signed;
+        config.cookie.secret = Some("test secret".to_string());
+    })
+    .await;
+
+    let res = app
+        .post(
+            "/api/auth/register",
+            json!({ "email": "cookie@example.com", "password": PASSWORD }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    let set_cookie = res.headers[SET_COOKIE].to_str().unwrap();
+    assert!(
+        set_cookie.starts_with("__Host-session_id="),
+        "{}",
+        set_cookie
+    );
+    let attributes: Vec<&str> = set_cookie.split("; ").skip(1).collect();
+    for attribute in [
+        "HttpOnly",
+        "Secure",
+        "SameSite=Strict",
+        "Path=/",
+        "Max-Age=600",
+    ] {
+        assert!(attributes.contains(&attribute), "{}", set_cookie);
+    }
+    assert!(!set_cookie.contains("Domain="), "{}", set_cookie);
+
+    // The signature travels with the value and the plain UUID alone is not accepted
+    let user_id = app.get("/api/auth/me").await.json()["id"].as_i64().unwrap();
+    let uuid = stored_session_uuid(&app, user_id).await;
+    let signed = app.session_id().unwrap();
+    assert_ne!(signed, uuid);
+    assert!(signed.ends_with(&uuid));
+
+    app.set_cookie("__Host-session_id", &uuid);
+    assert_eq!(
+        app.get("/api/auth/me").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    let tampered = format!("{}x{}", &signed[..signed.len() - uuid.len()], &uuid[1..]);
+    app.set_cookie("__Host-session_id", &tampered);
+    assert_eq!(
+        app.get("/api/auth/me").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    app.set_cookie("__Host-session_id", &signed);
+    assert_eq!(app.get("/api/auth/me").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn encrypted_session_cookies_hide_the_session_id() {
+    let mut app = TestApp::with_config(|config| {
+        config.cookie.session_id_protection = SessionIdProtection::Encrypted;
+    })
+    .await;
+    let user_id = app.register("encrypted@example.com").await;
+
+    let uuid = stored_session_uuid(&app, user_id).await;
+    assert!(!app.session_id().unwrap().contains(&uuid));
+    assert_eq!(app.get("/api/auth/me").await.status, StatusCode::OK);
+
+    app.set_cookie("session_id", &uuid);
+    assert_eq!(
+        app.get("/api/auth/me").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+}
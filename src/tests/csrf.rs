@@ -0,0 +1,240 @@
+use super::TestApp;
+use crate::auth::rbac::revoke_admin;
+use crate::config::CsrfStrategy;
+use axum::http::{Method, StatusCode};
+use serde_json::json;
+
+/// CSRFトークンを検査するリクエスト
+async fn change_locale(app: &mut TestApp, csrf_token: &str) -> StatusCode {
+    app.request_with_csrf_token(
+        Method::PATCH,
+        "/api/auth/me/locale",
+        Some(json!({ "locale": "en" })),
+        Some(csrf_token),
+    )
+    .await
+    .status
+}
+
+#[tokio::test]
+async fn refresh_requires_post() {
+    let mut app = TestApp::new().await;
+    app.register("csrf-get@example.com").await;
+    let token = app.csrf_token().await.unwrap();
+
+    let res = app.get("/api/csrf").await;
+    assert_eq!(res.status, StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(app.csrf_token().await.unwrap(), token);
+}
+
+#[tokio::test]
+async fn refreshing_again_within_the_grace_window_keeps_the_previous_token() {
+    let mut app = TestApp::new().await;
+    app.register("csrf-refresh@example.com").await;
+    let original = app.csrf_token().await.unwrap();
+
+    // A client that lost its token can still refresh without one
+    let res = app
+        .request_with_csrf_token(Method::POST, "/api/csrf", None, None)
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    let refreshed = res.json()["csrf_token"].as_str().unwrap().to_string();
+    assert_ne!(refreshed, original);
+
+    let res = app
+        .request_with_csrf_token(Method::POST, "/api/csrf", None, None)
+        .await;
+    assert_eq!(res.json()["csrf_token"], refreshed);
+
+    assert_eq!(change_locale(&mut app, &original).await, StatusCode::OK);
+    assert_eq!(change_locale(&mut app, &refreshed).await, StatusCode::OK);
+    assert_eq!(
+        change_locale(&mut app, "not-a-token").await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn refreshing_after_the_grace_window_rotates_again() {
+    let mut app = TestApp::with_config(|config| {
+        config.csrf.rotation_grace_seconds = 0;
+    })
+    .await;
+    app.register("csrf-rotate@example.com").await;
+    let original = app.csrf_token().await.unwrap();
+
+    let first = app.post("/api/csrf", json!({})).await.json()["csrf_token"].clone();
+    let second = app.post("/api/csrf", json!({})).await.json()["csrf_token"].clone();
+    assert_ne!(first, original);
+    assert_ne!(second, first);
+    assert_eq!(
+        change_locale(&mut app, &original).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn double_submit_accepts_the_cookie_token_of_the_same_session() {
+    let mut app = TestApp::with_config(|config| {
+        config.csrf.strategies = vec![CsrfStrategy::DoubleSubmit];
+    })
+    .await;
+    app.register("double-submit@example.com").await;
+
+    // No database token is involved; the token comes with a cookie
+    let res = app.post("/api/csrf", json!({})).await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    let token = res.json()["csrf_token"].as_str().unwrap().to_string();
+    let cookie_name = app.state.cookies.csrf_name().to_string();
+    assert_eq!(app.cookie(&cookie_name), Some(token.as_str()));
+
+    assert_eq!(change_locale(&mut app, &token).await, StatusCode::OK);
+    assert_eq!(
+        change_locale(&mut app, "forged.token").await,
+        StatusCode::FORBIDDEN
+    );
+
+    // A token signed for another session is useless even when planted in the cookie
+    app.post(
+        "/api/auth/login",
+        json!({ "email": "double-submit@example.com", "password": super::PASSWORD }),
+    )
+    .await;
+    app.set_cookie(&cookie_name, &token);
+    let res = app
+        .request_with_csrf_token(
+            Method::PATCH,
+            "/api/auth/me/locale",
+            Some(json!({ "locale": "en" })),
+            Some(&token),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(res.code(), "csrf_token_mismatch");
+}
+
+#[tokio::test]
+async fn fetch_metadata_rejects_cross_site_requests() {
+    let mut app = TestApp::with_config(|config| {
+        config.csrf.strategies = vec![CsrfStrategy::FetchMetadata];
+    })
+    .await;
+    app.register("fetch-metadata@example.com").await;
+    let base_url = app.state.config.server.base_url();
+
+    // No token is needed from the same origin
+    assert_eq!(change_locale(&mut app, "").await, StatusCode::OK);
+
+    app.set_header("Sec-Fetch-Site", Some("cross-site"));
+    let res = app
+        .request_with_csrf_token(
+            Method::PATCH,
+            "/api/auth/me/locale",
+            Some(json!({ "locale": "en" })),
+            None,
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(res.code(), "csrf_cross_site");
+
+    app.set_header("Sec-Fetch-Site", Some("same-site"));
+    assert_eq!(change_locale(&mut app, "").await, StatusCode::FORBIDDEN);
+
+    // Older browsers without Fetch Metadata are judged by Origin
+    app.set_header("Sec-Fetch-Site", None);
+    app.set_header("Origin", Some(&base_url));
+    assert_eq!(change_locale(&mut app, "").await, StatusCode::OK);
+    app.set_header("Origin", Some("https://attacker.example.com"));
+    assert_eq!(change_locale(&mut app, "").await, StatusCode::FORBIDDEN);
+
+    app.set_header("Origin", None);
+    let res = app
+        .request_with_csrf_token(
+            Method::PATCH,
+            "/api/auth/me/locale",
+            Some(json!({ "locale": "en" })),
+            None,
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(res.code(), "csrf_origin_missing");
+}
+
+#[tokio::test]
+async fn fetch_metadata_can_allow_same_site_requests() {
+    let mut app = TestApp::with_config(|config| {
+        config.csrf.strategies = vec![CsrfStrategy::FetchMetadata];
+        config.csrf.allow_same_site = true;
+    })
+    .await;
+    app.register("same-site@example.com").await;
+
+    app.set_header("Sec-Fetch-Site", Some("same-site"));
+    assert_eq!(change_locale(&mut app, "").await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn refresh_refuses_cross_site_requests() {
+    let mut app = TestApp::new().await;
+    app.register("csrf-cross-site@example.com").await;
+    let token = app.csrf_token().await.unwrap();
+
+    app.set_header("Sec-Fetch-Site", Some("cross-site"));
+    let res = app
+        .request_with_csrf_token(Method::POST, "/api/csrf", None, None)
+        .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(res.code(), "csrf_cross_site");
+
+    app.set_header("Sec-Fetch-Site", None);
+    let res = app
+        .request_with_csrf_token(Method::POST, "/api/csrf", None, None)
+        .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(res.code(), "csrf_origin_missing");
+
+    app.set_header("Sec-Fetch-Site", Some("same-origin"));
+    assert_eq!(app.csrf_token().await.unwrap(), token);
+}
+
+#[tokio::test]
+async fn login_replaces_the_previous_session_and_its_token() {
+    let mut app = TestApp::new().await;
+    let user_id = app.register("csrf-login@example.com").await;
+    let original = app.csrf_token().await.unwrap();
+
+    let res = app
+        .post(
+            "/api/auth/login",
+            json!({ "email": "csrf-login@example.com", "password": super::PASSWORD }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+
+    let sessions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+    assert_eq!(sessions, 1);
+    assert_ne!(app.csrf_token().await.unwrap(), original);
+    assert_eq!(
+        change_locale(&mut app, &original).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn revoking_admin_rotates_the_token() {
+    let (mut app, _) = super::admin::setup().await;
+    let original = app.csrf_token().await.unwrap();
+
+    assert!(revoke_admin(&app.state, "admin@example.com").await.unwrap());
+    assert!(!revoke_admin(&app.state, "admin@example.com").await.unwrap());
+
+    assert_ne!(app.csrf_token().await.unwrap(), original);
+    assert_eq!(
+        app.get("/api/admin/users").await.status,
+        StatusCode::FORBIDDEN
+    );
+}
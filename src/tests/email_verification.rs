@@ -0,0 +1,66 @@
+use super::TestApp;
+use axum::http::StatusCode;
+use serde_json::json;
+
+const SUBJECT: &str = "Subject: メールアドレスの確認";
+
+async fn resend(app: &mut TestApp, email: &str) -> super::TestResponse {
+    app.post("/api/auth/verify-email/resend", json!({ "email": email }))
+        .await
+}
+
+#[tokio::test]
+async fn unverified_users_are_held_back_until_they_verify() {
+    let mut app = TestApp::with_config(|config| {
+        config.auth.require_email_verification = true;
+    })
+    .await;
+    app.register("unverified@example.com").await;
+
+    let res = app.get("/api/auth/me").await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(res.code(), "email_not_verified");
+
+    let res = app
+        .post(
+            "/api/auth/verify-email",
+            json!({ "token": app.link_token("/verify-email") }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(app.get("/api/auth/me").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn resend_answers_the_same_for_every_address() {
+    let mut app = TestApp::new().await;
+    app.register("pending@example.com").await;
+    app.register("verified@example.com").await;
+    let token = app.link_token("/verify-email");
+    app.post("/api/auth/verify-email", json!({ "token": token }))
+        .await;
+    app.clear_cookies();
+    let sent = app.mails().matches(SUBJECT).count();
+
+    // The registration mail was just sent, so the repeated request is dropped without telling
+    let pending = resend(&mut app, "pending@example.com").await;
+    let verified = resend(&mut app, "verified@example.com").await;
+    let unknown = resend(&mut app, "unknown@example.com").await;
+    for res in [&pending, &verified] {
+        assert_eq!(res.status, unknown.status);
+        assert_eq!(res.body, unknown.body);
+    }
+    assert_eq!(unknown.status, StatusCode::OK);
+    assert_eq!(app.mails().matches(SUBJECT).count(), sent);
+
+    // Once the interval has passed, the mail is sent again
+    sqlx::query("UPDATE email_verification_tokens SET created_at = datetime('now', '-1 hour')")
+        .execute(&app.state.pool)
+        .await
+        .unwrap();
+    let res = resend(&mut app, "pending@example.com").await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(app.mails().matches(SUBJECT).count(), sent + 1);
+    let mails = app.mails();
+    assert!(mails[mails.rfind("To: ").unwrap()..].starts_with("To: pending@example.com\n"));
+}
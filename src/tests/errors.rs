@@ -0,0 +1,76 @@
+use super::TestApp;
+use super::admin::setup;
+use axum::http::{Method, StatusCode, header::CONTENT_TYPE};
+use serde_json::json;
+
+fn assert_problem(res: &super::TestResponse, status: StatusCode, code: &str) {
+    assert_eq!(res.status, status, "{:?}", res.json());
+    assert_eq!(res.headers[CONTENT_TYPE], "application/problem+json");
+    assert_eq!(res.code(), code);
+    assert_eq!(res.json()["status"], status.as_u16());
+}
+
+#[tokio::test]
+async fn rejected_bodies_are_problem_details() {
+    let mut app = TestApp::new().await;
+
+    let res = app
+        .request(Method::POST, "/api/auth/verify-email", None)
+        .await;
+    assert_problem(
+        &res,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        "unsupported_media_type",
+    );
+
+    let res = app.post("/api/auth/verify-email", json!({})).await;
+    assert_problem(&res, StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+    assert_eq!(res.json()["errors"][0]["field"], "token");
+    assert_eq!(res.json()["errors"][0]["code"], "field_required");
+
+    let res = app
+        .post("/api/auth/password-reset/confirm", json!({ "token": 1 }))
+        .await;
+    assert_problem(&res, StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+    assert_eq!(res.json()["errors"][0]["code"], "field_invalid");
+}
+
+#[tokio::test]
+async fn rejected_queries_and_paths_are_problem_details() {
+    let (mut app, _) = setup().await;
+
+    let res = app.get("/api/admin/users?page=abc").await;
+    assert_problem(&res, StatusCode::BAD_REQUEST, "invalid_query");
+
+    let res = app.get("/api/admin/users/abc").await;
+    assert_problem(&res, StatusCode::BAD_REQUEST, "invalid_path");
+}
+
+#[tokio::test]
+async fn auth_errors_are_problem_details_with_the_request_id() {
+    let mut app = TestApp::new().await;
+
+    let res = app
+        .post(
+            "/api/auth/login",
+            json!({ "email": "nobody@example.com", "password": "wrong password" }),
+        )
+        .await;
+    assert_problem(&res, StatusCode::UNAUTHORIZED, "invalid_credentials");
+    let request_id = res.headers["x-request-id"].to_str().unwrap();
+    assert_eq!(
+        res.json(),
+        json!({
+            "type": "about:blank",
+            "title": "Unauthorized",
+            "status": 401,
+            "detail": res.json()["detail"],
+            "code": "invalid_credentials",
+            "request_id": request_id,
+        })
+    );
+    assert!(!res.json()["detail"].as_str().unwrap().is_empty());
+
+    let res = app.get("/api/no-such-route").await;
+    assert_problem(&res, StatusCode::NOT_FOUND, "not_found");
+}
@@ -0,0 +1,61 @@
+use super::{PASSWORD, TestApp};
+use axum::http::{Method, StatusCode, header::CONTENT_LANGUAGE};
+use serde_json::json;
+
+async fn failed_login(app: &mut TestApp) -> super::TestResponse {
+    app.post(
+        "/api/auth/login",
+        json!({ "email": "nobody@example.com", "password": "wrong password" }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn messages_follow_accept_language() {
+    let mut app = TestApp::new().await;
+
+    // Japanese unless the browser prefers a supported language
+    let res = failed_login(&mut app).await;
+    assert_eq!(
+        res.json()["detail"],
+        "メールアドレスまたはパスワードが正しくありません"
+    );
+    assert_eq!(res.headers[CONTENT_LANGUAGE], "ja");
+
+    app.set_header("Accept-Language", Some("fr-FR, en-US;q=0.8, ja;q=0.5"));
+    let res = failed_login(&mut app).await;
+    assert_eq!(res.json()["detail"], "Invalid credentials");
+    assert_eq!(res.headers[CONTENT_LANGUAGE], "en");
+
+    // Field errors are translated as well
+    let res = app.post("/api/auth/verify-email", json!({})).await;
+    assert_eq!(res.json()["errors"][0]["message"], "This field is required");
+
+    app.set_header("Accept-Language", Some("en;q=0, ja"));
+    let res = app.post("/api/auth/verify-email", json!({})).await;
+    assert_eq!(res.json()["errors"][0]["message"], "入力してください");
+}
+
+#[tokio::test]
+async fn the_users_locale_overrides_accept_language() {
+    let mut app = TestApp::new().await;
+    app.register("english@example.com").await;
+    app.request(
+        Method::PATCH,
+        "/api/auth/me/locale",
+        Some(json!({ "locale": "en" })),
+    )
+    .await;
+
+    app.set_header("Accept-Language", Some("ja"));
+    let res = app
+        .request(
+            Method::PATCH,
+            "/api/auth/me/password",
+            Some(json!({ "current_password": "wrong password", "new_password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.json()["detail"], "Invalid credentials");
+    assert_eq!(res.headers[CONTENT_LANGUAGE], "en");
+}
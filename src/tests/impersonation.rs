@@ -0,0 +1,139 @@
+use super::TestApp;
+use super::admin::{create_user, setup};
+use crate::auth::rbac::grant_admin;
+use crate::repositories::UserRepository;
+use axum::http::{Method, StatusCode};
+use chrono::Utc;
+use serde_json::json;
+
+/// 管理者が一般ユーザーになりすました状態のアプリと、管理者・ユーザーのID
+async fn impersonate() -> (TestApp, i64, i64) {
+    let (mut app, admin_id) = setup().await;
+    let user_id = create_user(&app, "target@example.com").await;
+
+    let res = app
+        .post(
+            &format!("/api/admin/users/{}/impersonate", user_id),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(
+        app.get("/api/auth/me").await.json()["email"],
+        "target@example.com"
+    );
+    (app, admin_id, user_id)
+}
+
+async fn impersonation_audit(app: &TestApp) -> Vec<(String, i64, i64)> {
+    sqlx::query_as(
+        "SELECT action, admin_user_id, target_user_id FROM admin_audit_logs WHERE action LIKE 'user.impersonate%' ORDER BY id",
+    )
+    .fetch_all(&app.state.pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn ends_impersonation_and_returns_to_the_admin() {
+    let (mut app, admin_id, user_id) = impersonate().await;
+
+    let res = app.post("/api/auth/impersonation/end", json!({})).await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(res.json()["restored"], true);
+    assert_eq!(app.get("/api/auth/me").await.json()["id"], admin_id);
+
+    assert_eq!(
+        impersonation_audit(&app).await,
+        vec![
+            ("user.impersonate_start".to_string(), admin_id, user_id),
+            ("user.impersonate_end".to_string(), admin_id, user_id),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn logging_out_ends_impersonation() {
+    let (mut app, admin_id, user_id) = impersonate().await;
+
+    let res = app.post("/api/auth/logout", json!({})).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(app.get("/api/auth/me").await.json()["id"], admin_id);
+    assert_eq!(
+        impersonation_audit(&app).await.last(),
+        Some(&("user.impersonate_end".to_string(), admin_id, user_id))
+    );
+}
+
+#[tokio::test]
+async fn refuses_account_changes_and_data_export_while_impersonating() {
+    let (mut app, _, _) = impersonate().await;
+
+    let res = app
+        .request(
+            Method::PATCH,
+            "/api/auth/me/password",
+            Some(json!({ "current_password": "x", "new_password": "y" })),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(res.code(), "impersonation_forbidden");
+
+    let res = app.get("/api/auth/account/export").await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(res.code(), "impersonation_forbidden");
+
+    // Reading the account as the user is still allowed
+    assert_eq!(app.get("/api/auth/me").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn refuses_to_impersonate_another_admin() {
+    let (mut app, _) = setup().await;
+    let other_id = create_user(&app, "other-admin@example.com").await;
+    grant_admin(&app.state, "other-admin@example.com", false)
+        .await
+        .unwrap();
+
+    let res = app
+        .post(
+            &format!("/api/admin/users/{}/impersonate", other_id),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(res.code(), "permission_denied");
+}
+
+#[tokio::test]
+async fn disabling_the_admin_ends_their_impersonation() {
+    let (mut app, admin_id, _) = impersonate().await;
+
+    UserRepository::new(&app.state.pool)
+        .set_disabled(admin_id, Some(Utc::now()))
+        .await
+        .unwrap();
+    let res = app.get("/api/auth/me").await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn revoking_the_admin_role_ends_their_impersonation() {
+    let (mut app, admin_id, _) = impersonate().await;
+
+    sqlx::query("DELETE FROM user_roles WHERE user_id = ?")
+        .bind(admin_id)
+        .execute(&app.state.pool)
+        .await
+        .unwrap();
+    let res = app.get("/api/auth/me").await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+
+    // The impersonation session is gone for good
+    let remaining: i64 =
+        sqlx::query_scalar("SELECT count(*) FROM sessions WHERE impersonator_user_id IS NOT NULL")
+            .fetch_one(&app.state.pool)
+            .await
+            .unwrap();
+    assert_eq!(remaining, 0);
+}
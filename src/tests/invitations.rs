@@ -0,0 +1,95 @@
+use super::admin::setup;
+use super::{PASSWORD, TestApp};
+use crate::auth::token::{generate_token, hash_token};
+use crate::config::RegistrationMode;
+use crate::repositories::InvitationRepository;
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn rejects_invitations_that_cannot_be_used() {
+    let (mut app, _) = setup().await;
+
+    for max_uses in [0, -1] {
+        let res = app
+            .post("/api/admin/invitations", json!({ "max_uses": max_uses }))
+            .await;
+        assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(res.json()["errors"][0]["field"], "max_uses");
+        assert_eq!(res.json()["errors"][0]["code"], "max_uses_out_of_range");
+    }
+
+    let res = app
+        .post("/api/admin/invitations", json!({ "max_uses": 3 }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(res.json()["invitation"]["max_uses"], 3);
+}
+
+async fn register_with(app: &mut TestApp, email: &str, code: Option<&str>) -> super::TestResponse {
+    app.clear_cookies();
+    app.post(
+        "/api/auth/register",
+        json!({ "email": email, "password": PASSWORD, "invitation_code": code }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn invite_only_registration_consumes_invitation_codes() {
+    let mut app = TestApp::with_config(|config| {
+        config.auth.registration = RegistrationMode::Invite;
+    })
+    .await;
+    let invitation_repo = InvitationRepository::new(&app.state.pool);
+    let open_code = generate_token();
+    invitation_repo
+        .create(&hash_token(&open_code), None, 2, None, None)
+        .await
+        .unwrap();
+    let bound_code = generate_token();
+    invitation_repo
+        .create(
+            &hash_token(&bound_code),
+            Some("invited@example.com"),
+            1,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+    let res = register_with(&mut app, "uninvited@example.com", None).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(res.code(), "invalid_invitation");
+    let res = register_with(&mut app, "uninvited@example.com", Some("wrong")).await;
+    assert_eq!(res.code(), "invalid_invitation");
+
+    // A code issued for an address only works for that address
+    let res = register_with(&mut app, "someone@example.com", Some(&bound_code)).await;
+    assert_eq!(res.code(), "invalid_invitation");
+    let res = register_with(&mut app, "Invited@Example.com", Some(&bound_code)).await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    let res = register_with(&mut app, "invited2@example.com", Some(&bound_code)).await;
+    assert_eq!(res.code(), "invalid_invitation");
+
+    // Each use counts until the limit is reached
+    for email in ["first@example.com", "second@example.com"] {
+        let res = register_with(&mut app, email, Some(&open_code)).await;
+        assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    }
+    let res = register_with(&mut app, "third@example.com", Some(&open_code)).await;
+    assert_eq!(res.code(), "invalid_invitation");
+}
+
+#[tokio::test]
+async fn closed_registration_refuses_everyone() {
+    let mut app = TestApp::with_config(|config| {
+        config.auth.registration = RegistrationMode::Closed;
+    })
+    .await;
+
+    let res = register_with(&mut app, "closed@example.com", None).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    assert_eq!(res.code(), "registration_closed");
+}
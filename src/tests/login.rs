@@ -0,0 +1,94 @@
+use super::{PASSWORD, TestApp};
+use crate::repositories::UserRepository;
+use axum::http::StatusCode;
+use serde_json::json;
+
+async fn stored_hash(app: &TestApp, email: &str) -> String {
+    sqlx::query_scalar("SELECT password FROM users WHERE email = ?")
+        .bind(email)
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn legacy_bcrypt_hashes_are_upgraded_on_login() {
+    let mut app = TestApp::new().await;
+    let legacy_hash = bcrypt::hash(PASSWORD, 4).unwrap();
+    UserRepository::new(&app.state.pool)
+        .create("legacy@example.com", &legacy_hash)
+        .await
+        .unwrap();
+
+    // A failed attempt leaves the hash alone
+    let res = app
+        .post(
+            "/api/auth/login",
+            json!({ "email": "legacy@example.com", "password": "wrong password" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(stored_hash(&app, "legacy@example.com").await, legacy_hash);
+
+    let res = app
+        .post(
+            "/api/auth/login",
+            json!({ "email": "legacy@example.com", "password": PASSWORD }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    let upgraded = stored_hash(&app, "legacy@example.com").await;
+    assert!(upgraded.starts_with("$argon2id$"), "{}", upgraded);
+    assert!(!app.state.password_hasher.needs_rehash(&upgraded));
+
+    app.clear_cookies();
+    let res = app
+        .post(
+            "/api/auth/login",
+            json!({ "email": "legacy@example.com", "password": PASSWORD }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(stored_hash(&app, "legacy@example.com").await, upgraded);
+}
+
+async fn login(app: &mut TestApp, email: &str, password: &str) -> super::TestResponse {
+    app.post(
+        "/api/auth/login",
+        json!({ "email": email, "password": password }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn addresses_and_ips_lock_out_at_their_own_thresholds() {
+    let mut app = TestApp::with_config(|config| {
+        config.server.trust_proxy_headers = true;
+        config.server.trusted_proxy_hops = 1;
+        config.auth.login_throttle.max_attempts = 2;
+        config.auth.login_throttle.ip_max_attempts = 4;
+    })
+    .await;
+    app.set_header("X-Forwarded-For", Some("203.0.113.7"));
+
+    for _ in 0..2 {
+        let res = login(&mut app, "target@example.com", "wrong password").await;
+        assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    }
+    let res = login(&mut app, "target@example.com", "wrong password").await;
+    assert_eq!(res.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(res.code(), "too_many_attempts");
+    assert!(res.headers.contains_key("retry-after"));
+
+    // The shared address is still allowed to try other accounts
+    let res = login(&mut app, "other@example.com", "wrong password").await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = login(&mut app, "third@example.com", "wrong password").await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = login(&mut app, "fourth@example.com", "wrong password").await;
+    assert_eq!(res.status, StatusCode::TOO_MANY_REQUESTS);
+
+    app.set_header("X-Forwarded-For", Some("198.51.100.1"));
+    let res = login(&mut app, "fourth@example.com", "wrong password").await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+}
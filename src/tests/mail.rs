@@ -0,0 +1,156 @@
+use super::{PASSWORD, TestApp};
+use crate::mailer::{Mail, Mailer};
+use crate::repositories::UserRepository;
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use serde_json::json;
+use std::sync::Arc;
+
+/// 常に送信に失敗するMailer（SMTPサーバの障害を想定）
+struct FailingMailer;
+
+#[async_trait]
+impl Mailer for FailingMailer {
+    async fn send(&self, _mail: &Mail) -> anyhow::Result<()> {
+        anyhow::bail!("mail server unavailable")
+    }
+}
+
+#[tokio::test]
+async fn registration_mails_a_link_that_verifies_the_address() {
+    let mut app = TestApp::new().await;
+    app.register("verify@example.com").await;
+
+    let mails = app.mails();
+    assert!(mails.contains("To: verify@example.com\nSubject: メールアドレスの確認\n"));
+    let token = app.link_token("/verify-email");
+
+    let res = app
+        .post("/api/auth/verify-email", json!({ "token": token }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert!(!app.get("/api/auth/me").await.json()["email_verified_at"].is_null());
+
+    // The link works only once
+    let res = app
+        .post("/api/auth/verify-email", json!({ "token": token }))
+        .await;
+    assert_eq!(res.code(), "invalid_token");
+}
+
+#[tokio::test]
+async fn password_reset_mail_links_to_a_working_reset() {
+    let mut app = TestApp::new().await;
+    app.register("reset@example.com").await;
+    app.clear_cookies();
+
+    let res = app
+        .post(
+            "/api/auth/password-reset/request",
+            json!({ "email": "reset@example.com" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert!(
+        app.mails()
+            .contains("To: reset@example.com\nSubject: パスワードの再設定\n")
+    );
+
+    let new_password = "another horse battery staple";
+    let res = app
+        .post(
+            "/api/auth/password-reset/confirm",
+            json!({ "token": app.link_token("/password-reset"), "password": new_password }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+
+    let res = app
+        .post(
+            "/api/auth/login",
+            json!({ "email": "reset@example.com", "password": new_password }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+}
+
+#[tokio::test]
+async fn magic_link_mail_logs_in_from_the_requesting_browser() {
+    let mut app = TestApp::new().await;
+    app.register("magic@example.com").await;
+    app.clear_cookies();
+
+    let res = app
+        .post(
+            "/api/auth/magic-link/request",
+            json!({ "email": "magic@example.com" }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert!(
+        app.mails()
+            .contains("To: magic@example.com\nSubject: ログイン用リンク\n")
+    );
+
+    let res = app
+        .post(
+            "/api/auth/magic-link/verify",
+            json!({ "token": app.link_token("/magic-link") }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(
+        app.get("/api/auth/me").await.json()["email"],
+        "magic@example.com"
+    );
+}
+
+#[tokio::test]
+async fn repeated_magic_link_requests_look_like_unknown_addresses() {
+    let mut app = TestApp::new().await;
+    app.register("repeat@example.com").await;
+    app.clear_cookies();
+
+    for email in ["repeat@example.com", "nobody@example.com"] {
+        for _ in 0..2 {
+            let res = app
+                .post("/api/auth/magic-link/request", json!({ "email": email }))
+                .await;
+            assert_eq!(res.status, StatusCode::OK, "{}: {:?}", email, res.json());
+        }
+    }
+    assert_eq!(app.mails().matches("Subject: ログイン用リンク").count(), 1);
+
+    // The dropped request keeps the browser binding, so the link sent first still works
+    let res = app
+        .post(
+            "/api/auth/magic-link/verify",
+            json!({ "token": app.link_token("/magic-link") }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+}
+
+#[tokio::test]
+async fn mail_failures_look_like_unknown_addresses() {
+    let mut app = TestApp::with_mailer(Arc::new(FailingMailer)).await;
+    let password_hash = app.state.password_hasher.hash(PASSWORD).await.unwrap();
+    UserRepository::new(&app.state.pool)
+        .create("known@example.com", &password_hash)
+        .await
+        .unwrap();
+
+    for uri in [
+        "/api/auth/password-reset/request",
+        "/api/auth/magic-link/request",
+        "/api/auth/verify-email/resend",
+    ] {
+        let known = app.post(uri, json!({ "email": "known@example.com" })).await;
+        let unknown = app
+            .post(uri, json!({ "email": "unknown@example.com" }))
+            .await;
+        assert_eq!(known.status, StatusCode::OK, "{}: {:?}", uri, known.json());
+        assert_eq!(known.status, unknown.status, "{}", uri);
+        assert_eq!(known.body, unknown.body, "{}", uri);
+    }
+}
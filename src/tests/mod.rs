@@ -0,0 +1,306 @@
+//! ルーター全体にリクエストを通すテスト。DBはメモリ上のSQLite、メールは一時ファイルに書き出す
+
+mod account;
+mod admin;
+mod api_tokens;
+mod cleanup;
+mod cookies;
+mod csrf;
+mod email_verification;
+mod errors;
+mod i18n;
+mod impersonation;
+mod invitations;
+mod login;
+mod mail;
+mod oidc;
+mod passkey;
+mod password_policy;
+mod sessions;
+mod two_factor;
+mod validation;
+
+use crate::auth::password::PasswordHasher;
+use crate::config::AppConfig;
+use crate::cookie::CookieSettings;
+use crate::csrf::protection::CsrfProtection;
+use crate::mailer::{FileMailer, Mailer};
+use crate::{AppState, auth, router};
+use axum::{
+    Router,
+    body::{Body, Bytes},
+    http::{HeaderMap, Method, Request, StatusCode, header},
+};
+use serde_json::Value;
+use sqlx::sqlite::SqlitePoolOptions;
+use std::sync::Arc;
+use tempfile::NamedTempFile;
+use tower::Service;
+
+/// テストで登録するユーザーのパスワード（パスワードポリシーを満たすもの）
+pub const PASSWORD: &str = "correct horse battery staple";
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TestResponse {
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    /// Problem Detailsの `code`
+    pub fn code(&self) -> String {
+        self.json()["code"].as_str().unwrap_or_default().to_string()
+    }
+}
+
+/// 1つのブラウザに見立てたテスト用のクライアント。Set-Cookieを覚えて次のリクエストで送る
+pub struct TestApp {
+    pub state: AppState,
+    router: Router,
+    cookies: Vec<(String, String)>,
+    bearer_token: Option<String>,
+    /// 毎回送るヘッダ。ブラウザのSPAと同じく、既定では同一オリジンからのfetchとして扱われる
+    headers: Vec<(&'static str, String)>,
+    mail_file: NamedTempFile,
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        Self::with_config(|_| {}).await
+    }
+
+    pub async fn with_config(configure: impl FnOnce(&mut AppConfig)) -> Self {
+        Self::build(configure, None).await
+    }
+
+    /// FileMailerの代わりに指定したMailerで送信する
+    pub async fn with_mailer(mailer: Arc<dyn Mailer>) -> Self {
+        Self::build(|_| {}, Some(mailer)).await
+    }
+
+    async fn build(
+        configure: impl FnOnce(&mut AppConfig),
+        mailer: Option<Arc<dyn Mailer>>,
+    ) -> Self {
+        let mut config = AppConfig::default();
+        // Keep hashing cheap; the parameters themselves are not under test
+        config.auth.password_hash.memory_kib = 64;
+        config.auth.password_hash.iterations = 1;
+        configure(&mut config);
+
+        // Every connection to an in-memory database is a new database, so keep exactly one
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::raw_sql(include_str!("../../schema.sql"))
+            .execute(&pool)
+            .await
+            .unwrap();
+        crate::migration::seed_roles(&pool).await.unwrap();
+
+        let mail_file = NamedTempFile::new().unwrap();
+        let mailer = mailer.unwrap_or_else(|| {
+            Arc::new(FileMailer::new(
+                &config.mail.from,
+                mail_file.path().to_str().unwrap(),
+            ))
+        });
+        let state = AppState {
+            pool,
+            config: config.clone(),
+            mailer,
+            webauthn: Arc::new(auth::passkey::build_webauthn(&config.auth.webauthn)),
+            http_client: reqwest::Client::new(),
+            password_hasher: PasswordHasher::new(&config.auth.password_hash),
+            csrf: CsrfProtection::new(&config),
+            cookies: CookieSettings::new(&config),
+        };
+
+        Self {
+            router: router(state.clone()),
+            state,
+            cookies: Vec::new(),
+            bearer_token: None,
+            headers: vec![("Sec-Fetch-Site", "same-origin".to_string())],
+            mail_file,
+        }
+    }
+
+    /// 現在のセッションのCSRFトークンを付けて送る
+    pub async fn request(
+        &mut self,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+    ) -> TestResponse {
+        let csrf_token = match method {
+            Method::GET => None,
+            _ => self.csrf_token().await,
+        };
+        self.request_with_csrf_token(method, uri, body, csrf_token.as_deref())
+            .await
+    }
+
+    pub async fn request_with_csrf_token(
+        &mut self,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+        csrf_token: Option<&str>,
+    ) -> TestResponse {
+        let mut builder = Request::builder().method(&method).uri(uri);
+        if !self.cookies.is_empty() {
+            let cookie = self
+                .cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; ");
+            builder = builder.header(header::COOKIE, cookie);
+        }
+        for (name, value) in &self.headers {
+            builder = builder.header(*name, value);
+        }
+        if let Some(token) = &self.bearer_token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        if let Some(token) = csrf_token {
+            builder = builder.header("X-CSRF-Token", token);
+        }
+        let request = match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        }
+        .unwrap();
+
+        let response = self.router.call(request).await.unwrap();
+        let (parts, body) = response.into_parts();
+        for value in parts.headers.get_all(header::SET_COOKIE) {
+            self.store_cookie(value.to_str().unwrap());
+        }
+
+        TestResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body: axum::body::to_bytes(body, usize::MAX).await.unwrap(),
+        }
+    }
+
+    pub async fn get(&mut self, uri: &str) -> TestResponse {
+        self.request(Method::GET, uri, None).await
+    }
+
+    pub async fn post(&mut self, uri: &str, body: Value) -> TestResponse {
+        self.request(Method::POST, uri, Some(body)).await
+    }
+
+    fn store_cookie(&mut self, set_cookie: &str) {
+        let pair = set_cookie.split(';').next().unwrap_or_default();
+        let Some((name, value)) = pair.split_once('=') else {
+            return;
+        };
+        self.cookies.retain(|(n, _)| n != name);
+        let removed = set_cookie
+            .split(';')
+            .any(|attribute| attribute.trim().eq_ignore_ascii_case("Max-Age=0"));
+        if !removed {
+            self.cookies.push((name.to_string(), value.to_string()));
+        }
+    }
+
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_cookie(&mut self, name: &str, value: &str) {
+        self.cookies.retain(|(n, _)| n != name);
+        self.cookies.push((name.to_string(), value.to_string()));
+    }
+
+    /// ブラウザを閉じたのと同じように、すべてのCookieを捨てる
+    pub fn clear_cookies(&mut self) {
+        self.cookies.clear();
+    }
+
+    /// 以後のリクエストに付けるヘッダを設定する。`None` の場合は付けない
+    pub fn set_header(&mut self, name: &'static str, value: Option<&str>) {
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        if let Some(value) = value {
+            self.headers.push((name, value.to_string()));
+        }
+    }
+
+    /// 以後のリクエストに `Authorization: Bearer` でAPIトークンを付ける
+    pub fn set_bearer_token(&mut self, token: &str) {
+        self.bearer_token = Some(token.to_string());
+    }
+
+    pub fn session_id(&self) -> Option<String> {
+        self.cookie(self.state.cookies.session_name())
+            .map(str::to_string)
+    }
+
+    /// 現在のセッションのCSRFトークン（SPAがmetaタグから読むもの）
+    pub async fn csrf_token(&self) -> Option<String> {
+        let session_id = self.session_id()?;
+        sqlx::query_scalar("SELECT csrf_token FROM sessions WHERE uuid = ?")
+            .bind(session_id)
+            .fetch_optional(&self.state.pool)
+            .await
+            .unwrap()
+    }
+
+    /// 新規登録してログインした状態にする
+    pub async fn register(&mut self, email: &str) -> i64 {
+        let res = self
+            .post(
+                "/api/auth/register",
+                serde_json::json!({ "email": email, "password": PASSWORD }),
+            )
+            .await;
+        assert_eq!(
+            res.status,
+            StatusCode::OK,
+            "register failed: {:?}",
+            res.json()
+        );
+
+        sqlx::query_scalar("SELECT id FROM users WHERE email = ?")
+            .bind(email)
+            .fetch_one(&self.state.pool)
+            .await
+            .unwrap()
+    }
+
+    /// FileMailerが書き出したメールすべて
+    pub fn mails(&self) -> String {
+        std::fs::read_to_string(self.mail_file.path()).unwrap()
+    }
+
+    /// 最後に送られたメールのうち、`path` へのリンクに付いたトークン
+    pub fn link_token(&self, path: &str) -> String {
+        let mails = self.mails();
+        let marker = format!("{}?token=", path);
+        let start = mails
+            .rfind(&marker)
+            .unwrap_or_else(|| panic!("no link to {} in {:?}", path, mails))
+            + marker.len();
+        mails[start..]
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect()
+    }
+}
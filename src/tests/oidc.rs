@@ -0,0 +1,339 @@
+use super::TestApp;
+use crate::config::OidcProviderConfig;
+use axum::{
+    Form, Json, Router,
+    extract::State,
+    http::{StatusCode, header::LOCATION},
+    routing::{get, post},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, EncodingKey, Header, encode};
+use openssl::rsa::Rsa;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const PROVIDER: &str = "mock";
+const CLIENT_ID: &str = "test-client";
+const KEY_ID: &str = "test-key";
+
+#[derive(Default)]
+struct IssuedTokens {
+    /// 次のトークンリクエストに返すIDトークン
+    id_token: Option<String>,
+    /// トークンエンドポイントが受け取ったフォーム
+    requests: Vec<HashMap<String, String>>,
+}
+
+/// ディスカバリ・JWKS・トークンエンドポイントだけを持つIDプロバイダ
+struct MockIdp {
+    issuer: String,
+    encoding_key: EncodingKey,
+    tokens: Arc<Mutex<IssuedTokens>>,
+}
+
+impl MockIdp {
+    async fn start() -> Self {
+        let rsa = Rsa::generate(2048).unwrap();
+        let jwks = json!({
+            "keys": [{
+                "kty": "RSA",
+                "use": "sig",
+                "alg": "RS256",
+                "kid": KEY_ID,
+                "n": URL_SAFE_NO_PAD.encode(rsa.n().to_vec()),
+                "e": URL_SAFE_NO_PAD.encode(rsa.e().to_vec()),
+            }],
+        });
+        let encoding_key = EncodingKey::from_rsa_pem(&rsa.private_key_to_pem().unwrap()).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let discovery = json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "jwks_uri": format!("{}/jwks", issuer),
+        });
+        let tokens = Arc::new(Mutex::new(IssuedTokens::default()));
+
+        let router = Router::new()
+            .route(
+                "/.well-known/openid-configuration",
+                get(move || async move { Json(discovery) }),
+            )
+            .route("/jwks", get(move || async move { Json(jwks) }))
+            .route("/token", post(token))
+            .with_state(tokens.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        Self {
+            issuer,
+            encoding_key,
+            tokens,
+        }
+    }
+
+    fn provider_config(&self, auto_provision: bool) -> OidcProviderConfig {
+        OidcProviderConfig {
+            issuer: self.issuer.clone(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            discovery_url: None,
+            jwks_url: None,
+            redirect_url: None,
+            scopes: vec!["openid".to_string(), "email".to_string()],
+            auto_provision,
+        }
+    }
+
+    /// 次のトークンリクエストに返すIDトークンを署名して用意する。`claims` で既定のクレームを上書きする
+    fn issue(&self, claims: Value) {
+        let now = chrono::Utc::now().timestamp();
+        let mut token_claims = json!({
+            "iss": self.issuer,
+            "aud": CLIENT_ID,
+            "iat": now,
+            "exp": now + 300,
+        });
+        for (name, value) in claims.as_object().unwrap() {
+            token_claims[name] = value.clone();
+        }
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(KEY_ID.to_string());
+        let id_token = encode(&header, &token_claims, &self.encoding_key).unwrap();
+        self.tokens.lock().unwrap().id_token = Some(id_token);
+    }
+
+    fn last_token_request(&self) -> HashMap<String, String> {
+        self.tokens.lock().unwrap().requests.last().unwrap().clone()
+    }
+}
+
+async fn token(
+    State(tokens): State<Arc<Mutex<IssuedTokens>>>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<Value> {
+    let mut tokens = tokens.lock().unwrap();
+    tokens.requests.push(form);
+    Json(json!({
+        "access_token": "test-access-token",
+        "token_type": "Bearer",
+        "id_token": tokens.id_token.take().unwrap(),
+    }))
+}
+
+/// 認可エンドポイントに渡されたパラメータ
+struct Authorization {
+    state: String,
+    nonce: String,
+    code_challenge: String,
+}
+
+async fn setup(auto_provision: bool) -> (TestApp, MockIdp) {
+    let idp = MockIdp::start().await;
+    let provider = idp.provider_config(auto_provision);
+    let app = TestApp::with_config(|config| {
+        config.auth.oidc.insert(PROVIDER.to_string(), provider);
+    })
+    .await;
+    (app, idp)
+}
+
+async fn authorize(app: &mut TestApp) -> Authorization {
+    let res = app.get("/api/auth/oidc/mock/start").await;
+    assert_eq!(res.status, StatusCode::SEE_OTHER, "{:?}", res.json());
+
+    let location = reqwest::Url::parse(res.headers[LOCATION].to_str().unwrap()).unwrap();
+    let params: HashMap<String, String> = location.query_pairs().into_owned().collect();
+    assert_eq!(params["client_id"], CLIENT_ID);
+    Authorization {
+        state: params["state"].clone(),
+        nonce: params["nonce"].clone(),
+        code_challenge: params["code_challenge"].clone(),
+    }
+}
+
+async fn callback(app: &mut TestApp, state: &str) -> super::TestResponse {
+    app.get(&format!(
+        "/api/auth/oidc/mock/callback?code=test-code&state={}",
+        state
+    ))
+    .await
+}
+
+async fn identity_owner(app: &TestApp, subject: &str) -> Option<i64> {
+    sqlx::query_scalar("SELECT user_id FROM oidc_identities WHERE provider = ? AND subject = ?")
+        .bind(PROVIDER)
+        .bind(subject)
+        .fetch_optional(&app.state.pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn auto_provisions_a_user_for_a_new_identity() {
+    let (mut app, idp) = setup(true).await;
+
+    let authorization = authorize(&mut app).await;
+    idp.issue(json!({
+        "sub": "new-user",
+        "nonce": authorization.nonce,
+        "email": "New.User@Example.COM",
+        // Some providers send the claim as a string
+        "email_verified": "true",
+    }));
+    let res = callback(&mut app, &authorization.state).await;
+    assert_eq!(res.status, StatusCode::SEE_OTHER, "{:?}", res.json());
+    assert_eq!(res.headers[LOCATION], "/");
+    assert!(app.session_id().is_some());
+    assert!(app.cookie("oidc_state").is_none());
+
+    // The code is redeemed with the PKCE verifier matching the challenge sent to the IdP
+    let request = idp.last_token_request();
+    assert_eq!(request["code"], "test-code");
+    assert_eq!(
+        URL_SAFE_NO_PAD.encode(Sha256::digest(request["code_verifier"].as_bytes())),
+        authorization.code_challenge
+    );
+
+    let res = app.get("/api/auth/me").await;
+    assert_eq!(res.json()["email"], "New.User@example.com");
+    assert!(!res.json()["email_verified_at"].is_null());
+    assert_eq!(
+        identity_owner(&app, "new-user").await,
+        res.json()["id"].as_i64()
+    );
+
+    let registered: Option<String> = sqlx::query_scalar(
+        "SELECT details FROM auth_events WHERE user_id = ? AND event_type = 'register'",
+    )
+    .bind(res.json()["id"].as_i64())
+    .fetch_one(&app.state.pool)
+    .await
+    .unwrap();
+    assert_eq!(registered.as_deref(), Some("oidc:mock"));
+}
+
+#[tokio::test]
+async fn links_a_new_identity_to_the_logged_in_user() {
+    let (mut app, idp) = setup(false).await;
+    let user_id = app.register("linked@example.com").await;
+
+    let authorization = authorize(&mut app).await;
+    idp.issue(json!({
+        "sub": "linked-subject",
+        "nonce": authorization.nonce,
+        "email": "someone-else@example.com",
+    }));
+    let res = callback(&mut app, &authorization.state).await;
+    assert_eq!(res.status, StatusCode::SEE_OTHER, "{:?}", res.json());
+    assert_eq!(identity_owner(&app, "linked-subject").await, Some(user_id));
+
+    let linked: i64 = sqlx::query_scalar(
+        "SELECT count(*) FROM auth_events WHERE user_id = ? AND event_type = 'identity_linked'",
+    )
+    .bind(user_id)
+    .fetch_one(&app.state.pool)
+    .await
+    .unwrap();
+    assert_eq!(linked, 1);
+
+    // Once linked, the identity signs in to the same user without a session
+    app.clear_cookies();
+    let authorization = authorize(&mut app).await;
+    idp.issue(json!({ "sub": "linked-subject", "nonce": authorization.nonce }));
+    let res = callback(&mut app, &authorization.state).await;
+    assert_eq!(res.status, StatusCode::SEE_OTHER, "{:?}", res.json());
+    assert_eq!(app.get("/api/auth/me").await.json()["id"], user_id);
+}
+
+#[tokio::test]
+async fn rejects_an_unlinked_identity_without_auto_provisioning() {
+    let (mut app, idp) = setup(false).await;
+
+    let authorization = authorize(&mut app).await;
+    idp.issue(json!({
+        "sub": "stranger",
+        "nonce": authorization.nonce,
+        "email": "stranger@example.com",
+        "email_verified": true,
+    }));
+    let res = callback(&mut app, &authorization.state).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.code(), "invalid_credentials");
+    assert!(app.session_id().is_none());
+    assert_eq!(identity_owner(&app, "stranger").await, None);
+}
+
+#[tokio::test]
+async fn rejects_a_state_from_another_browser() {
+    let (mut app, _idp) = setup(true).await;
+
+    let authorization = authorize(&mut app).await;
+    // The state cookie stays in the browser that started the flow
+    app.clear_cookies();
+    let res = callback(&mut app, &authorization.state).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.code(), "oidc_failed");
+
+    // A state that was never issued is refused even when it matches the cookie
+    authorize(&mut app).await;
+    app.set_cookie("oidc_state", "forged");
+    let res = callback(&mut app, "forged").await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(res.code(), "invalid_token");
+    assert!(app.session_id().is_none());
+}
+
+#[tokio::test]
+async fn rejects_an_id_token_with_another_nonce() {
+    let (mut app, idp) = setup(true).await;
+
+    let authorization = authorize(&mut app).await;
+    idp.issue(json!({
+        "sub": "nonce-user",
+        "nonce": "replayed-nonce",
+        "email": "nonce@example.com",
+    }));
+    let res = callback(&mut app, &authorization.state).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.code(), "oidc_failed");
+    assert_eq!(identity_owner(&app, "nonce-user").await, None);
+}
+
+#[tokio::test]
+async fn rejects_an_id_token_from_another_issuer() {
+    let (mut app, idp) = setup(true).await;
+
+    let authorization = authorize(&mut app).await;
+    idp.issue(json!({
+        "iss": "https://attacker.example.com",
+        "sub": "issuer-user",
+        "nonce": authorization.nonce,
+        "email": "issuer@example.com",
+    }));
+    let res = callback(&mut app, &authorization.state).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.code(), "oidc_failed");
+    assert_eq!(identity_owner(&app, "issuer-user").await, None);
+}
+
+#[tokio::test]
+async fn rejects_an_id_token_for_another_client() {
+    let (mut app, idp) = setup(true).await;
+
+    let authorization = authorize(&mut app).await;
+    idp.issue(json!({
+        "aud": "another-client",
+        "sub": "audience-user",
+        "nonce": authorization.nonce,
+        "email": "audience@example.com",
+    }));
+    let res = callback(&mut app, &authorization.state).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.code(), "oidc_failed");
+    assert_eq!(identity_owner(&app, "audience-user").await, None);
+}
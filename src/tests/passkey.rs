@@ -0,0 +1,295 @@
+use super::TestApp;
+use axum::http::StatusCode;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use openssl::bn::BigNumContext;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::sign::Signer;
+use serde_cbor_2::Value as Cbor;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// authenticatorDataのフラグ
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// ES256の鍵を1つだけ持つソフトウェアの認証器
+struct SoftwareAuthenticator {
+    key: PKey<Private>,
+    credential_id: Vec<u8>,
+    counter: u32,
+    origin: String,
+    rp_id: String,
+}
+
+impl SoftwareAuthenticator {
+    fn new(app: &TestApp) -> Self {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        Self {
+            key: PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap(),
+            credential_id: rand::random::<[u8; 16]>().to_vec(),
+            counter: 0,
+            origin: app.state.config.auth.webauthn.rp_origin.clone(),
+            rp_id: app.state.config.auth.webauthn.rp_id.clone(),
+        }
+    }
+
+    fn client_data(&self, ceremony: &str, options: &Value) -> Vec<u8> {
+        json!({
+            "type": ceremony,
+            "challenge": options["publicKey"]["challenge"],
+            "origin": self.origin,
+            "crossOrigin": false,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn authenticator_data(&mut self, flags: u8) -> Vec<u8> {
+        self.counter += 1;
+        let mut data = Sha256::digest(self.rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.counter.to_be_bytes());
+        data
+    }
+
+    /// COSE_Key形式の公開鍵
+    fn cose_public_key(&self) -> Vec<u8> {
+        let ec_key = self.key.ec_key().unwrap();
+        let mut x = openssl::bn::BigNum::new().unwrap();
+        let mut y = openssl::bn::BigNum::new().unwrap();
+        ec_key
+            .public_key()
+            .affine_coordinates(
+                ec_key.group(),
+                &mut x,
+                &mut y,
+                &mut BigNumContext::new().unwrap(),
+            )
+            .unwrap();
+
+        let cose = BTreeMap::from([
+            (Cbor::Integer(1), Cbor::Integer(2)),
+            (Cbor::Integer(3), Cbor::Integer(-7)),
+            (Cbor::Integer(-1), Cbor::Integer(1)),
+            (Cbor::Integer(-2), Cbor::Bytes(x.to_vec_padded(32).unwrap())),
+            (Cbor::Integer(-3), Cbor::Bytes(y.to_vec_padded(32).unwrap())),
+        ]);
+        serde_cbor_2::to_vec(&Cbor::Map(cose)).unwrap()
+    }
+
+    /// navigator.credentials.create() の結果に相当するJSON（attestationはnone）
+    fn register(&mut self, options: &Value) -> Value {
+        let client_data = self.client_data("webauthn.create", options);
+
+        let mut auth_data = self.authenticator_data(
+            FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED_CREDENTIAL_DATA,
+        );
+        auth_data.extend_from_slice(&[0; 16]); // AAGUID
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        auth_data.extend_from_slice(&self.cose_public_key());
+
+        let attestation_object = BTreeMap::from([
+            (
+                Cbor::Text("fmt".to_string()),
+                Cbor::Text("none".to_string()),
+            ),
+            (
+                Cbor::Text("attStmt".to_string()),
+                Cbor::Map(BTreeMap::new()),
+            ),
+            (Cbor::Text("authData".to_string()), Cbor::Bytes(auth_data)),
+        ]);
+
+        json!({
+            "id": URL_SAFE_NO_PAD.encode(&self.credential_id),
+            "rawId": URL_SAFE_NO_PAD.encode(&self.credential_id),
+            "type": "public-key",
+            "response": {
+                "attestationObject": URL_SAFE_NO_PAD
+                    .encode(serde_cbor_2::to_vec(&Cbor::Map(attestation_object)).unwrap()),
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data),
+            },
+            "extensions": {},
+        })
+    }
+
+    /// navigator.credentials.get() の結果に相当するJSON
+    fn assert(&mut self, options: &Value) -> Value {
+        let client_data = self.client_data("webauthn.get", options);
+        let auth_data = self.authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED);
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key).unwrap();
+        signer.update(&auth_data).unwrap();
+        signer.update(&Sha256::digest(&client_data)).unwrap();
+        let signature = signer.sign_to_vec().unwrap();
+
+        json!({
+            "id": URL_SAFE_NO_PAD.encode(&self.credential_id),
+            "rawId": URL_SAFE_NO_PAD.encode(&self.credential_id),
+            "type": "public-key",
+            "response": {
+                "authenticatorData": URL_SAFE_NO_PAD.encode(auth_data),
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature),
+                "userHandle": null,
+            },
+            "extensions": {},
+        })
+    }
+}
+
+/// ログイン中のユーザーに認証器を登録する
+async fn register_passkey(app: &mut TestApp, authenticator: &mut SoftwareAuthenticator) {
+    let res = app
+        .post("/api/auth/passkeys/register/start", json!({}))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    let start = res.json();
+
+    let res = app
+        .post(
+            "/api/auth/passkeys/register/finish",
+            json!({
+                "challenge_token": start["challenge_token"],
+                "name": "test key",
+                "credential": authenticator.register(&start["options"]),
+            }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+}
+
+async fn start_login(app: &mut TestApp, email: &str) -> Value {
+    let res = app
+        .post("/api/auth/login/passkey/start", json!({ "email": email }))
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    res.json()
+}
+
+/// 登録してログアウトした状態のアプリと認証器
+async fn setup(email: &str) -> (TestApp, SoftwareAuthenticator) {
+    let mut app = TestApp::new().await;
+    let mut authenticator = SoftwareAuthenticator::new(&app);
+    app.register(email).await;
+    register_passkey(&mut app, &mut authenticator).await;
+    app.clear_cookies();
+    (app, authenticator)
+}
+
+#[tokio::test]
+async fn registers_a_passkey_and_logs_in_with_it() {
+    let (mut app, mut authenticator) = setup("passkey@example.com").await;
+
+    let start = start_login(&mut app, "passkey@example.com").await;
+    let res = app
+        .post(
+            "/api/auth/login/passkey/finish",
+            json!({
+                "challenge_token": start["challenge_token"],
+                "credential": authenticator.assert(&start["options"]),
+            }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert!(app.session_id().is_some());
+
+    let res = app.get("/api/auth/me").await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.json()["email"], "passkey@example.com");
+
+    // The sign counter reported by the authenticator is kept for clone detection
+    let counter: i64 = sqlx::query_scalar("SELECT sign_count FROM passkey_credentials")
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+    assert_eq!(counter, i64::from(authenticator.counter));
+}
+
+#[tokio::test]
+async fn rejects_a_replayed_assertion() {
+    let (mut app, mut authenticator) = setup("replay@example.com").await;
+
+    let start = start_login(&mut app, "replay@example.com").await;
+    let request = json!({
+        "challenge_token": start["challenge_token"],
+        "credential": authenticator.assert(&start["options"]),
+    });
+    let res = app
+        .post("/api/auth/login/passkey/finish", request.clone())
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    app.clear_cookies();
+
+    let res = app.post("/api/auth/login/passkey/finish", request).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    assert_eq!(res.code(), "invalid_token");
+    assert!(app.session_id().is_none());
+}
+
+#[tokio::test]
+async fn rejects_an_assertion_signed_for_another_challenge() {
+    let (mut app, mut authenticator) = setup("mismatch@example.com").await;
+
+    let first = start_login(&mut app, "mismatch@example.com").await;
+    let second = start_login(&mut app, "mismatch@example.com").await;
+    let res = app
+        .post(
+            "/api/auth/login/passkey/finish",
+            json!({
+                "challenge_token": second["challenge_token"],
+                "credential": authenticator.assert(&first["options"]),
+            }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.code(), "passkey_verification_failed");
+    assert!(app.session_id().is_none());
+
+    let failures: i64 = sqlx::query_scalar(
+        "SELECT count(*) FROM auth_events WHERE event_type = 'login_failure' AND details = 'passkey_verification_failed'",
+    )
+    .fetch_one(&app.state.pool)
+    .await
+    .unwrap();
+    assert_eq!(failures, 1);
+}
+
+#[tokio::test]
+async fn rejects_a_registration_for_another_challenge() {
+    let mut app = TestApp::new().await;
+    let mut authenticator = SoftwareAuthenticator::new(&app);
+    app.register("register-mismatch@example.com").await;
+
+    let first = app
+        .post("/api/auth/passkeys/register/start", json!({}))
+        .await
+        .json();
+    let second = app
+        .post("/api/auth/passkeys/register/start", json!({}))
+        .await
+        .json();
+    let res = app
+        .post(
+            "/api/auth/passkeys/register/finish",
+            json!({
+                "challenge_token": second["challenge_token"],
+                "credential": authenticator.register(&first["options"]),
+            }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.code(), "passkey_verification_failed");
+
+    let registered: i64 = sqlx::query_scalar("SELECT count(*) FROM passkey_credentials")
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+    assert_eq!(registered, 0);
+}
@@ -0,0 +1,78 @@
+use super::TestApp;
+use axum::http::StatusCode;
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use tempfile::TempDir;
+
+const BREACHED: &str = "Vq7#plume-orbit-Kestrel";
+/// 一覧にはあるが出現回数が0の詰め物の行
+const PADDING: &str = "Lantern-quarry-91-Mosaic";
+
+fn rules(res: &super::TestResponse) -> Vec<String> {
+    res.json()["violations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|violation| violation["rule"].as_str().unwrap().to_string())
+        .collect()
+}
+
+/// Have I Been Pwnedのrange形式で、パスワードの一覧を書き出す
+fn write_range(dir: &TempDir, password: &str, count: u32) {
+    let digest = hex::encode_upper(Sha1::digest(password.as_bytes()));
+    let (prefix, suffix) = digest.split_at(5);
+    let path = dir.path().join(format!("{}.txt", prefix));
+    let mut contents = std::fs::read_to_string(&path).unwrap_or_default();
+    contents.push_str(&format!("{}:{}\r\n", suffix, count));
+    std::fs::write(path, contents).unwrap();
+}
+
+async fn register(app: &mut TestApp, email: &str, password: &str) -> super::TestResponse {
+    app.clear_cookies();
+    app.post(
+        "/api/auth/register",
+        json!({ "email": email, "password": password }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn weak_passwords_are_rejected_with_every_violated_rule() {
+    let mut app = TestApp::new().await;
+
+    let res = register(&mut app, "weak@example.com", "abc").await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(res.code(), "password_policy_violation");
+    assert_eq!(rules(&res), vec!["min_length", "strength"]);
+
+    let res = register(&mut app, "weak@example.com", &"a".repeat(73)).await;
+    assert_eq!(rules(&res), vec!["max_bytes"]);
+
+    // The address itself makes a guessable password
+    let res = register(
+        &mut app,
+        "kestrel.orbit@example.com",
+        "kestrel.orbit@example.com",
+    )
+    .await;
+    assert_eq!(rules(&res), vec!["strength"]);
+}
+
+#[tokio::test]
+async fn breached_passwords_are_rejected_offline() {
+    let dir = TempDir::new().unwrap();
+    write_range(&dir, BREACHED, 42);
+    write_range(&dir, PADDING, 0);
+    let path = dir.path().to_str().unwrap().to_string();
+    let mut app = TestApp::with_config(|config| {
+        config.auth.password_policy.breached_passwords_dir = Some(path);
+    })
+    .await;
+
+    let res = register(&mut app, "breached@example.com", BREACHED).await;
+    assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(rules(&res), vec!["breached"]);
+
+    let res = register(&mut app, "padding@example.com", PADDING).await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+}
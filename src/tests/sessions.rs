@@ -0,0 +1,152 @@
+use super::{PASSWORD, TestApp};
+use axum::http::{Method, StatusCode, header::SET_COOKIE};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+
+async fn set_last_seen_at(app: &TestApp, last_seen_at: DateTime<Utc>) {
+    sqlx::query("UPDATE sessions SET last_seen_at = ? WHERE uuid = ?")
+        .bind(last_seen_at)
+        .bind(app.session_id().unwrap())
+        .execute(&app.state.pool)
+        .await
+        .unwrap();
+}
+
+async fn last_seen_at(app: &TestApp) -> DateTime<Utc> {
+    sqlx::query_scalar("SELECT last_seen_at FROM sessions WHERE uuid = ?")
+        .bind(app.session_id().unwrap())
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn activity_renews_the_session_until_it_idles_out() {
+    let mut app = TestApp::with_config(|config| {
+        config.auth.session.idle_timeout_minutes = 60;
+        config.auth.session.renewal_interval_minutes = 10;
+    })
+    .await;
+    app.register("sliding@example.com").await;
+
+    // Within the renewal interval nothing is written and no cookie is reissued
+    let seen = Utc::now() - Duration::minutes(5);
+    set_last_seen_at(&app, seen).await;
+    let res = app.get("/api/auth/me").await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert!(res.headers.get(SET_COOKIE).is_none());
+    assert_eq!(last_seen_at(&app).await, seen);
+
+    // Past the interval the session slides forward and the cookie follows it
+    set_last_seen_at(&app, Utc::now() - Duration::minutes(50)).await;
+    let res = app.get("/api/auth/me").await;
+    assert_eq!(res.status, StatusCode::OK);
+    let cookie = res.headers[SET_COOKIE].to_str().unwrap();
+    assert!(cookie.starts_with(app.state.cookies.session_name()));
+    assert!(cookie.contains("Max-Age=3600"), "{}", cookie);
+    assert!(Utc::now() - last_seen_at(&app).await < Duration::minutes(1));
+
+    set_last_seen_at(&app, Utc::now() - Duration::minutes(61)).await;
+    let res = app.get("/api/auth/me").await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn sessions_end_at_their_absolute_expiry_despite_activity() {
+    let mut app = TestApp::new().await;
+    app.register("absolute@example.com").await;
+
+    sqlx::query("UPDATE sessions SET expires_at = ? WHERE uuid = ?")
+        .bind(Utc::now() - Duration::seconds(1))
+        .bind(app.session_id().unwrap())
+        .execute(&app.state.pool)
+        .await
+        .unwrap();
+    let res = app.get("/api/auth/me").await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn revoking_a_session_signs_that_device_out() {
+    let mut app = TestApp::new().await;
+    app.register("devices@example.com").await;
+    let first_device = app.session_id().unwrap();
+    let session_name = app.state.cookies.session_name().to_string();
+
+    app.clear_cookies();
+    let res = app
+        .post(
+            "/api/auth/login",
+            json!({ "email": "devices@example.com", "password": PASSWORD }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+
+    let sessions = app.get("/api/auth/sessions").await.json();
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions.iter().all(|s| s.get("uuid").is_none()));
+    let other = sessions.iter().find(|s| s["current"] == false).unwrap()["id"]
+        .as_i64()
+        .unwrap();
+
+    let uri = format!("/api/auth/sessions/{}", other);
+    assert_eq!(
+        app.request(Method::DELETE, &uri, None).await.status,
+        StatusCode::OK
+    );
+    let res = app.request(Method::DELETE, &uri, None).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_eq!(res.code(), "session_not_found");
+    assert_eq!(
+        app.get("/api/auth/sessions")
+            .await
+            .json()
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let current_device = app.session_id().unwrap();
+    app.set_cookie(&session_name, &first_device);
+    assert_eq!(
+        app.get("/api/auth/me").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    app.set_cookie(&session_name, &current_device);
+    assert_eq!(app.get("/api/auth/me").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn sessions_of_other_users_cannot_be_revoked() {
+    let mut app = TestApp::new().await;
+    let victim_id = app.register("victim@example.com").await;
+    let victim_session: i64 = sqlx::query_scalar("SELECT id FROM sessions WHERE user_id = ?")
+        .bind(victim_id)
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+
+    app.clear_cookies();
+    app.register("attacker@example.com").await;
+    let res = app
+        .request(
+            Method::DELETE,
+            &format!("/api/auth/sessions/{}", victim_session),
+            None,
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    let res = app
+        .post("/api/auth/sessions/revoke-others", json!({}))
+        .await;
+    assert_eq!(res.json()["revoked"], 0);
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions WHERE user_id = ?")
+        .bind(victim_id)
+        .fetch_one(&app.state.pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 1);
+}
@@ -0,0 +1,121 @@
+use super::{PASSWORD, TestApp};
+use axum::http::StatusCode;
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+/// 認証アプリの代わりに、`offset` ステップ先のコードを計算する
+fn code_at(secret: &str, offset: u64) -> String {
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(secret.to_string()).to_bytes().unwrap(),
+        None,
+        String::new(),
+    )
+    .unwrap();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    totp.generate(now + offset * 30)
+}
+
+/// 二要素認証を有効にしたユーザーでログインし直し、チャレンジトークンを受け取る
+async fn start_login(app: &mut TestApp, email: &str) -> String {
+    app.clear_cookies();
+    let res = app
+        .post(
+            "/api/auth/login",
+            json!({ "email": email, "password": PASSWORD }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(res.json()["two_factor_required"], true);
+    assert!(app.session_id().is_none());
+    res.json()["challenge_token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn totp_codes_are_verified_and_never_accepted_twice() {
+    let mut app = TestApp::new().await;
+    app.register("totp@example.com").await;
+
+    let res = app.post("/api/auth/2fa/totp/enroll", json!({})).await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    let secret = res.json()["secret"].as_str().unwrap().to_string();
+    assert!(
+        res.json()["otpauth_uri"]
+            .as_str()
+            .unwrap()
+            .starts_with("otpauth://totp/")
+    );
+
+    let res = app
+        .post("/api/auth/2fa/totp/confirm", json!({ "code": "000000" }))
+        .await;
+    assert_eq!(res.code(), "invalid_two_factor_code");
+    let confirmation = code_at(&secret, 0);
+    let res = app
+        .post(
+            "/api/auth/2fa/totp/confirm",
+            json!({ "code": confirmation }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    let recovery_code = res.json()["recovery_codes"][0]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // The code used for confirmation cannot be replayed to sign in
+    let challenge = start_login(&mut app, "totp@example.com").await;
+    let res = app
+        .post(
+            "/api/auth/login/2fa",
+            json!({ "challenge_token": challenge, "code": confirmation }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(res.code(), "invalid_two_factor_code");
+
+    // The next step is still within the allowed clock drift
+    let next = code_at(&secret, 1);
+    let res = app
+        .post(
+            "/api/auth/login/2fa",
+            json!({ "challenge_token": challenge, "code": next }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert!(app.session_id().is_some());
+
+    let challenge = start_login(&mut app, "totp@example.com").await;
+    let res = app
+        .post(
+            "/api/auth/login/2fa",
+            json!({ "challenge_token": challenge, "code": next }),
+        )
+        .await;
+    assert_eq!(res.code(), "invalid_two_factor_code");
+
+    // Recovery codes work once, whatever their formatting
+    let res = app
+        .post(
+            "/api/auth/login/2fa",
+            json!({ "challenge_token": challenge, "recovery_code": recovery_code.to_uppercase().replace('-', "") }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+
+    let challenge = start_login(&mut app, "totp@example.com").await;
+    let res = app
+        .post(
+            "/api/auth/login/2fa",
+            json!({ "challenge_token": challenge, "recovery_code": recovery_code }),
+        )
+        .await;
+    assert_eq!(res.code(), "invalid_two_factor_code");
+}
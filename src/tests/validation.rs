@@ -0,0 +1,62 @@
+use super::{PASSWORD, TestApp};
+use axum::http::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn emails_are_normalized_before_they_are_stored_or_compared() {
+    let mut app = TestApp::new().await;
+
+    let res = app
+        .post(
+            "/api/auth/register",
+            json!({ "email": "  Mixed.Case@Bücher.Example ", "password": PASSWORD }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+    assert_eq!(
+        app.get("/api/auth/me").await.json()["email"],
+        "Mixed.Case@xn--bcher-kva.example"
+    );
+
+    // The same mailbox in another spelling is the same account
+    app.clear_cookies();
+    let res = app
+        .post(
+            "/api/auth/register",
+            json!({ "email": "mixed.case@XN--BCHER-KVA.example", "password": PASSWORD }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    assert_eq!(res.code(), "email_already_exists");
+
+    let res = app
+        .post(
+            "/api/auth/login",
+            json!({ "email": "MIXED.CASE@bücher.example", "password": PASSWORD }),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK, "{:?}", res.json());
+}
+
+#[tokio::test]
+async fn malformed_emails_are_reported_per_field() {
+    let mut app = TestApp::new().await;
+
+    for (email, code) in [
+        ("", "email_required"),
+        ("no-at-sign", "email_missing_at"),
+        ("@example.com", "email_local_part_missing"),
+        ("a b@example.com", "email_local_part_invalid"),
+        ("someone@localhost", "email_domain_invalid"),
+    ] {
+        let res = app
+            .post(
+                "/api/auth/register",
+                json!({ "email": email, "password": PASSWORD }),
+            )
+            .await;
+        assert_eq!(res.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", email);
+        assert_eq!(res.json()["errors"][0]["field"], "email");
+        assert_eq!(res.json()["errors"][0]["code"], code, "{}", email);
+    }
+}
@@ -0,0 +1,90 @@
+use thiserror::Error;
+
+/// ローカル部の最大長（RFC 5321）
+const MAX_LOCAL_PART_BYTES: usize = 64;
+/// アドレス全体の最大長（RFC 5321のパスの上限からブラケットを除いたもの）
+const MAX_ADDRESS_BYTES: usize = 254;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailError {
+    #[error("Email is required")]
+    Required,
+
+    #[error("Email must contain '@'")]
+    MissingAt,
+
+    #[error("Email must have a local part")]
+    LocalPartMissing,
+
+    #[error("Email local part is too long")]
+    LocalPartTooLong,
+
+    #[error("Email local part contains invalid characters")]
+    LocalPartInvalid,
+
+    #[error("Email domain is invalid")]
+    DomainInvalid,
+
+    #[error("Email is too long")]
+    TooLong,
+}
+
+impl EmailError {
+    /// 項目の不備として返すコード
+    pub fn code(&self) -> &'static str {
+        match self {
+            EmailError::Required => "email_required",
+            EmailError::MissingAt => "email_missing_at",
+            EmailError::LocalPartMissing => "email_local_part_missing",
+            EmailError::LocalPartTooLong => "email_local_part_too_long",
+            EmailError::LocalPartInvalid => "email_local_part_invalid",
+            EmailError::DomainInvalid => "email_domain_invalid",
+            EmailError::TooLong => "email_too_long",
+        }
+    }
+}
+
+/// メールアドレスを検証し、保存・比較に使う形に正規化する。
+/// 前後の空白を取り除き、ドメインは小文字のASCII（国際化ドメイン名はPunycode）に変換する。
+/// ローカル部の大文字・小文字は配送先のサーバ次第のため変更しない。
+pub fn normalize_email(input: &str) -> Result<String, EmailError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(EmailError::Required);
+    }
+
+    let (local, domain) = input.rsplit_once('@').ok_or(EmailError::MissingAt)?;
+
+    if local.is_empty() {
+        return Err(EmailError::LocalPartMissing);
+    }
+    if local.len() > MAX_LOCAL_PART_BYTES {
+        return Err(EmailError::LocalPartTooLong);
+    }
+    if local
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control() || c == '@')
+    {
+        return Err(EmailError::LocalPartInvalid);
+    }
+
+    let domain = idna::domain_to_ascii(domain).map_err(|_| EmailError::DomainInvalid)?;
+    if domain.is_empty()
+        || !domain.contains('.')
+        || domain.split('.').any(|label| {
+            label.is_empty()
+                || label.starts_with('-')
+                || label.ends_with('-')
+                || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+    {
+        return Err(EmailError::DomainInvalid);
+    }
+
+    let email = format!("{}@{}", local, domain);
+    if email.len() > MAX_ADDRESS_BYTES {
+        return Err(EmailError::TooLong);
+    }
+
+    Ok(email)
+}
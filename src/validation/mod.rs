@@ -0,0 +1,241 @@
+pub mod email;
+
+pub use email::{EmailError, normalize_email};
+
+use crate::error::AppError;
+use crate::i18n;
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{StatusCode, header::CONTENT_TYPE},
+    response::{IntoResponse, Response},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use std::ops::RangeInclusive;
+use thiserror::Error;
+
+/// 有効期限として指定できる日数（最長10年）
+pub const EXPIRES_IN_DAYS: RangeInclusive<i64> = 1..=3650;
+
+/// 入力の不備を項目ごとに表したもの
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    /// JSONでの項目のパス（例: `email`、`items[0].name`）。特定できない場合は空
+    pub field: String,
+    /// 不備の種類を表すコード（例: `field_required`、`email_domain_invalid`）
+    pub code: &'static str,
+    /// 表示言語に合わせたメッセージ
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: &'static str) -> Self {
+        Self {
+            field: field.into(),
+            code,
+            message: i18n::text(i18n::current(), code).to_string(),
+        }
+    }
+}
+
+/// 検証で見つかった不備の一覧
+#[derive(Debug, Default)]
+pub struct ValidationErrors {
+    errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn add(&mut self, field: impl Into<String>, code: &'static str) {
+        self.errors.push(FieldError::new(field, code));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// メールアドレスを正規化して書き換える。不正な場合は不備として記録する
+    pub fn email(&mut self, field: &str, value: &mut String) {
+        match normalize_email(value) {
+            Ok(email) => *value = email,
+            Err(e) => self.add(field, e.code()),
+        }
+    }
+
+    /// 値が範囲内か確認する。範囲外の場合は `code` の不備として記録する
+    pub fn range(
+        &mut self,
+        field: &str,
+        value: i64,
+        range: &RangeInclusive<i64>,
+        code: &'static str,
+    ) {
+        if !range.contains(&value) {
+            self.add(field, code);
+        }
+    }
+
+    pub fn into_errors(self) -> Vec<FieldError> {
+        self.errors
+    }
+}
+
+/// リクエストの入力を検証・正規化する。検証する項目がなければ空のimplでよい
+pub trait Validate {
+    /// 値を正規化し、不正な項目を `errors` に追加する
+    fn validate(&mut self, _errors: &mut ValidationErrors) {}
+}
+
+/// JSONのボディを読み込み、`Validate` で検証・正規化してから渡すextractor。
+/// 不正な場合はaxumの `Json` のようなプレーンテキストではなく、項目ごとの不備をJSONで返す。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+/// `ValidatedJson` や `extract` のextractorが受け付けなかったリクエスト
+#[derive(Error, Debug)]
+pub enum ValidationError {
+    #[error("Unsupported content type")]
+    UnsupportedMediaType,
+
+    #[error("Malformed request body")]
+    MalformedBody,
+
+    #[error("Validation failed")]
+    Invalid(Vec<FieldError>),
+
+    #[error("Malformed query string")]
+    InvalidQuery,
+
+    #[error("Malformed path parameter")]
+    InvalidPath,
+}
+
+impl ValidationError {
+    /// エラーコード・ステータス・メッセージの対応表
+    fn parts(&self) -> (&'static str, StatusCode, &'static str) {
+        match self {
+            ValidationError::UnsupportedMediaType => (
+                "unsupported_media_type",
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Expected request with `Content-Type: application/json`",
+            ),
+            ValidationError::MalformedBody => (
+                "malformed_body",
+                StatusCode::BAD_REQUEST,
+                "Request body is not valid JSON",
+            ),
+            ValidationError::Invalid(_) => (
+                "validation_failed",
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Validation failed",
+            ),
+            ValidationError::InvalidQuery => (
+                "invalid_query",
+                StatusCode::BAD_REQUEST,
+                "Query string is malformed",
+            ),
+            ValidationError::InvalidPath => (
+                "invalid_path",
+                StatusCode::BAD_REQUEST,
+                "Path parameter is malformed",
+            ),
+        }
+    }
+
+    /// クライアントが判別に使うエラーコード。変更しないこと
+    pub fn code(&self) -> &'static str {
+        self.parts().0
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.parts().1
+    }
+
+    /// クライアントに返すメッセージ
+    pub fn message(&self) -> &'static str {
+        self.parts().2
+    }
+}
+
+impl IntoResponse for ValidationError {
+    fn into_response(self) -> Response {
+        AppError::from(self).into_response()
+    }
+}
+
+fn is_json_content_type(req: &Request) -> bool {
+    let Some(content_type) = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+}
+
+/// serdeのエラーから項目のパスを取り出す。
+/// 必須項目の欠落はパスが親を指すため、メッセージに含まれる項目名を補う
+fn field_error(error: serde_path_to_error::Error<serde_json::Error>) -> FieldError {
+    let path = error.path().to_string();
+    let message = error.inner().to_string();
+
+    let missing = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split_once('`'))
+        .map(|(name, _)| name.to_string());
+    let code = if missing.is_some() {
+        "field_required"
+    } else {
+        "field_invalid"
+    };
+    let field = match (path.as_str(), missing) {
+        (".", Some(name)) => name,
+        (".", None) => String::new(),
+        (_, Some(name)) => format!("{}.{}", path, name),
+        (_, None) => path,
+    };
+
+    FieldError::new(field, code)
+}
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ValidationError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json_content_type(&req) {
+            return Err(ValidationError::UnsupportedMediaType);
+        }
+
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|_| ValidationError::MalformedBody)?;
+
+        let deserializer = &mut serde_json::Deserializer::from_slice(&bytes);
+        let mut value: T = serde_path_to_error::deserialize(deserializer).map_err(|e| {
+            if e.inner().is_data() {
+                ValidationError::Invalid(vec![field_error(e)])
+            } else {
+                ValidationError::MalformedBody
+            }
+        })?;
+
+        let mut errors = ValidationErrors::default();
+        value.validate(&mut errors);
+        if !errors.is_empty() {
+            return Err(ValidationError::Invalid(errors.into_errors()));
+        }
+
+        Ok(ValidatedJson(value))
+    }
+}